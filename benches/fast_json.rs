@@ -0,0 +1,69 @@
+use actix_util::extract::FastJson;
+use actix_util::JsonConfigBuilder;
+use actix_web::{test, web, FromRequest};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Telemetry {
+    device: String,
+    timestamp: u64,
+    metrics: Vec<Metric>,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Metric {
+    name: String,
+    value: f64,
+    tags: Vec<String>,
+}
+
+/// 约size字节的遥测数据
+fn payload(size: usize) -> Vec<u8> {
+    let metric = r#"{"name":"if_in_octets","value":12345.678,"tags":["eth0","uplink"]}"#;
+    let count = (size / (metric.len() + 1)).max(1);
+    format!(
+        r#"{{"device":"router-01","timestamp":1714521600,"metrics":[{}]}}"#,
+        vec![metric; count].join(",")
+    )
+    .into_bytes()
+}
+
+fn extract(c: &mut Criterion) {
+    let runtime = actix_web::rt::System::new();
+    let config = JsonConfigBuilder::new().limit(1024 * 1024);
+    let mut group = c.benchmark_group("json_extractor");
+    for size in [1024, 50 * 1024] {
+        let body = payload(size);
+        let request = || {
+            test::TestRequest::post()
+                .insert_header(("content-type", "application/json"))
+                .app_data(config.clone())
+                .app_data(config.clone().build())
+                .set_payload(body.clone())
+                .to_http_parts()
+        };
+        group.bench_with_input(BenchmarkId::new("web::Json", size), &size, |b, _| {
+            b.iter(|| {
+                let (req, mut payload) = request();
+                runtime
+                    .block_on(web::Json::<Telemetry>::from_request(&req, &mut payload))
+                    .unwrap()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("FastJson", size), &size, |b, _| {
+            b.iter(|| {
+                let (req, mut payload) = request();
+                runtime
+                    .block_on(FastJson::<Telemetry>::from_request(&req, &mut payload))
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, extract);
+criterion_main!(benches);
@@ -0,0 +1,170 @@
+use super::define::ExtraDescError;
+use super::err::{render_error, status_for_code, ErrorDetail, ErrorOutTpl};
+use actix_web::{http::StatusCode, HttpResponse};
+use serde::Serialize;
+
+/// 批量操作中成功的单项
+#[derive(Debug, Serialize)]
+pub struct BatchSucceeded<K, T> {
+    key: K,
+    item: T,
+}
+
+/// 批量操作中失败的单项，错误信息沿用ErrorDetail的结构
+#[derive(Debug, Serialize)]
+pub struct BatchFailed<K> {
+    key: K,
+    #[serde(flatten)]
+    detail: ErrorDetail,
+}
+
+#[derive(Debug, Serialize)]
+struct BatchOutTpl<'a, K, T> {
+    succeeded: &'a [BatchSucceeded<K, T>],
+    failed: &'a [BatchFailed<K>],
+}
+
+/// 批量操作结果，按插入顺序记录每一项的成功或失败
+///
+/// 全部成功返回200，全部失败返回标准错误结构，部分成功返回207
+///
+/// # Example
+///
+/// ```ignore
+/// let mut outcome = BatchOutcome::new();
+/// for id in ids {
+///     match delete_device(id) {
+///         Ok(dev) => outcome.push_ok(id, dev),
+///         Err(e) => outcome.push_err(id, e),
+///     }
+/// }
+/// outcome.into_response()
+/// ```
+#[derive(Debug)]
+pub struct BatchOutcome<T, K = usize> {
+    succeeded: Vec<BatchSucceeded<K, T>>,
+    failed: Vec<(K, ExtraDescError)>,
+}
+
+impl<T, K> Default for BatchOutcome<T, K> {
+    fn default() -> Self {
+        BatchOutcome {
+            succeeded: Vec::new(),
+            failed: Vec::new(),
+        }
+    }
+}
+
+impl<T: Serialize, K: Serialize> BatchOutcome<T, K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_ok(&mut self, key: K, item: T) {
+        self.succeeded.push(BatchSucceeded { key, item });
+    }
+
+    pub fn push_err(&mut self, key: K, err: ExtraDescError) {
+        self.failed.push((key, err));
+    }
+
+    pub fn is_all_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    pub fn into_response(self) -> HttpResponse {
+        if self.succeeded.is_empty() && !self.failed.is_empty() {
+            let code = &self.failed[0].1.err;
+            let status = status_for_code(code);
+            let details = self
+                .failed
+                .iter()
+                .map(|(_, err)| ErrorDetail::new_from_extra(err))
+                .collect();
+            return render_error(
+                status,
+                &ErrorOutTpl::new_from_details(status, details),
+                code.code(),
+            );
+        }
+
+        let status = if self.failed.is_empty() {
+            StatusCode::OK
+        } else {
+            StatusCode::MULTI_STATUS
+        };
+        let failed: Vec<BatchFailed<K>> = self
+            .failed
+            .into_iter()
+            .map(|(key, err)| BatchFailed {
+                key,
+                detail: ErrorDetail::new_from_extra(&err),
+            })
+            .collect();
+        HttpResponse::build(status).json(BatchOutTpl {
+            succeeded: &self.succeeded,
+            failed: &failed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compat::{async_test, body_bytes};
+    use crate::define::{DeviceNotFound, PermissionDenied};
+    use serde_json::{json, Value};
+
+    async fn render<T: Serialize>(outcome: BatchOutcome<T>) -> (StatusCode, Value) {
+        let res = outcome.into_response();
+        let status = res.status();
+        let body = body_bytes(res).await;
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[async_test]
+    async fn test_all_ok() {
+        let mut outcome = BatchOutcome::new();
+        outcome.push_ok(0, "a");
+        outcome.push_ok(1, "b");
+        let (status, body) = render(outcome).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            json!({"succeeded": [{"key": 0, "item": "a"}, {"key": 1, "item": "b"}], "failed": []})
+        );
+    }
+
+    #[async_test]
+    async fn test_all_fail() {
+        let mut outcome = BatchOutcome::<()>::new();
+        outcome.push_err(0, DeviceNotFound.from_desc("设备0不存在"));
+        outcome.push_err(1, PermissionDenied.from_desc("设备1无权限"));
+        let (status, body) = render(outcome).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["status"], 404);
+        assert_eq!(body["error"]["details"][0]["code"], 4004);
+        assert_eq!(body["error"]["details"][1]["code"], 1002);
+    }
+
+    #[async_test]
+    async fn test_mixed() {
+        let mut outcome = BatchOutcome::new();
+        outcome.push_err(2, DeviceNotFound.from_desc("设备2不存在"));
+        outcome.push_ok(0, 10);
+        outcome.push_err(1, PermissionDenied.from_desc("设备1无权限"));
+        outcome.push_ok(3, 13);
+        let (status, body) = render(outcome).await;
+        assert_eq!(status, StatusCode::MULTI_STATUS);
+        assert_eq!(
+            body,
+            json!({
+                "succeeded": [{"key": 0, "item": 10}, {"key": 3, "item": 13}],
+                "failed": [
+                    {"key": 2, "code": 4004, "err_type": "device not found", "desc": "设备2不存在"},
+                    {"key": 1, "code": 1002, "err_type": "permission denied", "desc": "设备1无权限"}
+                ]
+            })
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use super::define::{ExtraDescError, Result, UnexpectedErrorOccured};
+use super::middleware::current_locale;
+use actix_web::web;
+
+/// 在阻塞线程池中执行闭包，并把线程池错误和闭包的错误统一转换为ExtraDescError
+///
+/// 线程池任务被取消（如闭包panic）时返回UnexpectedErrorOccured
+///
+/// # Example
+///
+/// ```ignore
+/// let dev = blocking(move || repo::find(&mut conn, id)).await?;
+/// ```
+pub async fn blocking<T, E, F>(f: F) -> Result<T>
+where
+    F: FnOnce() -> std::result::Result<T, E> + Send + 'static,
+    T: Send + 'static,
+    E: Into<ExtraDescError> + Send + 'static,
+{
+    let locale = current_locale();
+    match web::block(f).await {
+        Ok(result) => result.map_err(Into::into),
+        Err(e) => Err(UnexpectedErrorOccured.from_desc(format!(
+            "{}: {}",
+            locale.pick("阻塞任务执行失败", "blocking task failed"),
+            e
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::result::Error as DieselError;
+
+    #[actix_web::test]
+    async fn test_success() {
+        let value = blocking(|| Ok::<_, DieselError>(42)).await.unwrap();
+        assert_eq!(value, 42);
+    }
+
+    #[actix_web::test]
+    async fn test_diesel_not_found() {
+        let err = blocking(|| Err::<(), _>(DieselError::NotFound))
+            .await
+            .unwrap_err();
+        assert_eq!(err.err.code(), 3003);
+    }
+
+    #[actix_web::test]
+    async fn test_canceled() {
+        let err = blocking(|| -> std::result::Result<(), DieselError> { panic!("pool gone") })
+            .await
+            .unwrap_err();
+        assert_eq!(err.err.code(), 5001);
+    }
+}
@@ -0,0 +1,388 @@
+use super::define::{Result, UnexpectedErrorOccured};
+use super::middleware::current_locale;
+use super::query::QueryOutput;
+use futures_util::future::{ready, Future};
+use futures_util::stream::{self, Stream, StreamExt};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// 自动翻页读取其它服务的分页接口
+///
+/// 某一页数量少于limit、或已读取到total时停止。为避免服务端的错误导致死循环，最多读取max_pages页，
+/// 读取max_pages页后仍未结束时返回错误，不会静默返回不完整的数据
+///
+/// # Example
+///
+/// ```ignore
+/// let fetcher = PageFetcher::new(100, |offset, limit| async move {
+///     let output: QueryOutput<Device> = client
+///         .get(format!("{}/devices?offset={}&limit={}", base, offset, limit))
+///         .send()
+///         .await?
+///         .json()
+///         .await?;
+///     Ok(output)
+/// });
+/// let devices = fetcher.collect_all(10_000).await?;
+/// ```
+pub struct PageFetcher<F> {
+    fetch: F,
+    limit: usize,
+    max_pages: usize,
+}
+
+impl<T, F, Fut> PageFetcher<F>
+where
+    F: Fn(usize, usize) -> Fut,
+    Fut: Future<Output = Result<QueryOutput<T>>>,
+{
+    /// limit为每页数量，为0时按1处理
+    pub fn new(limit: usize, fetch: F) -> Self {
+        PageFetcher {
+            fetch,
+            limit: limit.max(1),
+            max_pages: 1000,
+        }
+    }
+
+    /// 最多读取的页数，默认1000，超出时stream返回UnexpectedErrorOccured
+    pub fn max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = max_pages;
+        self
+    }
+
+    /// 逐项返回所有数据，请求出错或超出max_pages时返回错误并结束
+    pub fn stream<'a>(&'a self) -> impl Stream<Item = Result<T>> + 'a
+    where
+        T: 'a,
+    {
+        let pages = stream::unfold(Some((0usize, 0usize)), move |state| async move {
+            let (offset, pages) = state?;
+            if pages >= self.max_pages {
+                log::warn!("分页读取达到最大页数{}，停止读取", self.max_pages);
+                let desc = format!(
+                    "{}: {}",
+                    current_locale().pick(
+                        "分页读取达到最大页数，数据不完整",
+                        "max pages reached, data is incomplete"
+                    ),
+                    self.max_pages
+                );
+                let err = UnexpectedErrorOccured
+                    .from_desc(desc)
+                    .with_extra("max_pages", self.max_pages);
+                return Some((Err(err), None));
+            }
+            match (self.fetch)(offset, self.limit).await {
+                Ok(page) => {
+                    let next = offset + page.items.len();
+                    let done =
+                        page.items.len() < self.limit || (page.total > 0 && next >= page.total);
+                    let state = (!done).then_some((next, pages + 1));
+                    Some((Ok(page.items), state))
+                }
+                Err(e) => Some((Err(e), None)),
+            }
+        });
+        pages.flat_map(|page| match page {
+            Ok(items) => stream::iter(items.into_iter().map(Ok)).left_stream(),
+            Err(e) => stream::once(ready(Err(e))).right_stream(),
+        })
+    }
+
+    /// 读取所有数据，最多max_items项
+    pub async fn collect_all(&self, max_items: usize) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        let stream = self.stream().take(max_items);
+        futures_util::pin_mut!(stream);
+        while let Some(item) = stream.next().await {
+            items.push(item?);
+        }
+        Ok(items)
+    }
+}
+
+/// 把分页接口转换为逐项返回的流，当前页被消费完后才请求下一页
+///
+/// 某一页数量少于limit、或已读取到total时结束，请求出错时返回该错误并结束。
+/// 开启prefetch后收到一页时立即请求下一页，处理本页数据的同时等待下一页
+///
+/// # Example
+///
+/// ```ignore
+/// let devices = paged_stream(|offset, limit| fetch_devices(offset, limit), 500).prefetch(true);
+/// futures_util::pin_mut!(devices);
+/// while let Some(device) = devices.next().await {
+///     save_device(device?).await?;
+/// }
+/// ```
+pub fn paged_stream<T, F, Fut>(fetch: F, limit: u64) -> PagedStream<T, F, Fut>
+where
+    F: FnMut(u64, u64) -> Fut,
+    Fut: Future<Output = Result<QueryOutput<T>>>,
+{
+    PagedStream {
+        fetch,
+        limit: limit.max(1),
+        next_offset: 0,
+        prefetch: false,
+        done: false,
+        buffer: VecDeque::new(),
+        pending: None,
+        fetched: None,
+    }
+}
+
+/// paged_stream返回的流
+pub struct PagedStream<T, F, Fut> {
+    fetch: F,
+    limit: u64,
+    next_offset: u64,
+    prefetch: bool,
+    done: bool,
+    buffer: VecDeque<T>,
+    pending: Option<Pin<Box<Fut>>>,
+    fetched: Option<Result<QueryOutput<T>>>,
+}
+
+// 请求中的future已经Box::pin，其余字段不会被结构化地pin
+impl<T, F, Fut> Unpin for PagedStream<T, F, Fut> {}
+
+impl<T, F, Fut> PagedStream<T, F, Fut>
+where
+    F: FnMut(u64, u64) -> Fut,
+    Fut: Future<Output = Result<QueryOutput<T>>>,
+{
+    /// 是否预取下一页，默认false
+    pub fn prefetch(mut self, prefetch: bool) -> Self {
+        self.prefetch = prefetch;
+        self
+    }
+
+    fn request(&mut self) {
+        let fut = (self.fetch)(self.next_offset, self.limit);
+        self.next_offset = self.next_offset.saturating_add(self.limit);
+        self.pending = Some(Box::pin(fut));
+    }
+
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        match self.pending.as_mut().map(|fut| fut.as_mut().poll(cx)) {
+            Some(Poll::Ready(result)) => {
+                self.pending = None;
+                self.fetched = Some(result);
+                Poll::Ready(())
+            }
+            Some(Poll::Pending) => Poll::Pending,
+            None => Poll::Ready(()),
+        }
+    }
+}
+
+impl<T, F, Fut> Stream for PagedStream<T, F, Fut>
+where
+    F: FnMut(u64, u64) -> Fut,
+    Fut: Future<Output = Result<QueryOutput<T>>>,
+{
+    type Item = Result<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.fetched.is_none() {
+            // 预取的页在消费本页数据时推进，结果暂存到fetched
+            let _ = this.poll_pending(cx);
+        }
+        loop {
+            if let Some(item) = this.buffer.pop_front() {
+                return Poll::Ready(Some(Ok(item)));
+            }
+            match this.fetched.take() {
+                Some(Ok(page)) => {
+                    let received = this.next_offset - this.limit + page.items.len() as u64;
+                    this.done = (page.items.len() as u64) < this.limit
+                        || (page.total > 0 && received >= page.total as u64);
+                    this.buffer.extend(page.items);
+                    if this.prefetch && !this.done {
+                        this.request();
+                        let _ = this.poll_pending(cx);
+                    }
+                }
+                Some(Err(e)) => {
+                    this.done = true;
+                    this.pending = None;
+                    return Poll::Ready(Some(Err(e)));
+                }
+                None if this.pending.is_some() => {
+                    if this.poll_pending(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                }
+                None if this.done => return Poll::Ready(None),
+                None => this.request(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::define::DeviceNotFound;
+    use std::cell::RefCell;
+
+    /// 模拟total条数据的分页接口，记录每次请求的offset
+    fn server(
+        total: usize,
+        calls: &RefCell<Vec<usize>>,
+    ) -> impl Fn(usize, usize) -> futures_util::future::Ready<Result<QueryOutput<usize>>> + '_ {
+        move |offset, limit| {
+            calls.borrow_mut().push(offset);
+            ready(Ok(QueryOutput::paginate_vec(
+                (0..total).collect(),
+                offset,
+                limit,
+            )))
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_exact_multiple() {
+        let calls = RefCell::new(Vec::new());
+        let items = PageFetcher::new(5, server(10, &calls))
+            .collect_all(usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(items, (0..10).collect::<Vec<_>>());
+        assert_eq!(*calls.borrow(), vec![0, 5]);
+    }
+
+    #[actix_web::test]
+    async fn test_short_last_page() {
+        let calls = RefCell::new(Vec::new());
+        let fetcher = PageFetcher::new(5, server(12, &calls));
+        let items: Vec<usize> = fetcher.stream().map(|item| item.unwrap()).collect().await;
+        assert_eq!(items, (0..12).collect::<Vec<_>>());
+        assert_eq!(*calls.borrow(), vec![0, 5, 10]);
+
+        let items = fetcher.collect_all(7).await.unwrap();
+        assert_eq!(items, (0..7).collect::<Vec<_>>());
+    }
+
+    #[actix_web::test]
+    async fn test_max_pages() {
+        let calls = RefCell::new(Vec::new());
+        // total为0且每页都是满的，模拟不会结束的服务端
+        let fetcher = PageFetcher::new(2, |offset, limit| {
+            calls.borrow_mut().push(offset);
+            ready(Ok(QueryOutput::from(vec![offset; limit]).total(0)))
+        })
+        .max_pages(3);
+        let err = fetcher.collect_all(usize::MAX).await.unwrap_err();
+        assert_eq!(err.err, UnexpectedErrorOccured);
+        assert_eq!(err.extra.unwrap()["max_pages"], 3);
+        assert_eq!(*calls.borrow(), vec![0, 2, 4]);
+
+        // 读取到的数据先返回，最后一项是错误
+        let items: Vec<Result<usize>> = fetcher.stream().collect().await;
+        assert_eq!(items.len(), 7);
+        assert!(items[..6].iter().all(|item| item.is_ok()));
+        assert!(items[6].is_err());
+
+        // max_items在上限之内时不会读到上限
+        let items = fetcher.collect_all(6).await.unwrap();
+        assert_eq!(items.len(), 6);
+    }
+
+    #[actix_web::test]
+    async fn test_error() {
+        let fetcher = PageFetcher::new(2, |offset, _| {
+            ready(match offset {
+                0 => Ok(QueryOutput::from(vec![1, 2]).total(10)),
+                _ => Err(DeviceNotFound.from_desc("服务不可用")),
+            })
+        });
+        let err = fetcher.collect_all(usize::MAX).await.unwrap_err();
+        assert_eq!(err.desc, "服务不可用");
+    }
+
+    /// 模拟total条数据的分页接口，offset为fail_at时返回错误
+    fn paged_server(
+        total: u64,
+        fail_at: Option<u64>,
+        calls: &RefCell<Vec<u64>>,
+    ) -> impl FnMut(u64, u64) -> futures_util::future::Ready<Result<QueryOutput<u64>>> + '_ {
+        move |offset, limit| {
+            calls.borrow_mut().push(offset);
+            ready(if Some(offset) == fail_at {
+                Err(DeviceNotFound.from_desc("服务不可用"))
+            } else {
+                Ok(QueryOutput::paginate_vec(
+                    (0..total).collect(),
+                    offset as usize,
+                    limit as usize,
+                ))
+            })
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_paged_stream_lazy() {
+        let calls = RefCell::new(Vec::new());
+        let mut items = paged_stream(paged_server(7, None, &calls), 3);
+        assert!(calls.borrow().is_empty());
+
+        assert_eq!(items.next().await.unwrap().unwrap(), 0);
+        assert_eq!(*calls.borrow(), vec![0]);
+        assert_eq!(items.next().await.unwrap().unwrap(), 1);
+        assert_eq!(items.next().await.unwrap().unwrap(), 2);
+        // 本页消费完之前不会请求下一页
+        assert_eq!(*calls.borrow(), vec![0]);
+        assert_eq!(items.next().await.unwrap().unwrap(), 3);
+        assert_eq!(*calls.borrow(), vec![0, 3]);
+
+        let rest: Vec<u64> = items.map(|item| item.unwrap()).collect().await;
+        assert_eq!(rest, vec![4, 5, 6]);
+        assert_eq!(*calls.borrow(), vec![0, 3, 6]);
+    }
+
+    #[actix_web::test]
+    async fn test_paged_stream_error() {
+        let calls = RefCell::new(Vec::new());
+        let items: Vec<Result<u64>> = paged_stream(paged_server(10, Some(3), &calls), 3)
+            .collect()
+            .await;
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[2].as_ref().unwrap(), &2);
+        assert_eq!(items[3].as_ref().unwrap_err().desc, "服务不可用");
+        assert_eq!(*calls.borrow(), vec![0, 3]);
+    }
+
+    #[actix_web::test]
+    async fn test_paged_stream_prefetch() {
+        let calls = RefCell::new(Vec::new());
+        let mut items = paged_stream(paged_server(7, None, &calls), 3).prefetch(true);
+        assert_eq!(items.next().await.unwrap().unwrap(), 0);
+        // 收到第一页后立即请求第二页
+        assert_eq!(*calls.borrow(), vec![0, 3]);
+        assert_eq!(items.next().await.unwrap().unwrap(), 1);
+        assert_eq!(*calls.borrow(), vec![0, 3]);
+
+        let rest: Vec<u64> = items.map(|item| item.unwrap()).collect().await;
+        assert_eq!(rest, vec![2, 3, 4, 5, 6]);
+        // 最后一页不满，不再预取
+        assert_eq!(*calls.borrow(), vec![0, 3, 6]);
+
+        // 预取的页出错时，先返回本页剩余数据再返回错误
+        let calls = RefCell::new(Vec::new());
+        let items: Vec<Result<u64>> = paged_stream(paged_server(10, Some(3), &calls), 3)
+            .prefetch(true)
+            .collect()
+            .await;
+        let ok: Vec<u64> = items
+            .iter()
+            .filter_map(|item| item.as_ref().ok().copied())
+            .collect();
+        assert_eq!(ok, vec![0, 1, 2]);
+        assert!(items[3].is_err());
+    }
+}
@@ -0,0 +1,205 @@
+// actix-web 3和4之间有差异的接口，其余代码只通过这里的适配函数使用这些接口
+use crate::config::ContentTypePredicate;
+use actix_web::error::{JsonPayloadError, PayloadError};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::web::JsonConfig;
+use actix_web::HttpRequest;
+
+#[cfg(not(feature = "actix4"))]
+pub use actix_web::dev::HttpResponseBuilder;
+#[cfg(feature = "actix4")]
+pub(crate) use actix_web::mime::Mime;
+#[cfg(feature = "actix4")]
+pub use actix_web::HttpResponseBuilder;
+#[cfg(not(feature = "actix4"))]
+pub(crate) use mime::Mime;
+
+/// 设置响应头，覆盖同名的已有值（actix-web 4为insert_header，3为set_header）
+#[cfg(feature = "actix4")]
+pub(crate) fn insert_header(
+    builder: &mut HttpResponseBuilder,
+    name: HeaderName,
+    value: HeaderValue,
+) {
+    builder.insert_header((name, value));
+}
+
+#[cfg(not(feature = "actix4"))]
+pub(crate) fn insert_header(
+    builder: &mut HttpResponseBuilder,
+    name: HeaderName,
+    value: HeaderValue,
+) {
+    builder.set_header(name, value);
+}
+
+/// 为`$ty<T: Serialize>`实现Responder，渲染为output::json_response
+///
+/// actix-web 4的Responder直接返回响应，3返回响应的Future
+macro_rules! impl_json_responder {
+    ($ty:ident) => {
+        #[cfg(feature = "actix4")]
+        impl<T: serde::Serialize> actix_web::Responder for $ty<T> {
+            type Body = actix_web::body::BoxBody;
+
+            fn respond_to(self, _req: &actix_web::HttpRequest) -> actix_web::HttpResponse {
+                $crate::output::json_response(&self)
+            }
+        }
+
+        #[cfg(not(feature = "actix4"))]
+        impl<T: serde::Serialize> actix_web::Responder for $ty<T> {
+            type Error = actix_web::Error;
+            type Future = std::future::Ready<Result<actix_web::HttpResponse, actix_web::Error>>;
+
+            fn respond_to(self, _req: &actix_web::HttpRequest) -> Self::Future {
+                std::future::ready(Ok($crate::output::json_response(&self)))
+            }
+        }
+    };
+}
+pub(crate) use impl_json_responder;
+
+/// JsonConfig错误处理函数收到的JsonPayloadError的统一视图
+///
+/// actix-web 3的Overflow不带大小限制，也没有OverflowKnownLength，此时limit为None，length取自Content-Length
+pub(crate) enum JsonError<'a> {
+    Overflow {
+        limit: Option<usize>,
+        length: Option<usize>,
+    },
+    ContentType,
+    Deserialize(&'a serde_json::Error),
+    Other,
+}
+
+#[cfg(feature = "actix4")]
+pub(crate) fn json_error<'a>(err: &'a JsonPayloadError, _req: &HttpRequest) -> JsonError<'a> {
+    match err {
+        JsonPayloadError::OverflowKnownLength { length, limit } => JsonError::Overflow {
+            limit: Some(*limit),
+            length: Some(*length),
+        },
+        JsonPayloadError::Overflow { limit } => JsonError::Overflow {
+            limit: Some(*limit),
+            length: None,
+        },
+        JsonPayloadError::Payload(PayloadError::Overflow) => JsonError::Overflow {
+            limit: None,
+            length: None,
+        },
+        JsonPayloadError::ContentType => JsonError::ContentType,
+        JsonPayloadError::Deserialize(json_err) => JsonError::Deserialize(json_err),
+        _ => JsonError::Other,
+    }
+}
+
+#[cfg(not(feature = "actix4"))]
+pub(crate) fn json_error<'a>(err: &'a JsonPayloadError, req: &HttpRequest) -> JsonError<'a> {
+    match err {
+        JsonPayloadError::Overflow | JsonPayloadError::Payload(PayloadError::Overflow) => {
+            JsonError::Overflow {
+                limit: None,
+                length: req
+                    .headers()
+                    .get(actix_web::http::header::CONTENT_LENGTH)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.parse().ok()),
+            }
+        }
+        JsonPayloadError::ContentType => JsonError::ContentType,
+        JsonPayloadError::Deserialize(json_err) => JsonError::Deserialize(json_err),
+        JsonPayloadError::Payload(_) => JsonError::Other,
+    }
+}
+
+/// 设置json提取器接受的Content-Type
+#[cfg(feature = "actix4")]
+pub(crate) fn json_content_type(
+    config: JsonConfig,
+    required: bool,
+    predicate: Option<ContentTypePredicate>,
+) -> JsonConfig {
+    let config = config.content_type_required(required);
+    match predicate {
+        Some(predicate) => config.content_type(move |mime| predicate(mime)),
+        None => config,
+    }
+}
+
+/// actix-web 3没有content_type_required，不要求时接受任意Content-Type，但仍然拒绝缺少Content-Type的请求
+#[cfg(not(feature = "actix4"))]
+pub(crate) fn json_content_type(
+    config: JsonConfig,
+    required: bool,
+    predicate: Option<ContentTypePredicate>,
+) -> JsonConfig {
+    match (required, predicate) {
+        (false, _) => config.content_type(|_| true),
+        (true, Some(predicate)) => config.content_type(move |mime| predicate(mime)),
+        (true, None) => config,
+    }
+}
+
+/// 带Content-Type请求头和请求体的POST测试请求
+#[cfg(all(test, feature = "actix4"))]
+pub(crate) fn test_post(content_type: &'static str, body: &str) -> actix_web::test::TestRequest {
+    actix_web::test::TestRequest::post()
+        .insert_header((actix_web::http::header::CONTENT_TYPE, content_type))
+        .set_payload(body.to_string())
+}
+
+/// actix-web 3的set_payload不设置Content-Length，这里与真实请求一样带上
+#[cfg(all(test, not(feature = "actix4")))]
+pub(crate) fn test_post(content_type: &'static str, body: &str) -> actix_web::test::TestRequest {
+    actix_web::test::TestRequest::post()
+        .header(actix_web::http::header::CONTENT_TYPE, content_type)
+        .header(actix_web::http::header::CONTENT_LENGTH, body.len())
+        .set_payload(body.to_string())
+}
+
+// 异步测试使用的属性宏，actix-web 3没有提供，使用actix-rt 1的
+#[cfg(all(test, not(feature = "actix4")))]
+pub(crate) use actix_rt::test as async_test;
+#[cfg(all(test, feature = "actix4"))]
+pub(crate) use actix_web::test as async_test;
+
+/// 初始化测试服务并发送一个请求，actix-web 3的call_service需要服务的可变引用
+#[cfg(all(test, feature = "actix4"))]
+macro_rules! call_app {
+    ($app:expr, $req:expr) => {{
+        let app = actix_web::test::init_service($app).await;
+        actix_web::test::call_service(&app, $req).await
+    }};
+}
+
+#[cfg(all(test, not(feature = "actix4")))]
+macro_rules! call_app {
+    ($app:expr, $req:expr) => {{
+        let mut app = actix_web::test::init_service($app).await;
+        actix_web::test::call_service(&mut app, $req).await
+    }};
+}
+#[cfg(test)]
+pub(crate) use call_app;
+
+/// 读取完整的响应体
+#[cfg(all(test, feature = "actix4"))]
+pub(crate) async fn body_bytes(res: actix_web::HttpResponse) -> Vec<u8> {
+    actix_web::body::to_bytes(res.into_body())
+        .await
+        .unwrap()
+        .to_vec()
+}
+
+#[cfg(all(test, not(feature = "actix4")))]
+pub(crate) async fn body_bytes(mut res: actix_web::HttpResponse) -> Vec<u8> {
+    use futures_util::StreamExt;
+
+    let mut body = res.take_body();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.next().await {
+        bytes.extend_from_slice(&chunk.unwrap());
+    }
+    bytes
+}
@@ -0,0 +1,955 @@
+use super::define::{
+    default_locale, Error as StdError, ExtraDescError, InvalidInput, InvalidMessageData, Locale,
+    PayloadTooLarge, UnsupportedContentType,
+};
+use super::err::Error;
+use crate::compat::{self, JsonError, Mime};
+#[cfg(feature = "actix4")]
+use crate::extract::PayloadLimit;
+#[cfg(feature = "actix4")]
+use crate::middleware::captured_body;
+use crate::middleware::{explicit_request_locale, request_locale, with_locale};
+use actix_web::error::{InternalError, JsonPayloadError, PathError, QueryPayloadError};
+#[cfg(feature = "actix4")]
+use actix_web::error::{PayloadError, UrlencodedError};
+use actix_web::http::{header, StatusCode};
+#[cfg(feature = "actix4")]
+use actix_web::web::{FormConfig, PayloadConfig, ServiceConfig};
+use actix_web::web::{JsonConfig, PathConfig, QueryConfig};
+use actix_web::{HttpRequest, ResponseError};
+use std::fmt::{Debug, Display};
+use std::sync::Arc;
+
+pub(crate) type ContentTypePredicate = Arc<dyn Fn(Mime) -> bool + Send + Sync>;
+#[cfg(feature = "actix4")]
+type Redactor = Arc<dyn Fn(String) -> String + Send + Sync>;
+
+/// 将提取器的错误渲染为标准错误结构，同时保留原始错误；显式指定了语言时按该语言渲染
+pub(crate) fn extractor_error<E>(
+    cause: E,
+    status: StatusCode,
+    detail: ExtraDescError,
+    locale: Option<Locale>,
+) -> actix_web::Error
+where
+    E: Debug + Display + 'static,
+{
+    let render = || Error::new(status).err(detail).error_response();
+    let response = match locale {
+        Some(locale) => with_locale(locale, render),
+        None => render(),
+    };
+    InternalError::from_response(cause, response).into()
+}
+
+pub(crate) fn payload_too_large(
+    locale: Locale,
+    limit: usize,
+    length: Option<usize>,
+) -> ExtraDescError {
+    let desc = match (locale, length) {
+        (Locale::Zh, Some(length)) => {
+            format!("请求数据过大，限制{}字节，实际{}字节", limit, length)
+        }
+        (Locale::Zh, None) => format!("请求数据过大，限制{}字节", limit),
+        (Locale::En, Some(length)) => format!(
+            "payload too large: limit {} bytes, received {} bytes",
+            limit, length
+        ),
+        (Locale::En, None) => format!("payload too large: limit {} bytes", limit),
+    };
+    PayloadTooLarge.from_desc(desc)
+}
+
+pub(crate) fn unsupported_content_type(locale: Locale, req: &HttpRequest) -> ExtraDescError {
+    let received = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_else(|| locale.pick("无", "none"));
+    let desc = format!(
+        "{}: {}",
+        locale.pick("不支持的Content-Type", "unsupported content type"),
+        received
+    );
+    UnsupportedContentType.from_desc(desc)
+}
+
+/// 解析serde的unknown variant错误，返回收到的值和允许的取值
+fn parse_unknown_variant(message: &str) -> Option<(&str, Vec<&str>)> {
+    let rest = message.split_once("unknown variant `")?.1;
+    let (received, expected) = rest.split_once("`, expected ")?;
+    let allowed = expected.split('`').skip(1).step_by(2).collect::<Vec<_>>();
+    Some((received, allowed))
+}
+
+/// 反序列化失败时的错误详情，枚举取值错误时在desc中列出允许的取值
+pub(crate) fn deserialize_error(
+    code: StdError,
+    locale: Locale,
+    prefix: &str,
+    message: &str,
+) -> ExtraDescError {
+    match parse_unknown_variant(message) {
+        Some((received, allowed)) => {
+            let desc = format!(
+                "{}: {}`{}`{}: {}",
+                prefix,
+                locale.pick("取值", "invalid value "),
+                received,
+                locale.pick("无效，可选值", ", allowed values"),
+                allowed.join(", ")
+            );
+            code.from_desc(desc)
+                .with_extra("received", received)
+                .with_extra("allowed", allowed)
+        }
+        None => code.from_desc(format!("{}: {}", prefix, message)),
+    }
+}
+
+/// JsonConfig构建器，可以按路由设置大小限制、语言和错误码
+///
+/// # Example
+///
+/// ```ignore
+/// App::new().service(
+///     web::resource("/login")
+///         .app_data(JsonConfigBuilder::new().limit(4 * 1024).build())
+///         .route(web::post().to(login)),
+/// )
+/// ```
+#[derive(Clone)]
+pub struct JsonConfigBuilder {
+    limit: usize,
+    content_type_required: bool,
+    content_type: Option<ContentTypePredicate>,
+    locale: Option<Locale>,
+    error_code: StdError,
+    #[cfg(feature = "actix4")]
+    echo_body: Option<usize>,
+    #[cfg(feature = "actix4")]
+    redact: Option<Redactor>,
+}
+
+impl Default for JsonConfigBuilder {
+    fn default() -> Self {
+        JsonConfigBuilder {
+            limit: 1024 * 1024 * 1000,
+            content_type_required: true,
+            content_type: None,
+            locale: None,
+            error_code: InvalidMessageData,
+            #[cfg(feature = "actix4")]
+            echo_body: None,
+            #[cfg(feature = "actix4")]
+            redact: None,
+        }
+    }
+}
+
+impl JsonConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn content_type_required(mut self, required: bool) -> Self {
+        self.content_type_required = required;
+        self
+    }
+
+    /// 自定义允许的Content-Type
+    pub fn content_type<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(Mime) -> bool + Send + Sync + 'static,
+    {
+        self.content_type = Some(Arc::new(predicate));
+        self
+    }
+
+    /// 不设置时使用全局默认语言
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.locale = Some(locale);
+        self
+    }
+
+    pub fn error_code(mut self, code: StdError) -> Self {
+        self.error_code = code;
+        self
+    }
+
+    /// json解析失败时在extra.body_preview中返回请求体的前max_bytes字节，默认关闭
+    ///
+    /// 请求体由middleware::CaptureBody记录，没有安装该中间件时不返回预览
+    #[cfg(feature = "actix4")]
+    pub fn echo_body(mut self, max_bytes: usize) -> Self {
+        self.echo_body = Some(max_bytes);
+        self
+    }
+
+    /// 返回请求体预览前对其脱敏，如去掉password等字段的值
+    #[cfg(feature = "actix4")]
+    pub fn redact<F>(mut self, redact: F) -> Self
+    where
+        F: Fn(String) -> String + Send + Sync + 'static,
+    {
+        self.redact = Some(Arc::new(redact));
+        self
+    }
+
+    pub fn build(self) -> JsonConfig {
+        let config = compat::json_content_type(
+            JsonConfig::default().limit(self.limit),
+            self.content_type_required,
+            self.content_type.clone(),
+        );
+        config.error_handler(move |err, req| self.handle_error(err, req))
+    }
+
+    /// 请求体大小限制，FastJson和JsonOrForm使用
+    #[cfg(feature = "actix4")]
+    pub(crate) fn body_limit(&self) -> usize {
+        self.limit
+    }
+
+    /// 按与web::Json相同的规则检查Content-Type
+    #[cfg(feature = "simd")]
+    pub(crate) fn accepts_content_type(&self, req: &HttpRequest) -> bool {
+        use actix_web::HttpMessage;
+        if !self.content_type_required {
+            return true;
+        }
+        match req.mime_type() {
+            Ok(Some(mime)) => {
+                mime.subtype() == actix_web::mime::JSON
+                    || mime.suffix() == Some(actix_web::mime::JSON)
+                    || self
+                        .content_type
+                        .as_ref()
+                        .is_some_and(|predicate| predicate(mime))
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn handle_error(
+        &self,
+        err: JsonPayloadError,
+        req: &HttpRequest,
+    ) -> actix_web::Error {
+        let explicit = self.locale.or_else(|| explicit_request_locale(req));
+        let locale = explicit.unwrap_or_else(default_locale);
+        let (status, detail) = match compat::json_error(&err, req) {
+            JsonError::Overflow { limit, length } => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                payload_too_large(locale, limit.unwrap_or(self.limit), length),
+            ),
+            JsonError::ContentType => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                unsupported_content_type(locale, req),
+            ),
+            JsonError::Deserialize(json_err) => {
+                #[allow(unused_mut)]
+                let mut detail = deserialize_error(
+                    self.error_code.clone(),
+                    locale,
+                    locale.pick("json解析错误", "json parse error"),
+                    &json_err.to_string(),
+                );
+                #[cfg(feature = "actix4")]
+                if let Some((preview, truncated)) = self.body_preview(req) {
+                    detail = detail.with_extra("body_preview", preview);
+                    if truncated {
+                        detail = detail.with_extra("body_truncated", true);
+                    }
+                }
+                (StatusCode::BAD_REQUEST, detail)
+            }
+            JsonError::Other => {
+                let desc = format!(
+                    "{}: {}",
+                    locale.pick("请求数据读取失败", "failed to read payload"),
+                    err
+                );
+                (
+                    StatusCode::BAD_REQUEST,
+                    self.error_code.clone().from_desc(desc),
+                )
+            }
+        };
+        extractor_error(err, status, detail, explicit)
+    }
+
+    #[cfg(feature = "actix4")]
+    fn body_preview(&self, req: &HttpRequest) -> Option<(String, bool)> {
+        let max_bytes = self.echo_body?;
+        let (body, mut truncated) = captured_body(req)?;
+        let mut preview = String::from_utf8_lossy(&body).into_owned();
+        if let Some(redact) = &self.redact {
+            preview = redact(preview);
+        }
+        if preview.len() > max_bytes {
+            let mut end = max_bytes;
+            while !preview.is_char_boundary(end) {
+                end -= 1;
+            }
+            preview.truncate(end);
+            truncated = true;
+        }
+        Some((preview, truncated))
+    }
+}
+
+pub fn get_default_jsonconfig() -> JsonConfig {
+    JsonConfigBuilder::default().build()
+}
+
+/// 查询参数解析失败时返回标准错误结构
+///
+/// # Example
+///
+/// ```ignore
+/// App::new().app_data(get_default_queryconfig())
+/// ```
+pub fn get_default_queryconfig() -> QueryConfig {
+    QueryConfig::default().error_handler(|err, req| {
+        let locale = request_locale(req);
+        let reason = match &err {
+            QueryPayloadError::Deserialize(de_err) => de_err.to_string(),
+            // actix-web 3中只有Deserialize一种错误
+            #[allow(unreachable_patterns)]
+            other => other.to_string(),
+        };
+        let detail = deserialize_error(
+            InvalidInput,
+            locale,
+            locale.pick("查询参数错误", "invalid query parameters"),
+            &reason,
+        );
+        extractor_error(
+            err,
+            StatusCode::BAD_REQUEST,
+            detail,
+            explicit_request_locale(req),
+        )
+    })
+}
+
+/// 路径参数解析失败时返回标准错误结构，desc中包含出错的路径段和期望的类型
+pub fn get_default_pathconfig() -> PathConfig {
+    PathConfig::default().error_handler(|err, req| {
+        let locale = request_locale(req);
+        let reason = match &err {
+            PathError::Deserialize(de_err) => de_err.to_string(),
+            // actix-web 3中只有Deserialize一种错误
+            #[allow(unreachable_patterns)]
+            other => other.to_string(),
+        };
+        let desc = format!(
+            "{} {}: {}",
+            locale.pick("路径参数错误", "invalid path parameter"),
+            req.path(),
+            reason
+        );
+        extractor_error(
+            err,
+            StatusCode::BAD_REQUEST,
+            InvalidInput.from_desc(desc),
+            explicit_request_locale(req),
+        )
+    })
+}
+
+/// FormConfig构建器，错误处理与JsonConfigBuilder保持一致
+#[cfg(feature = "actix4")]
+#[derive(Debug, Clone)]
+pub struct FormConfigBuilder {
+    limit: usize,
+    locale: Option<Locale>,
+}
+
+#[cfg(feature = "actix4")]
+impl Default for FormConfigBuilder {
+    fn default() -> Self {
+        FormConfigBuilder {
+            limit: 16 * 1024,
+            locale: None,
+        }
+    }
+}
+
+#[cfg(feature = "actix4")]
+impl FormConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// 不设置时使用全局默认语言
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.locale = Some(locale);
+        self
+    }
+
+    pub fn build(self) -> FormConfig {
+        FormConfig::default()
+            .limit(self.limit)
+            .error_handler(move |err, req| self.handle_error(err, req))
+    }
+
+    fn handle_error(&self, err: UrlencodedError, req: &HttpRequest) -> actix_web::Error {
+        let explicit = self.locale.or_else(|| explicit_request_locale(req));
+        let locale = explicit.unwrap_or_else(default_locale);
+        let (status, detail) = match &err {
+            UrlencodedError::Overflow { size, limit } => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                payload_too_large(locale, *limit, Some(*size)),
+            ),
+            UrlencodedError::Payload(PayloadError::Overflow) => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                payload_too_large(locale, self.limit, None),
+            ),
+            UrlencodedError::ContentType => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                unsupported_content_type(locale, req),
+            ),
+            UrlencodedError::Parse(de_err) => (
+                StatusCode::BAD_REQUEST,
+                deserialize_error(
+                    InvalidInput,
+                    locale,
+                    locale.pick("表单解析错误", "form parse error"),
+                    &de_err.to_string(),
+                ),
+            ),
+            other => {
+                let desc = format!(
+                    "{}: {}",
+                    locale.pick("请求数据读取失败", "failed to read payload"),
+                    other
+                );
+                (StatusCode::BAD_REQUEST, InvalidInput.from_desc(desc))
+            }
+        };
+        extractor_error(err, status, detail, explicit)
+    }
+}
+
+#[cfg(feature = "actix4")]
+pub fn get_default_formconfig() -> FormConfig {
+    FormConfigBuilder::default().build()
+}
+
+/// web::Bytes和String提取器的大小限制
+///
+/// actix的PayloadConfig不支持自定义错误处理，需要标准错误结构时使用
+/// [`LimitedBytes`](crate::extract::LimitedBytes)和[`LimitedString`](crate::extract::LimitedString)，
+/// 并通过[`PayloadLimit`](crate::extract::PayloadLimit)设置相同的限制
+#[cfg(feature = "actix4")]
+pub fn get_default_payloadconfig(limit: usize) -> PayloadConfig {
+    PayloadConfig::new(limit)
+}
+
+/// 一次性注册所有提取器的默认配置，可以单独覆盖某一项
+///
+/// # Example
+///
+/// ```ignore
+/// App::new().configure(|cfg| DefaultConfigs::new().json_limit(4 * 1024).apply(cfg))
+/// ```
+#[cfg(feature = "actix4")]
+#[derive(Clone)]
+pub struct DefaultConfigs {
+    json: JsonConfigBuilder,
+    form: FormConfigBuilder,
+    payload_limit: usize,
+}
+
+#[cfg(feature = "actix4")]
+impl Default for DefaultConfigs {
+    fn default() -> Self {
+        DefaultConfigs {
+            json: JsonConfigBuilder::default(),
+            form: FormConfigBuilder::default(),
+            payload_limit: PayloadLimit::default().0,
+        }
+    }
+}
+
+#[cfg(feature = "actix4")]
+impl DefaultConfigs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn json(mut self, json: JsonConfigBuilder) -> Self {
+        self.json = json;
+        self
+    }
+
+    pub fn json_limit(mut self, limit: usize) -> Self {
+        self.json = self.json.limit(limit);
+        self
+    }
+
+    pub fn form(mut self, form: FormConfigBuilder) -> Self {
+        self.form = form;
+        self
+    }
+
+    /// 同时作用于web::Bytes/String和LimitedBytes/LimitedString
+    pub fn payload_limit(mut self, limit: usize) -> Self {
+        self.payload_limit = limit;
+        self
+    }
+
+    /// 同时注册JsonConfigBuilder，FastJson与web::Json使用相同的配置
+    pub fn apply(self, cfg: &mut ServiceConfig) {
+        cfg.app_data(self.json.clone())
+            .app_data(self.json.build())
+            .app_data(get_default_queryconfig())
+            .app_data(get_default_pathconfig())
+            .app_data(self.form.build())
+            .app_data(get_default_payloadconfig(self.payload_limit))
+            .app_data(PayloadLimit(self.payload_limit));
+    }
+}
+
+/// 注册所有提取器的默认配置
+///
+/// # Example
+///
+/// ```ignore
+/// App::new().configure(actix_util::register_default_configs)
+/// ```
+#[cfg(feature = "actix4")]
+pub fn register_default_configs(cfg: &mut ServiceConfig) {
+    DefaultConfigs::default().apply(cfg);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compat::{async_test, call_app, test_post};
+    use actix_web::{test, web, App};
+    use serde_json::Value;
+
+    #[derive(Deserialize)]
+    struct Device {
+        name: String,
+    }
+
+    #[derive(Deserialize)]
+    struct ListParams {
+        limit: usize,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    #[allow(dead_code)]
+    enum Status {
+        Online,
+        Offline,
+        Unknown,
+    }
+
+    #[derive(Deserialize)]
+    struct StatusParams {
+        #[allow(dead_code)]
+        status: Status,
+    }
+
+    async fn by_status(_: web::Query<StatusParams>) -> &'static str {
+        "ok"
+    }
+
+    async fn update_status(_: web::Json<StatusParams>) -> &'static str {
+        "ok"
+    }
+
+    async fn list(params: web::Query<ListParams>) -> String {
+        params.limit.to_string()
+    }
+
+    async fn detail(id: web::Path<u64>) -> String {
+        id.to_string()
+    }
+
+    #[cfg(feature = "actix4")]
+    async fn submit(device: web::Form<Device>) -> String {
+        device.into_inner().name
+    }
+
+    #[cfg(feature = "actix4")]
+    async fn post_form(config: FormConfig, body: &str) -> (StatusCode, Value) {
+        let app = test::init_service(
+            App::new()
+                .app_data(config)
+                .route("/", web::post().to(submit)),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/")
+            .insert_header(("content-type", "application/x-www-form-urlencoded"))
+            .set_payload(body.to_string())
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let status = res.status();
+        (status, test::read_body_json(res).await)
+    }
+
+    async fn create(device: web::Json<Device>) -> String {
+        device.into_inner().name
+    }
+
+    async fn post(config: JsonConfig, body: &str) -> (StatusCode, Value) {
+        let req = test_post("application/json", body).uri("/").to_request();
+        let res = call_app!(
+            App::new()
+                .app_data(config)
+                .route("/", web::post().to(create)),
+            req
+        );
+        let status = res.status();
+        (status, test::read_body_json(res).await)
+    }
+
+    /// 安装CaptureBody后提交json，用于请求体预览
+    #[cfg(feature = "actix4")]
+    async fn post_captured(config: JsonConfig, body: &str) -> (StatusCode, Value) {
+        let req = test_post("application/json", body).uri("/").to_request();
+        let res = call_app!(
+            App::new()
+                .wrap(crate::middleware::CaptureBody::new())
+                .app_data(config)
+                .route("/", web::post().to(create)),
+            req
+        );
+        let status = res.status();
+        (status, test::read_body_json(res).await)
+    }
+
+    #[async_test]
+    async fn test_deserialize_error() {
+        let (status, body) = post(get_default_jsonconfig(), "{\"name\": 1}").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["status"], 400);
+        let detail = &body["error"]["details"][0];
+        assert_eq!(detail["code"], 2006);
+        assert_eq!(
+            detail["desc"],
+            "json解析错误: invalid type: integer `1`, expected a string at line 1 column 10"
+        );
+    }
+
+    #[async_test]
+    async fn test_overflow() {
+        let config = JsonConfigBuilder::new().limit(8).locale(Locale::Zh).build();
+        let (status, body) = post(config, "{\"name\": \"router-01\"}").await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body["error"]["status"], 413);
+        let detail = &body["error"]["details"][0];
+        assert_eq!(detail["code"], 2009);
+        assert_eq!(detail["desc"], "请求数据过大，限制8字节，实际21字节");
+    }
+
+    #[async_test]
+    async fn test_content_type() {
+        let req = test_post("text/plain", "{\"name\": \"router-01\"}")
+            .uri("/")
+            .to_request();
+        let res = call_app!(
+            App::new()
+                .app_data(JsonConfigBuilder::new().locale(Locale::En).build())
+                .route("/", web::post().to(create)),
+            req
+        );
+        assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["error"]["status"], 415);
+        assert_eq!(body["error"]["details"][0]["code"], 2010);
+        assert_eq!(
+            body["error"]["details"][0]["desc"],
+            "unsupported content type: text/plain"
+        );
+    }
+
+    #[async_test]
+    async fn test_content_type_not_required() {
+        let req = test_post("text/plain", "{\"name\": \"router-01\"}")
+            .uri("/")
+            .to_request();
+        let res = call_app!(
+            App::new()
+                .app_data(
+                    JsonConfigBuilder::new()
+                        .content_type_required(false)
+                        .build()
+                )
+                .route("/", web::post().to(create)),
+            req
+        );
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(test::read_body(res).await, "router-01");
+    }
+
+    #[async_test]
+    async fn test_error_code_and_locale() {
+        let config = JsonConfigBuilder::new()
+            .locale(Locale::En)
+            .error_code(InvalidInput)
+            .build();
+        let (status, body) = post(config, "not json").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let detail = &body["error"]["details"][0];
+        assert_eq!(detail["code"], 1012);
+        assert!(detail["desc"]
+            .as_str()
+            .unwrap()
+            .starts_with("json parse error: expected ident at line 1 column 2"));
+    }
+
+    #[async_test]
+    async fn test_query_config() {
+        let app = || {
+            App::new()
+                .app_data(get_default_queryconfig())
+                .route("/devices", web::get().to(list))
+        };
+        let req = test::TestRequest::get()
+            .uri("/devices?limit=abc")
+            .to_request();
+        let res = call_app!(app(), req);
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["error"]["status"], 400);
+        let detail = &body["error"]["details"][0];
+        assert_eq!(detail["code"], 1012);
+        assert_eq!(detail["err_type"], "invalid input parameter");
+        assert!(detail["desc"].as_str().unwrap().contains("invalid digit"));
+
+        let req = test::TestRequest::get()
+            .uri("/devices?limit=20")
+            .to_request();
+        let res = call_app!(app(), req);
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[async_test]
+    async fn test_unknown_variant() {
+        let app = || {
+            App::new()
+                .app_data(get_default_queryconfig())
+                .app_data(JsonConfigBuilder::new().locale(Locale::En).build())
+                .route("/devices", web::get().to(by_status))
+                .route("/devices", web::post().to(update_status))
+        };
+        let req = test::TestRequest::get()
+            .uri("/devices?status=offlin")
+            .to_request();
+        let res = call_app!(app(), req);
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(res).await;
+        let detail = &body["error"]["details"][0];
+        assert_eq!(detail["code"], 1012);
+        assert_eq!(
+            detail["desc"],
+            "查询参数错误: 取值`offlin`无效，可选值: online, offline, unknown"
+        );
+        assert_eq!(detail["extra"]["received"], "offlin");
+        assert_eq!(
+            detail["extra"]["allowed"],
+            serde_json::json!(["online", "offline", "unknown"])
+        );
+
+        let req = test_post("application/json", r#"{"status": "gone"}"#)
+            .uri("/devices")
+            .to_request();
+        let res = call_app!(app(), req);
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(res).await;
+        let detail = &body["error"]["details"][0];
+        assert_eq!(detail["code"], 2006);
+        assert_eq!(
+            detail["desc"],
+            "json parse error: invalid value `gone`, allowed values: online, offline, unknown"
+        );
+    }
+
+    #[async_test]
+    async fn test_parse_unknown_variant() {
+        assert_eq!(
+            parse_unknown_variant("unknown variant `x`, expected `a` or `b` at line 1 column 3"),
+            Some(("x", vec!["a", "b"]))
+        );
+        assert_eq!(
+            parse_unknown_variant("unknown variant `x`, expected `a`"),
+            Some(("x", vec!["a"]))
+        );
+        assert_eq!(parse_unknown_variant("invalid type: integer `1`"), None);
+    }
+
+    #[async_test]
+    async fn test_path_config() {
+        let app = || {
+            App::new()
+                .app_data(get_default_pathconfig())
+                .route("/devices/{id}", web::get().to(detail))
+        };
+        let req = test::TestRequest::get().uri("/devices/abc").to_request();
+        let res = call_app!(app(), req);
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["error"]["status"], 400);
+        let detail = &body["error"]["details"][0];
+        assert_eq!(detail["code"], 1012);
+        let desc = detail["desc"].as_str().unwrap();
+        assert!(desc.contains("/devices/abc"), "{}", desc);
+        assert!(desc.contains("u64"), "{}", desc);
+
+        let req = test::TestRequest::get().uri("/devices/42").to_request();
+        let res = call_app!(app(), req);
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(test::read_body(res).await, "42");
+    }
+
+    #[cfg(feature = "actix4")]
+    #[actix_web::test]
+    async fn test_form_parse_error() {
+        let (status, body) = post_form(get_default_formconfig(), "title=router").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["status"], 400);
+        let detail = &body["error"]["details"][0];
+        assert_eq!(detail["code"], 1012);
+        assert!(detail["desc"]
+            .as_str()
+            .unwrap()
+            .contains("missing field `name`"));
+    }
+
+    #[cfg(feature = "actix4")]
+    #[actix_web::test]
+    async fn test_form_overflow() {
+        let config = FormConfigBuilder::new().limit(8).locale(Locale::En).build();
+        let (status, body) = post_form(config, "name=router-01").await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body["error"]["status"], 413);
+        let detail = &body["error"]["details"][0];
+        assert_eq!(detail["code"], 2009);
+        assert_eq!(
+            detail["desc"],
+            "payload too large: limit 8 bytes, received 14 bytes"
+        );
+    }
+
+    #[cfg(feature = "actix4")]
+    #[actix_web::test]
+    async fn test_register_default_configs() {
+        let app = test::init_service(
+            App::new()
+                .configure(register_default_configs)
+                .route("/", web::post().to(create))
+                .route("/devices", web::get().to(list)),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/")
+            .insert_header(("content-type", "application/json"))
+            .set_payload("{")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["error"]["details"][0]["code"], 2006);
+
+        let req = test::TestRequest::get()
+            .uri("/devices?limit=abc")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["error"]["details"][0]["code"], 1012);
+    }
+
+    #[cfg(feature = "actix4")]
+    #[actix_web::test]
+    async fn test_default_configs_json_limit() {
+        let app = test::init_service(
+            App::new()
+                .configure(|cfg| DefaultConfigs::new().json_limit(8).apply(cfg))
+                .route("/", web::post().to(create))
+                .route("/devices", web::get().to(list)),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/")
+            .insert_header(("content-type", "application/json"))
+            .set_payload("{\"name\": \"router-01\"}")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let req = test::TestRequest::get()
+            .uri("/devices?limit=abc")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["error"]["details"][0]["code"], 1012);
+    }
+
+    #[cfg(feature = "actix4")]
+    #[actix_web::test]
+    async fn test_echo_body_disabled_by_default() {
+        let (status, body) = post_captured(get_default_jsonconfig(), r#"{"name": 1}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"]["details"][0].get("extra").is_none());
+    }
+
+    #[cfg(feature = "actix4")]
+    #[actix_web::test]
+    async fn test_echo_body_truncated() {
+        let config = JsonConfigBuilder::new().echo_body(8).build();
+        let (_, body) = post_captured(config, r#"{"name": 12345678}"#).await;
+        let extra = &body["error"]["details"][0]["extra"];
+        assert_eq!(extra["body_preview"], r#"{"name":"#);
+        assert_eq!(extra["body_truncated"], true);
+
+        let config = JsonConfigBuilder::new().echo_body(64).build();
+        let (_, body) = post_captured(config, r#"{"name": 1}"#).await;
+        let extra = &body["error"]["details"][0]["extra"];
+        assert_eq!(extra["body_preview"], r#"{"name": 1}"#);
+        assert!(extra.get("body_truncated").is_none());
+    }
+
+    #[cfg(feature = "actix4")]
+    #[actix_web::test]
+    async fn test_echo_body_redact() {
+        let config = JsonConfigBuilder::new()
+            .echo_body(64)
+            .redact(|body| body.replace("secret", "***"))
+            .build();
+        let (_, body) = post_captured(config, r#"{"name": 1, "password": "secret"}"#).await;
+        assert_eq!(
+            body["error"]["details"][0]["extra"]["body_preview"],
+            r#"{"name": 1, "password": "***"}"#
+        );
+    }
+
+    #[cfg(feature = "actix4")]
+    #[actix_web::test]
+    async fn test_echo_body_not_on_overflow() {
+        let config = JsonConfigBuilder::new().limit(4).echo_body(64).build();
+        let (status, body) = post_captured(config, r#"{"name": "abc"}"#).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(body["error"]["details"][0].get("extra").is_none());
+    }
+}
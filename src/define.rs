@@ -1,6 +1,10 @@
 use diesel::result::Error as DieselError;
 use serde_derive::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
 use std::fmt::{self, Display};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::RwLock;
 use std::{io::Error as IoError, string::ToString};
 use thiserror::Error as ThisError;
 
@@ -104,6 +108,12 @@ status_codes! {
     (2006, InvalidMessageData, "invalid message data", "无效的消息格式");
     (2007, InvalidCommand, "invalid command", "无效的消息指令");
     (2008, InvalidUseRule, "invalid use rule", "无效的规则");
+    (2009, PayloadTooLarge, "payload too large", "请求数据过大");
+    (2010, UnsupportedContentType, "unsupported content type", "不支持的Content-Type");
+    (2011, RouteNotFound, "route not found", "请求的路由不存在");
+    (2012, MethodNotAllowed, "method not allowed", "请求方法不允许");
+    (2013, ValidationFailed, "validation failed", "数据校验失败");
+    (2014, RateLimited, "rate limited", "请求过于频繁");
     //DataBase Error 3001-4000
     (3001, DataBaseInvalidQuery, "dataBase invalid query", "数据库查询参数错误");
     (3002, DataBaseError, "database error", "数据库返回错误");
@@ -128,9 +138,15 @@ status_codes! {
     (5001, UnexpectedErrorOccured, "unexpected error occured", "发生意外错误");
     (5002, ServerRegisterFail, "server register fail", "服务注册失败");
     (5003, ConfigurationInvalid, "configuration invalid", "配置无效");
+    (5004, ServerBusy, "server busy", "服务繁忙");
     (5100, UnKnowError, "unknow error", "未定义错误");
     //Token Error 6001-7000
     (6001, RoleTypeError, "role type error", "权限类型不存在");
+    (6002, Unauthorized, "unauthorized", "身份认证失败");
+    (6003, TokenMissing, "token missing", "缺少访问令牌");
+    (6004, TokenMalformed, "token malformed", "访问令牌格式错误");
+    (6005, TokenExpired, "token expired", "访问令牌已过期");
+    (6006, TokenInvalid, "token invalid", "访问令牌无效");
 
     //translate Error 7001-7999
     (7001, TransInitError, "translate init error", "翻译器初始化错误");
@@ -139,6 +155,62 @@ status_codes! {
     (7004, TransInnerError, "translate inner error", "翻译内部错误");
 }
 
+/// 错误信息的语言
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub enum Locale {
+    #[default]
+    Zh,
+    En,
+}
+
+static DEFAULT_LOCALE: AtomicU8 = AtomicU8::new(Locale::Zh as u8);
+static DEFAULT_LOCALE_SET: AtomicBool = AtomicBool::new(false);
+
+/// 设置全局默认语言，未单独指定语言的地方都使用该设置
+///
+/// 设置后错误详情中的err_type也按该语言返回，未设置时err_type固定为英文
+pub fn set_default_locale(locale: Locale) {
+    DEFAULT_LOCALE.store(locale as u8, Ordering::Relaxed);
+    DEFAULT_LOCALE_SET.store(true, Ordering::Relaxed);
+}
+
+/// 通过set_default_locale显式设置过的全局默认语言
+pub(crate) fn explicit_default_locale() -> Option<Locale> {
+    DEFAULT_LOCALE_SET
+        .load(Ordering::Relaxed)
+        .then(default_locale)
+}
+
+pub fn default_locale() -> Locale {
+    match DEFAULT_LOCALE.load(Ordering::Relaxed) {
+        1 => Locale::En,
+        _ => Locale::Zh,
+    }
+}
+
+impl Locale {
+    /// 按语言选择对应的文本
+    pub fn pick<'a>(self, cn: &'a str, en: &'a str) -> &'a str {
+        match self {
+            Locale::Zh => cn,
+            Locale::En => en,
+        }
+    }
+
+    /// 根据语言标签（如zh-CN、en-US）选择支持的语言，只比较主标签
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let primary = tag.trim().split(['-', '_']).next()?;
+        if primary.eq_ignore_ascii_case("zh") {
+            Some(Locale::Zh)
+        } else if primary.eq_ignore_ascii_case("en") {
+            Some(Locale::En)
+        } else {
+            None
+        }
+    }
+}
+
+#[allow(dead_code)]
 #[derive(ThisError, Debug)]
 pub(crate) struct CustomError {
     pub code: u16,
@@ -156,6 +228,7 @@ impl Display for CustomError {
     }
 }
 
+#[allow(dead_code)]
 pub(crate) trait ErrorMeta {
     fn status_code(&self) -> u16;
     fn reason(&self) -> String;
@@ -175,6 +248,26 @@ pub struct ExtraDescError {
     #[source]
     pub err: Error,
     pub desc: String,
+    /// 出错的字段名称
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// 附加的结构化信息
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra: Option<Map<String, Value>>,
+}
+
+impl ExtraDescError {
+    pub fn with_field<S: Into<String>>(mut self, field: S) -> Self {
+        self.field = Some(field.into());
+        self
+    }
+
+    pub fn with_extra<S: Into<String>, V: Into<Value>>(mut self, key: S, value: V) -> Self {
+        self.extra
+            .get_or_insert_with(Map::new)
+            .insert(key.into(), value.into());
+        self
+    }
 }
 
 impl Display for ExtraDescError {
@@ -185,10 +278,7 @@ impl Display for ExtraDescError {
 
 impl From<Error> for ExtraDescError {
     fn from(source: Error) -> Self {
-        ExtraDescError {
-            err: source,
-            desc: String::new(),
-        }
+        source.from_desc(String::new())
     }
 }
 
@@ -220,12 +310,39 @@ impl Error {
         canonical_reason_cn(self.0)
     }
 
+    pub fn reason(&self, locale: Locale) -> Option<&str> {
+        match locale {
+            Locale::Zh => self.reason_cn(),
+            Locale::En => self.reason_en(),
+        }
+    }
+
+    /// 是否为临时性错误，稍后重试可能成功（超时、连接中断、限流、服务繁忙等）
+    #[allow(non_upper_case_globals)]
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            *self,
+            ConnectionRefused
+                | ConnectionReset
+                | ConnectionAborted
+                | NotConnected
+                | BrokenPipe
+                | WouldBlock
+                | TimedOut
+                | Interrupted
+                | FetchMessageTimeout
+                | RateLimited
+                | InvalidConnection
+                | ConnectionDeviceTimeout
+                | SendDataTimeout
+                | ReceiveDataTimeout
+                | ServerBusy
+        )
+    }
+
     #[allow(dead_code, clippy::wrong_self_convention)]
     pub fn from_error(self, error: Error) -> ExtraDescError {
-        ExtraDescError {
-            err: self,
-            desc: error.to_string(),
-        }
+        self.from_desc(error.to_string())
     }
 
     #[allow(clippy::wrong_self_convention)]
@@ -233,6 +350,8 @@ impl Error {
         ExtraDescError {
             err: self,
             desc: desc.into(),
+            field: None,
+            extra: None,
         }
     }
 }
@@ -276,10 +395,62 @@ impl From<toml::de::Error> for ExtraDescError {
     }
 }
 
+/// 数据库约束对应的提示信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintMessage {
+    pub en: String,
+    pub cn: String,
+}
+
+static CONSTRAINT_MESSAGES: RwLock<BTreeMap<String, ConstraintMessage>> =
+    RwLock::new(BTreeMap::new());
+
+/// 注册数据库约束名称对应的友好提示，违反该约束时按当前语言作为错误描述返回
+///
+/// # Example
+///
+/// ```ignore
+/// register_constraint_message("uq_device_name", "device name already exists", "设备名称已存在");
+/// ```
+pub fn register_constraint_message(
+    constraint: impl Into<String>,
+    en: impl Into<String>,
+    cn: impl Into<String>,
+) {
+    CONSTRAINT_MESSAGES.write().unwrap().insert(
+        constraint.into(),
+        ConstraintMessage {
+            en: en.into(),
+            cn: cn.into(),
+        },
+    );
+}
+
+pub fn constraint_message(constraint: &str) -> Option<ConstraintMessage> {
+    CONSTRAINT_MESSAGES.read().unwrap().get(constraint).cloned()
+}
+
 impl From<DieselError> for ExtraDescError {
     fn from(error: DieselError) -> Self {
         match error {
-            DieselError::DatabaseError(_, err) => DataBaseError.from_desc(err.message()),
+            DieselError::DatabaseError(_, info) => {
+                let mut error = match info.constraint_name().and_then(constraint_message) {
+                    Some(message) => DataBaseError.from_desc(
+                        crate::middleware::current_locale().pick(&message.cn, &message.en),
+                    ),
+                    None => DataBaseError.from_desc(info.message()),
+                };
+                if let Some(column) = info.column_name() {
+                    error = error.with_field(column);
+                }
+                if let Some(constraint) = info.constraint_name() {
+                    error = error.with_extra("constraint", constraint);
+                }
+                if let Some(table) = info.table_name() {
+                    error = error.with_extra("table", table);
+                }
+                error
+            }
             DieselError::NotFound => DataBaseNotFound.from_desc(error.to_string()),
             DieselError::QueryBuilderError(err) => DataBaseInvalidQuery.from_desc(err.to_string()),
             err => UnKnowError.from_desc(err.to_string()),
@@ -326,12 +497,14 @@ mod tests {
 
     pub type NetResult<T> = Result<T, NetError>;
 
+    #[allow(clippy::useless_format)]
     fn old_read_line() -> NetResult<()> {
         Err(NetError::ConnProtoError(format!(
             "read_line error, encounter bad channel.",
         )))
     }
 
+    #[allow(clippy::useless_conversion, clippy::needless_borrows_for_generic_args)]
     fn new_read_line() -> Result<()> {
         old_read_line().map_err(|error| {
             //error!("")
@@ -339,6 +512,81 @@ mod tests {
         })
     }
 
+    struct MockDatabaseError {
+        constraint: Option<&'static str>,
+    }
+
+    impl diesel::result::DatabaseErrorInformation for MockDatabaseError {
+        fn message(&self) -> &str {
+            "duplicate key value violates unique constraint"
+        }
+
+        fn details(&self) -> Option<&str> {
+            None
+        }
+
+        fn hint(&self) -> Option<&str> {
+            None
+        }
+
+        fn table_name(&self) -> Option<&str> {
+            self.constraint.map(|_| "device")
+        }
+
+        fn column_name(&self) -> Option<&str> {
+            self.constraint.map(|_| "name")
+        }
+
+        fn constraint_name(&self) -> Option<&str> {
+            self.constraint
+        }
+    }
+
+    fn unique_violation(constraint: Option<&'static str>) -> DieselError {
+        DieselError::DatabaseError(
+            diesel::result::DatabaseErrorKind::UniqueViolation,
+            Box::new(MockDatabaseError { constraint }),
+        )
+    }
+
+    #[test]
+    fn test_diesel_constraint() {
+        register_constraint_message(
+            "uq_device_name",
+            "device name already exists",
+            "设备名称已存在",
+        );
+        let error: ExtraDescError = unique_violation(Some("uq_device_name")).into();
+        assert_eq!(error.err, DataBaseError);
+        assert_eq!(error.desc, "设备名称已存在");
+        assert_eq!(error.field.as_deref(), Some("name"));
+        let extra = error.extra.unwrap();
+        assert_eq!(extra["constraint"], "uq_device_name");
+        assert_eq!(extra["table"], "device");
+
+        let error: ExtraDescError = crate::middleware::with_locale(Locale::En, || {
+            unique_violation(Some("uq_device_name")).into()
+        });
+        assert_eq!(error.desc, "device name already exists");
+    }
+
+    #[test]
+    fn test_constraint_message_argument_types() {
+        let constraint = String::from("uq_device_serial");
+        register_constraint_message(constraint, "serial already exists", "序列号已存在");
+        let message = constraint_message("uq_device_serial").unwrap();
+        assert_eq!(message.en, "serial already exists");
+        assert_eq!(message.cn, "序列号已存在");
+    }
+
+    #[test]
+    fn test_diesel_without_metadata() {
+        let error: ExtraDescError = unique_violation(None).into();
+        assert_eq!(error.desc, "duplicate key value violates unique constraint");
+        assert!(error.field.is_none());
+        assert!(error.extra.is_none());
+    }
+
     #[test]
     fn test_map_error() {
         if let Err(error) = new_read_line() {
@@ -1,8 +1,10 @@
 use super::define::Error as StdError;
 use super::define::*;
+use super::middleware::{current_locale, current_request_id, explicit_locale};
+use actix_web::http::header::{self, HeaderName, HeaderValue};
 use actix_web::{http::StatusCode, HttpResponse, ResponseError};
 use serde_derive::Serialize;
-use serde_json::json;
+use serde_json::{error::Category, json, Map, Value};
 use std::{
     fmt::{Display, Formatter, Result as FmtResult},
     str::Utf8Error,
@@ -13,49 +15,110 @@ pub type HttpResult<I> = Result<I, Error>;
 pub struct Error {
     real_error: Option<ExtraDescError>,
     status: StatusCode,
+    retryable: Option<bool>,
+    retry_after: Option<u64>,
+    headers: Vec<(HeaderName, HeaderValue)>,
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct ErrorDetail {
+    code: u16,
     err_type: String,
     desc: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "utoipa", schema(value_type = Option<Object>))]
+    extra: Option<Map<String, Value>>,
+}
+
+impl ErrorDetail {
+    /// err_type只在显式指定语言时本地化，否则保持英文
+    pub(crate) fn new_from_extra(err: &ExtraDescError) -> ErrorDetail {
+        ErrorDetail {
+            code: err.err.code(),
+            err_type: err
+                .err
+                .reason(explicit_locale().unwrap_or(Locale::En))
+                .expect("unkown err")
+                .to_string(),
+            desc: err.desc.clone(),
+            field: err.field.clone(),
+            extra: err.extra.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct ErrorWrapper {
     status: u16,
     details: Vec<ErrorDetail>,
+    /// 当前请求的RequestId，由AssignRequestId中间件设置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace_id: Option<String>,
+    /// 是否可以重试：错误码可重试时为true，通过Error::retryable显式设置时按设置的值返回，其它情况不返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retryable: Option<bool>,
+    /// 建议的重试等待秒数，只在retryable为true且通过Error::retry_after设置时返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retry_after_secs: Option<u64>,
+}
+
+/// 错误码可重试时返回Some(true)，否则不返回
+fn derived_retryable(code: &StdError) -> Option<bool> {
+    code.is_retryable().then_some(true)
 }
 
 impl ErrorWrapper {
     fn new_from_error(err: &Error) -> ErrorWrapper {
-        if let Some(real_error) = &err.real_error {
-            let err_detail = ErrorDetail {
-                err_type: real_error.err.reason_en().expect("unkown err").to_string(),
-                desc: real_error.desc.clone(),
-            };
-            ErrorWrapper {
-                status: err.status.as_u16(),
-                details: vec![err_detail],
-            }
-        } else {
-            ErrorWrapper {
-                status: err.status.as_u16(),
-                details: vec![],
-            }
+        let retryable = err.retryable.or_else(|| {
+            err.real_error
+                .as_ref()
+                .and_then(|real_error| derived_retryable(&real_error.err))
+        });
+        let retry_after_secs = err.retry_after.filter(|_| retryable == Some(true));
+        let details = match &err.real_error {
+            Some(real_error) => vec![ErrorDetail::new_from_extra(real_error)],
+            None => vec![],
+        };
+        ErrorWrapper {
+            status: err.status.as_u16(),
+            details,
+            trace_id: current_request_id(),
+            retryable,
+            retry_after_secs,
         }
     }
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct ErrorOutTpl {
     error: ErrorWrapper,
 }
 
 impl ErrorOutTpl {
+    /// 多条错误时retryable由第一条错误决定
+    pub(crate) fn new_from_details(status: StatusCode, details: Vec<ErrorDetail>) -> ErrorOutTpl {
+        let retryable = details
+            .first()
+            .and_then(|detail| derived_retryable(&StdError(detail.code)));
+        ErrorOutTpl {
+            error: ErrorWrapper {
+                status: status.as_u16(),
+                details,
+                trace_id: current_request_id(),
+                retryable,
+                retry_after_secs: None,
+            },
+        }
+    }
+
     fn new_from_error(err: &Error) -> ErrorOutTpl {
         ErrorOutTpl {
-            error: ErrorWrapper::new_from_error(&err),
+            error: ErrorWrapper::new_from_error(err),
         }
     }
 }
@@ -65,9 +128,30 @@ impl Error {
         Error {
             real_error: None,
             status: code,
+            retryable: None,
+            retry_after: None,
+            headers: Vec::new(),
         }
     }
 
+    /// 覆盖由错误码得出的retryable
+    pub fn retryable(mut self, retryable: bool) -> Self {
+        self.retryable = Some(retryable);
+        self
+    }
+
+    /// 建议的重试等待秒数，设置Retry-After头，可重试时同时在响应体中返回retry_after_secs
+    pub fn retry_after(mut self, seconds: u64) -> Self {
+        self.retry_after = Some(seconds);
+        self
+    }
+
+    /// 渲染响应时附加的响应头，如401时的WWW-Authenticate
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
     pub fn err(mut self, e: ExtraDescError) -> Self {
         self.real_error = Some(e);
         self
@@ -82,6 +166,20 @@ impl Error {
         self.real_error = Some(InvalidMessageData.from_desc(msg));
         self
     }
+
+    /// 序列化自身数据失败，返回500
+    pub fn serialization(error: serde_json::Error) -> Self {
+        Error::new(StatusCode::INTERNAL_SERVER_ERROR)
+            .err(UnexpectedErrorOccured.from_desc(error.to_string()))
+    }
+
+    /// 渲染时使用的标准错误码，没有具体错误时为UnexpectedErrorOccured
+    pub(crate) fn code(&self) -> u16 {
+        self.real_error
+            .as_ref()
+            .map(|real_error| real_error.err.code())
+            .unwrap_or(UnexpectedErrorOccured.code())
+    }
 }
 
 impl Display for Error {
@@ -93,20 +191,30 @@ impl Display for Error {
 impl ResponseError for Error {
     fn error_response(&self) -> HttpResponse {
         let status_code = self.status_code();
-        if self.real_error.is_some() {
-            HttpResponse::build(status_code).json(json!(ErrorOutTpl::new_from_error(self)))
+        let mut response = if self.real_error.is_some() {
+            render_error(status_code, &ErrorOutTpl::new_from_error(self), self.code())
         } else {
-            let std_err = StdError(5001);
-            let err_ext = ExtraDescError {
-                err: std_err,
-                desc: "发生意外错误".to_string(),
-            };
+            let std_err = UnexpectedErrorOccured;
+            let err_ext = std_err
+                .from_desc(current_locale().pick("发生意外错误", "unexpected error occured"));
             let err = Error {
                 status: status_code,
                 real_error: Some(err_ext),
+                retryable: self.retryable,
+                retry_after: self.retry_after,
+                headers: Vec::new(),
             };
-            HttpResponse::build(status_code).json(json!(ErrorOutTpl::new_from_error(&err)))
+            render_error(status_code, &ErrorOutTpl::new_from_error(&err), self.code())
+        };
+        if let Some(seconds) = self.retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        for (name, value) in &self.headers {
+            response.headers_mut().insert(name.clone(), value.clone());
         }
+        response
     }
 
     fn status_code(&self) -> StatusCode {
@@ -114,14 +222,339 @@ impl ResponseError for Error {
     }
 }
 
+/// 多个错误的集合，渲染为带多条details的标准错误结构，状态码由第一个错误决定
+#[derive(Debug, Default)]
+pub struct ErrorList(Vec<ExtraDescError>);
+
+impl ErrorList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, err: ExtraDescError) {
+        self.0.push(err);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, ExtraDescError> {
+        self.0.iter()
+    }
+
+    pub fn into_inner(self) -> Vec<ExtraDescError> {
+        self.0
+    }
+
+    /// 没有错误时返回Ok
+    pub fn into_result(self) -> Result<(), ErrorList> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// 渲染时使用的标准错误码，由第一个错误决定
+    pub(crate) fn code(&self) -> u16 {
+        self.0
+            .first()
+            .map(|err| err.err.code())
+            .unwrap_or(UnexpectedErrorOccured.code())
+    }
+}
+
+impl From<Vec<ExtraDescError>> for ErrorList {
+    fn from(errors: Vec<ExtraDescError>) -> Self {
+        ErrorList(errors)
+    }
+}
+
+impl Display for ErrorList {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl ResponseError for ErrorList {
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let code = self.code();
+        let details = self.0.iter().map(ErrorDetail::new_from_extra).collect();
+        render_error(
+            status,
+            &ErrorOutTpl::new_from_details(status, details),
+            code,
+        )
+    }
+
+    fn status_code(&self) -> StatusCode {
+        self.0
+            .first()
+            .map(|err| status_for_code(&err.err))
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+/// 标准错误码，写入错误响应的extensions，中间件据此识别标准错误结构而无需解析响应体
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCode(pub u16);
+
+pub(crate) fn render_error(status: StatusCode, tpl: &ErrorOutTpl, code: u16) -> HttpResponse {
+    let mut response = HttpResponse::build(status).json(json!(tpl));
+    response.extensions_mut().insert(ErrorCode(code));
+    response
+}
+
+/// 根据HTTP状态码选择标准错误码，用于改写其他组件产生的错误响应
+#[cfg(feature = "actix4")]
+pub(crate) fn code_for_status(status: StatusCode) -> StdError {
+    match status {
+        StatusCode::BAD_REQUEST => InvalidInput,
+        StatusCode::UNAUTHORIZED => Unauthorized,
+        StatusCode::FORBIDDEN => PermissionDenied,
+        StatusCode::NOT_FOUND => RouteNotFound,
+        StatusCode::METHOD_NOT_ALLOWED => MethodNotAllowed,
+        StatusCode::UNPROCESSABLE_ENTITY => ValidationFailed,
+        StatusCode::TOO_MANY_REQUESTS => RateLimited,
+        StatusCode::SERVICE_UNAVAILABLE => ServerBusy,
+        StatusCode::PAYLOAD_TOO_LARGE => PayloadTooLarge,
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => UnsupportedContentType,
+        StatusCode::GATEWAY_TIMEOUT => TimedOut,
+        status if status.is_client_error() => InvalidInput,
+        _ => UnexpectedErrorOccured,
+    }
+}
+
+/// 根据标准错误码选择HTTP状态码，未列出的错误码统一返回500
+#[allow(non_upper_case_globals)]
+pub(crate) fn status_for_code(err: &StdError) -> StatusCode {
+    match *err {
+        FileNotFound | DataBaseNotFound | DeviceNotFound | RouteNotFound => StatusCode::NOT_FOUND,
+        PermissionDenied => StatusCode::FORBIDDEN,
+        TimedOut => StatusCode::GATEWAY_TIMEOUT,
+        InvalidInput | InvalidData | InvalidMessageData => StatusCode::BAD_REQUEST,
+        PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        UnsupportedContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+        ValidationFailed => StatusCode::UNPROCESSABLE_ENTITY,
+        RateLimited => StatusCode::TOO_MANY_REQUESTS,
+        ServerBusy => StatusCode::SERVICE_UNAVAILABLE,
+        Unauthorized | TokenMissing | TokenMalformed | TokenExpired | TokenInvalid => {
+            StatusCode::UNAUTHORIZED
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+impl From<ExtraDescError> for Error {
+    fn from(error: ExtraDescError) -> Self {
+        Error::new(status_for_code(&error.err)).err(error)
+    }
+}
+
 impl From<Utf8Error> for Error {
     fn from(error: Utf8Error) -> Self {
-        Error::new(StatusCode::INTERNAL_SERVER_ERROR).invalid_data(error.to_string().as_str())
+        Error::new(StatusCode::BAD_REQUEST).invalid_data(error.to_string().as_str())
     }
 }
 
 impl From<std::io::Error> for Error {
     fn from(error: std::io::Error) -> Self {
-        Error::new(StatusCode::INTERNAL_SERVER_ERROR).invalid_data(error.to_string().as_str())
+        let desc = error.to_string();
+        let mut real_error = ExtraDescError::from(error);
+        real_error.desc = desc;
+        real_error.into()
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    /// 解析输入失败（语法错误、数据不完整、类型不匹配）返回400，I/O错误返回500
+    ///
+    /// 序列化自身数据失败时使用[`Error::serialization`]
+    fn from(error: serde_json::Error) -> Self {
+        let status = match error.classify() {
+            Category::Io => StatusCode::INTERNAL_SERVER_ERROR,
+            Category::Syntax | Category::Eof | Category::Data => StatusCode::BAD_REQUEST,
+        };
+        Error::new(status).invalid_data(error.to_string().as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compat::{async_test, body_bytes, call_app};
+    use actix_web::{test, web, App};
+    use serde_json::Value;
+    use std::io::{Error as IoError, ErrorKind};
+
+    async fn io_handler(kind: web::Path<String>) -> HttpResult<String> {
+        let kind = match kind.as_str() {
+            "not_found" => ErrorKind::NotFound,
+            "denied" => ErrorKind::PermissionDenied,
+            _ => ErrorKind::TimedOut,
+        };
+        Err(IoError::new(kind, "io failed"))?
+    }
+
+    async fn call_io(kind: &str) -> (StatusCode, Value) {
+        let req = test::TestRequest::get()
+            .uri(&format!("/io/{}", kind))
+            .to_request();
+        let res = call_app!(
+            App::new().route("/io/{kind}", web::get().to(io_handler)),
+            req
+        );
+        let status = res.status();
+        (status, test::read_body_json(res).await)
+    }
+
+    #[async_test]
+    async fn test_io_not_found() {
+        let (status, body) = call_io("not_found").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["status"], 404);
+        assert_eq!(body["error"]["details"][0]["err_type"], "file not found");
+        assert_eq!(body["error"]["details"][0]["desc"], "io failed");
+    }
+
+    #[async_test]
+    async fn test_io_permission_denied() {
+        let (status, body) = call_io("denied").await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["error"]["details"][0]["err_type"], "permission denied");
+    }
+
+    #[async_test]
+    async fn test_io_timed_out() {
+        let (status, body) = call_io("timeout").await;
+        assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(body["error"]["details"][0]["err_type"], "timed out");
+        assert_eq!(body["error"]["retryable"], true);
+        assert!(body["error"].get("retry_after_secs").is_none());
+    }
+
+    #[async_test]
+    async fn test_retryable_hint() {
+        let error: Error = InvalidInput.from_desc("参数错误").into();
+        let body = serde_json::to_value(ErrorOutTpl::new_from_error(&error)).unwrap();
+        assert!(body["error"].get("retryable").is_none());
+
+        let error = Error::new(StatusCode::BAD_REQUEST)
+            .err(InvalidInput.from_desc("参数错误"))
+            .retryable(true);
+        let body = serde_json::to_value(ErrorOutTpl::new_from_error(&error)).unwrap();
+        assert_eq!(body["error"]["retryable"], true);
+
+        let error = Error::new(StatusCode::GATEWAY_TIMEOUT)
+            .err(TimedOut.from_desc("操作超时"))
+            .retryable(false)
+            .retry_after(5);
+        let res = error.error_response();
+        assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "5");
+        let body: Value = serde_json::from_slice(&body_bytes(res).await).unwrap();
+        assert_eq!(body["error"]["retryable"], false);
+        assert!(body["error"].get("retry_after_secs").is_none());
+
+        let error = Error::new(StatusCode::GATEWAY_TIMEOUT)
+            .err(TimedOut.from_desc("操作超时"))
+            .retry_after(5);
+        let body = serde_json::to_value(ErrorOutTpl::new_from_error(&error)).unwrap();
+        assert_eq!(body["error"]["retryable"], true);
+        assert_eq!(body["error"]["retry_after_secs"], 5);
+    }
+
+    #[async_test]
+    async fn test_utf8_error() {
+        let bytes = vec![0xff, 0xfe];
+        let error: Error = std::str::from_utf8(&bytes).unwrap_err().into();
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[async_test]
+    async fn test_json_parse_error() {
+        let parsed = serde_json::from_str::<Value>("{\"name\": }").unwrap_err();
+        let error: Error = parsed.into();
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+        let desc = &error.real_error.as_ref().unwrap().desc;
+        assert!(desc.contains("line 1 column 10"), "{}", desc);
+    }
+
+    #[async_test]
+    async fn test_json_from_value_error() {
+        let value = serde_json::json!({"name": 1});
+        let error: Error =
+            serde_json::from_value::<std::collections::HashMap<String, String>>(value)
+                .unwrap_err()
+                .into();
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(error.code(), InvalidMessageData.code());
+    }
+
+    #[async_test]
+    async fn test_detail_field_and_extra() {
+        let error: Error = DataBaseError
+            .from_desc("设备名称已存在")
+            .with_field("name")
+            .with_extra("constraint", "uq_device_name")
+            .into();
+        let body = serde_json::to_value(ErrorOutTpl::new_from_error(&error)).unwrap();
+        let detail = &body["error"]["details"][0];
+        assert_eq!(detail["field"], "name");
+        assert_eq!(detail["extra"]["constraint"], "uq_device_name");
+
+        let error: Error = DataBaseError.from_desc("数据库返回错误").into();
+        let body = serde_json::to_value(ErrorOutTpl::new_from_error(&error)).unwrap();
+        assert!(body["error"]["details"][0].get("field").is_none());
+        assert!(body["error"]["details"][0].get("extra").is_none());
+    }
+
+    struct BrokenWriter;
+
+    impl std::io::Write for BrokenWriter {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(IoError::new(ErrorKind::BrokenPipe, "broken pipe"))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[async_test]
+    async fn test_json_serialize_error() {
+        let mut map = std::collections::HashMap::new();
+        map.insert(vec![1u8], 1);
+        let error = Error::serialization(serde_json::to_string(&map).unwrap_err());
+        assert_eq!(error.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(error.code(), UnexpectedErrorOccured.code());
+    }
+
+    struct Unserializable;
+
+    impl serde::Serialize for Unserializable {
+        fn serialize<S: serde::Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("unserializable"))
+        }
+    }
+
+    #[async_test]
+    async fn test_json_custom_serialize_error() {
+        let error = Error::serialization(serde_json::to_string(&Unserializable).unwrap_err());
+        assert_eq!(error.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[async_test]
+    async fn test_json_write_error() {
+        let error: Error = serde_json::to_writer(BrokenWriter, &vec![1, 2, 3])
+            .unwrap_err()
+            .into();
+        assert_eq!(error.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
     }
 }
@@ -0,0 +1,166 @@
+use super::attachment;
+use crate::define::{Result, UnexpectedErrorOccured};
+use crate::middleware::current_locale;
+use crate::query::QueryOutput;
+use actix_web::http::header::ContentType;
+use actix_web::HttpResponse;
+use serde::Serialize;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// 将QueryOutput导出为CSV，第一行为字段名
+///
+/// T只能包含字符串、数字、布尔等简单字段，包含嵌套结构时返回错误
+///
+/// # Example
+///
+/// ```ignore
+/// CsvExport::new().bom(true).response(&output, "设备列表.csv")
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CsvExport {
+    bom: bool,
+}
+
+impl CsvExport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 在开头写入UTF-8 BOM，Excel直接打开时中文不会乱码，默认关闭
+    pub fn bom(mut self, bom: bool) -> Self {
+        self.bom = bom;
+        self
+    }
+
+    pub fn write<T: Serialize>(&self, output: &QueryOutput<T>) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        if self.bom {
+            buf.extend_from_slice(UTF8_BOM);
+        }
+        let mut writer = csv::Writer::from_writer(buf);
+        for item in &output.items {
+            writer.serialize(item).map_err(export_error)?;
+        }
+        writer
+            .into_inner()
+            .map_err(|e| export_error(e.into_error()))
+    }
+
+    pub fn response<T: Serialize>(
+        &self,
+        output: &QueryOutput<T>,
+        filename: &str,
+    ) -> Result<HttpResponse> {
+        let body = self.write(output)?;
+        Ok(HttpResponse::Ok()
+            .insert_header(ContentType(
+                "text/csv; charset=utf-8".parse().expect("valid mime"),
+            ))
+            .insert_header(attachment(filename))
+            .body(body))
+    }
+}
+
+fn export_error<E: std::fmt::Display>(err: E) -> crate::define::ExtraDescError {
+    let desc = format!(
+        "{}: {}",
+        current_locale().pick("CSV导出失败", "csv export failed"),
+        err
+    );
+    UnexpectedErrorOccured.from_desc(desc)
+}
+
+impl<T: Serialize> QueryOutput<T> {
+    /// 以CSV附件的形式返回items，不写入BOM
+    pub fn to_csv_response(&self, filename: &str) -> Result<HttpResponse> {
+        CsvExport::new().response(self, filename)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body::to_bytes;
+
+    #[derive(Serialize)]
+    struct Device {
+        name: String,
+        ip: String,
+        port: u16,
+        online: bool,
+    }
+
+    #[derive(Serialize)]
+    struct Location {
+        city: String,
+    }
+
+    #[derive(Serialize)]
+    struct Nested {
+        name: String,
+        location: Location,
+    }
+
+    fn devices() -> QueryOutput<Device> {
+        QueryOutput::from(vec![
+            Device {
+                name: "核心路由器，一号机房".into(),
+                ip: "10.0.0.1".into(),
+                port: 80,
+                online: true,
+            },
+            Device {
+                name: "名为\"边缘\"的设备\n第二行".into(),
+                ip: "10.0.0.2".into(),
+                port: 8080,
+                online: false,
+            },
+        ])
+    }
+
+    #[actix_web::test]
+    async fn test_csv_response() {
+        let res = devices().to_csv_response("设备列表.csv").unwrap();
+        assert_eq!(
+            res.headers().get("content-type").unwrap(),
+            "text/csv; charset=utf-8"
+        );
+        assert_eq!(
+            res.headers().get("content-disposition").unwrap(),
+            "attachment; filename=\"____.csv\"; filename*=UTF-8''%E8%AE%BE%E5%A4%87%E5%88%97%E8%A1%A8.csv"
+        );
+        let body = to_bytes(res.into_body()).await.unwrap();
+        let mut reader = csv::Reader::from_reader(body.as_ref());
+        assert_eq!(
+            reader.headers().unwrap(),
+            vec!["name", "ip", "port", "online"]
+        );
+        let rows: Vec<csv::StringRecord> = reader.records().map(|row| row.unwrap()).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[0][0], "核心路由器，一号机房");
+        assert_eq!(&rows[1][0], "名为\"边缘\"的设备\n第二行");
+        assert_eq!(&rows[1][2], "8080");
+        assert_eq!(&rows[1][3], "false");
+    }
+
+    #[test]
+    fn test_bom() {
+        let body = CsvExport::new().bom(true).write(&devices()).unwrap();
+        assert!(body.starts_with(UTF8_BOM));
+        assert!(body[UTF8_BOM.len()..].starts_with(b"name,ip,port,online\n"));
+    }
+
+    #[test]
+    fn test_nested_fields() {
+        let output = QueryOutput::from(vec![Nested {
+            name: "摄像头".into(),
+            location: Location {
+                city: "北京".into(),
+            },
+        }]);
+        let err = CsvExport::new().write(&output).unwrap_err();
+        assert_eq!(err.err.code(), 5001);
+        assert!(err.desc.starts_with("CSV导出失败"), "{}", err.desc);
+    }
+}
@@ -0,0 +1,39 @@
+use actix_web::http::header::{
+    Charset, ContentDisposition, DispositionParam, DispositionType, ExtendedValue,
+};
+
+#[cfg(feature = "csv")]
+mod csv;
+#[cfg(feature = "xlsx")]
+mod xlsx;
+
+#[cfg(feature = "csv")]
+pub use self::csv::CsvExport;
+#[cfg(feature = "xlsx")]
+pub use xlsx::ExcelExport;
+
+/// 附件下载的Content-Disposition，非ASCII文件名按RFC 5987编码，同时提供ASCII的filename
+pub(crate) fn attachment(filename: &str) -> ContentDisposition {
+    let fallback: String = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() && c != '"' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let mut parameters = vec![DispositionParam::Filename(fallback)];
+    if !filename.is_ascii() {
+        parameters.push(DispositionParam::FilenameExt(ExtendedValue {
+            charset: Charset::Ext("UTF-8".to_string()),
+            language_tag: None,
+            value: filename.as_bytes().to_vec(),
+        }));
+    }
+    ContentDisposition {
+        disposition: DispositionType::Attachment,
+        parameters,
+    }
+}
@@ -0,0 +1,273 @@
+use super::attachment;
+use crate::define::{ExtraDescError, InvalidInput, Result, UnexpectedErrorOccured};
+use crate::middleware::current_locale;
+use crate::query::QueryOutput;
+use actix_web::http::header::ContentType;
+use actix_web::HttpResponse;
+use rust_xlsxwriter::{ExcelDateTime, Format, Workbook, Worksheet, XlsxError};
+use serde::Serialize;
+use serde_json::Value;
+
+const XLSX_MIME: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+/// 将QueryOutput导出为xlsx，第一行为表头
+///
+/// 列通过serde序列化后的字段名选择，字符串、数字、布尔值写为对应类型的单元格，
+/// 通过datetime()指定的列写为日期时间，嵌套结构写为json文本，行中没有的字段写为空单元格
+///
+/// # Example
+///
+/// ```ignore
+/// ExcelExport::new("设备")
+///     .columns(&[("name", "名称"), ("port", "端口"), ("created_at", "创建时间")])
+///     .datetime("created_at")
+///     .response(&output, "设备列表.xlsx")
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExcelExport {
+    sheet_name: String,
+    columns: Vec<(String, String)>,
+    datetime_columns: Vec<String>,
+    max_rows: usize,
+}
+
+impl ExcelExport {
+    pub fn new(sheet_name: &str) -> Self {
+        ExcelExport {
+            sheet_name: sheet_name.to_string(),
+            columns: Vec::new(),
+            datetime_columns: Vec::new(),
+            max_rows: 100_000,
+        }
+    }
+
+    /// 导出的列，(字段名, 表头)
+    pub fn columns(mut self, columns: &[(&str, &str)]) -> Self {
+        self.columns = columns
+            .iter()
+            .map(|(field, header)| (field.to_string(), header.to_string()))
+            .collect();
+        self
+    }
+
+    /// 按日期时间写入的列，值为`2024-05-01T08:00:00Z`形式的UTC时间或秒级时间戳
+    pub fn datetime(mut self, field: &str) -> Self {
+        self.datetime_columns.push(field.to_string());
+        self
+    }
+
+    /// 最多导出的数据行数，超出时返回InvalidInput，默认100000
+    pub fn max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = max_rows;
+        self
+    }
+
+    pub fn write<T: Serialize>(&self, output: &QueryOutput<T>) -> Result<Vec<u8>> {
+        if output.items.len() > self.max_rows {
+            let desc = format!(
+                "{}{}",
+                current_locale().pick("导出行数超过限制", "too many rows to export, limit "),
+                self.max_rows
+            );
+            return Err(InvalidInput.from_desc(desc));
+        }
+        let rows = output
+            .items
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(export_error)?;
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(&self.sheet_name).map_err(export_error)?;
+        self.write_sheet(worksheet, &rows).map_err(export_error)?;
+        workbook.save_to_buffer().map_err(export_error)
+    }
+
+    pub fn response<T: Serialize>(
+        &self,
+        output: &QueryOutput<T>,
+        filename: &str,
+    ) -> Result<HttpResponse> {
+        let body = self.write(output)?;
+        Ok(HttpResponse::Ok()
+            .insert_header(ContentType(XLSX_MIME.parse().expect("valid mime")))
+            .insert_header(attachment(filename))
+            .body(body))
+    }
+
+    fn write_sheet(
+        &self,
+        worksheet: &mut Worksheet,
+        rows: &[Value],
+    ) -> std::result::Result<(), XlsxError> {
+        let bold = Format::new().set_bold();
+        let datetime_format = Format::new().set_num_format("yyyy-mm-dd hh:mm:ss");
+        for (col, (_, header)) in self.columns.iter().enumerate() {
+            worksheet.write_string_with_format(0, col as u16, header, &bold)?;
+        }
+        for (index, value) in rows.iter().enumerate() {
+            let row = index as u32 + 1;
+            for (col, (field, _)) in self.columns.iter().enumerate() {
+                let col = col as u16;
+                let cell = match value.get(field) {
+                    Some(cell) => cell,
+                    None => continue,
+                };
+                if self.datetime_columns.contains(field) {
+                    if let Some(datetime) = to_datetime(cell) {
+                        worksheet.write_datetime_with_format(
+                            row,
+                            col,
+                            &datetime,
+                            &datetime_format,
+                        )?;
+                        continue;
+                    }
+                }
+                match cell {
+                    Value::Null => {}
+                    Value::Bool(value) => {
+                        worksheet.write_boolean(row, col, *value)?;
+                    }
+                    Value::Number(value) => {
+                        worksheet.write_number(row, col, value.as_f64().unwrap_or_default())?;
+                    }
+                    Value::String(value) => {
+                        worksheet.write_string(row, col, value)?;
+                    }
+                    other => {
+                        worksheet.write_string(row, col, other.to_string())?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn to_datetime(value: &Value) -> Option<ExcelDateTime> {
+    match value {
+        Value::Number(secs) => ExcelDateTime::from_timestamp(secs.as_i64()?).ok(),
+        Value::String(value) => ExcelDateTime::parse_from_str(value).ok(),
+        _ => None,
+    }
+}
+
+fn export_error<E: std::fmt::Display>(err: E) -> ExtraDescError {
+    let desc = format!(
+        "{}: {}",
+        current_locale().pick("Excel导出失败", "excel export failed"),
+        err
+    );
+    UnexpectedErrorOccured.from_desc(desc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body::to_bytes;
+    use std::io::{Cursor, Read};
+
+    #[derive(Serialize)]
+    struct Device {
+        name: String,
+        port: u16,
+        online: bool,
+        created_at: String,
+        secret: String,
+    }
+
+    fn devices(count: usize) -> QueryOutput<Device> {
+        (0..count)
+            .map(|i| Device {
+                name: format!("路由器-{}", i),
+                port: 8080,
+                online: i % 2 == 0,
+                created_at: "2024-05-01T08:00:00Z".into(),
+                secret: "hunter2".into(),
+            })
+            .collect()
+    }
+
+    fn export() -> ExcelExport {
+        ExcelExport::new("设备")
+            .columns(&[
+                ("name", "名称"),
+                ("port", "端口"),
+                ("online", "在线"),
+                ("created_at", "创建时间"),
+            ])
+            .datetime("created_at")
+    }
+
+    fn read_entry(xlsx: &[u8], name: &str) -> String {
+        let mut archive = zip::ZipArchive::new(Cursor::new(xlsx)).unwrap();
+        let mut content = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    #[actix_web::test]
+    async fn test_xlsx_response() {
+        let res = export().response(&devices(2), "设备.xlsx").unwrap();
+        assert_eq!(res.headers().get("content-type").unwrap(), XLSX_MIME);
+        let disposition = res.headers().get("content-disposition").unwrap();
+        assert!(disposition.to_str().unwrap().starts_with("attachment;"));
+
+        let body = to_bytes(res.into_body()).await.unwrap();
+        let workbook = read_entry(&body, "xl/workbook.xml");
+        assert!(workbook.contains("name=\"设备\""), "{}", workbook);
+        let strings = read_entry(&body, "xl/sharedStrings.xml");
+        for text in ["名称", "端口", "在线", "创建时间", "路由器-1"] {
+            assert!(strings.contains(text), "{}", strings);
+        }
+        assert!(!strings.contains("hunter2"));
+        let sheet = read_entry(&body, "xl/worksheets/sheet1.xml");
+        assert!(sheet.contains("<v>8080</v>"), "{}", sheet);
+        assert!(sheet.contains("t=\"b\""), "{}", sheet);
+        // 2024-05-01 08:00:00对应的Excel序列值
+        assert!(sheet.contains("<v>45413.333333333"), "{}", sheet);
+    }
+
+    #[test]
+    fn test_max_rows() {
+        let err = export().max_rows(2).write(&devices(3)).unwrap_err();
+        assert_eq!(err.err.code(), 1012);
+        assert!(export().max_rows(3).write(&devices(3)).is_ok());
+    }
+
+    #[derive(Serialize)]
+    struct Alarm {
+        id: u32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+    }
+
+    #[test]
+    fn test_optional_column() {
+        let export = ExcelExport::new("告警").columns(&[("id", "编号"), ("note", "备注")]);
+        let alarms: QueryOutput<Alarm> = vec![
+            Alarm { id: 1, note: None },
+            Alarm {
+                id: 2,
+                note: Some("端口异常".into()),
+            },
+        ]
+        .into();
+        let xlsx = export.write(&alarms).unwrap();
+        assert!(read_entry(&xlsx, "xl/sharedStrings.xml").contains("端口异常"));
+
+        // 当前页所有行都省略了该字段时仍然导出，写为空列
+        let alarms: QueryOutput<Alarm> = vec![Alarm { id: 1, note: None }].into();
+        let xlsx = export.write(&alarms).unwrap();
+        assert!(read_entry(&xlsx, "xl/sharedStrings.xml").contains("备注"));
+        let sheet = read_entry(&xlsx, "xl/worksheets/sheet1.xml");
+        assert!(sheet.contains("<c r=\"A2\""), "{}", sheet);
+        assert!(!sheet.contains("<c r=\"B2\""), "{}", sheet);
+    }
+}
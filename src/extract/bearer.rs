@@ -0,0 +1,144 @@
+use crate::define::{ExtraDescError, TokenMalformed, TokenMissing};
+use crate::err::Error;
+use crate::middleware::request_locale;
+use actix_web::dev::Payload;
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::{FromRequest, HttpRequest};
+use futures_util::future::{ready, Ready};
+use std::fmt::{self, Display};
+use std::ops::Deref;
+
+/// 401标准错误，渲染时带上WWW-Authenticate: Bearer响应头
+pub(crate) fn unauthorized(detail: ExtraDescError) -> actix_web::Error {
+    Error::from(detail)
+        .header(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"))
+        .into()
+}
+
+/// RFC 6750中b64token允许的字符
+fn is_token_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~' | b'+' | b'/' | b'=')
+}
+
+/// 从Authorization头中读取Bearer令牌
+pub(crate) fn bearer_token(req: &HttpRequest) -> Result<String, ExtraDescError> {
+    let locale = request_locale(req);
+    let value = req.headers().get(header::AUTHORIZATION).ok_or_else(|| {
+        TokenMissing.from_desc(locale.pick("缺少Authorization头", "missing authorization header"))
+    })?;
+    let malformed = || {
+        TokenMalformed.from_desc(locale.pick(
+            "Authorization头格式错误，应为Bearer <token>",
+            "malformed authorization header, expected Bearer <token>",
+        ))
+    };
+    let value = value.to_str().map_err(|_| malformed())?;
+    let (scheme, token) = value.trim().split_once(' ').ok_or_else(malformed)?;
+    let token = token.trim();
+    if !scheme.eq_ignore_ascii_case("bearer")
+        || token.is_empty()
+        || !token.bytes().all(is_token_char)
+    {
+        return Err(malformed());
+    }
+    Ok(token.to_string())
+}
+
+/// Authorization头中的Bearer令牌，缺失或格式错误时返回401标准错误结构
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BearerToken(pub String);
+
+impl BearerToken {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl Deref for BearerToken {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for BearerToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromRequest for BearerToken {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(bearer_token(req).map(BearerToken).map_err(unauthorized))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, web, App};
+    use serde_json::Value;
+
+    async fn whoami(token: BearerToken) -> String {
+        token.into_inner()
+    }
+
+    async fn call(authorization: Option<&str>) -> (StatusCode, Option<String>, web::Bytes) {
+        let app = test::init_service(App::new().route("/", web::get().to(whoami))).await;
+        let mut req = test::TestRequest::get().uri("/");
+        if let Some(value) = authorization {
+            req = req.insert_header((header::AUTHORIZATION, value));
+        }
+        let res = test::call_service(&app, req.to_request()).await;
+        let status = res.status();
+        let challenge = res
+            .headers()
+            .get(header::WWW_AUTHENTICATE)
+            .map(|value| value.to_str().unwrap().to_string());
+        (status, challenge, test::read_body(res).await)
+    }
+
+    fn code(body: &[u8]) -> Value {
+        let body: Value = serde_json::from_slice(body).unwrap();
+        body["error"]["details"][0]["code"].clone()
+    }
+
+    #[actix_web::test]
+    async fn test_valid_token() {
+        let (status, _, body) = call(Some("Bearer abc.def-123")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "abc.def-123");
+    }
+
+    #[actix_web::test]
+    async fn test_missing_header() {
+        let (status, challenge, body) = call(None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(challenge.as_deref(), Some("Bearer"));
+        assert_eq!(code(&body), 6003);
+    }
+
+    #[actix_web::test]
+    async fn test_wrong_scheme_and_garbage() {
+        for value in [
+            "Basic dXNlcjpwYXNz",
+            "Bearer abc def",
+            "Bearer a\"b",
+            "Bearer",
+        ] {
+            let (status, challenge, body) = call(Some(value)).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", value);
+            assert_eq!(challenge.as_deref(), Some("Bearer"));
+            assert_eq!(code(&body), 6004);
+        }
+    }
+}
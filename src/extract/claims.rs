@@ -0,0 +1,180 @@
+use super::bearer::{bearer_token, unauthorized};
+use crate::define::{ConfigurationInvalid, TokenExpired, TokenInvalid, TokenMalformed};
+use crate::err::Error;
+use crate::middleware::request_locale;
+use actix_web::dev::Payload;
+use actix_web::http::StatusCode;
+use actix_web::{FromRequest, HttpRequest};
+use futures_util::future::{ready, Ready};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde::de::DeserializeOwned;
+use std::ops::Deref;
+
+/// Claims使用的密钥和校验规则，通过app_data设置
+///
+/// # Example
+///
+/// ```ignore
+/// App::new().app_data(JwtConfig::hs256(b"secret"))
+/// ```
+#[derive(Clone)]
+pub struct JwtConfig {
+    key: DecodingKey,
+    validation: Validation,
+}
+
+impl JwtConfig {
+    pub fn new(key: DecodingKey, validation: Validation) -> Self {
+        JwtConfig { key, validation }
+    }
+
+    pub fn hs256(secret: &[u8]) -> Self {
+        Self::new(
+            DecodingKey::from_secret(secret),
+            Validation::new(Algorithm::HS256),
+        )
+    }
+}
+
+/// 校验Bearer令牌中的JWT并解析出载荷
+///
+/// 过期返回TokenExpired，无法解析返回TokenMalformed，签名、签发者等校验失败返回TokenInvalid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Claims<T>(pub T);
+
+impl<T> Claims<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Claims<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned> Claims<T> {
+    fn decode(req: &HttpRequest) -> Result<Self, actix_web::Error> {
+        let locale = request_locale(req);
+        let config = req.app_data::<JwtConfig>().ok_or_else(|| {
+            Error::new(StatusCode::INTERNAL_SERVER_ERROR).err(
+                ConfigurationInvalid
+                    .from_desc(locale.pick("未配置JwtConfig", "JwtConfig not configured")),
+            )
+        })?;
+        let token = bearer_token(req).map_err(unauthorized)?;
+        match decode::<T>(&token, &config.key, &config.validation) {
+            Ok(data) => Ok(Claims(data.claims)),
+            Err(e) => {
+                let detail = match e.kind() {
+                    ErrorKind::ExpiredSignature => {
+                        TokenExpired.from_desc(locale.pick("访问令牌已过期", "token expired"))
+                    }
+                    ErrorKind::InvalidToken
+                    | ErrorKind::Base64(_)
+                    | ErrorKind::Json(_)
+                    | ErrorKind::Utf8(_) => TokenMalformed.from_desc(format!(
+                        "{}: {}",
+                        locale.pick("访问令牌格式错误", "malformed token"),
+                        e
+                    )),
+                    _ => TokenInvalid.from_desc(format!(
+                        "{}: {}",
+                        locale.pick("访问令牌无效", "invalid token"),
+                        e
+                    )),
+                };
+                Err(unauthorized(detail))
+            }
+        }
+    }
+}
+
+impl<T: DeserializeOwned> FromRequest for Claims<T> {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Self::decode(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header;
+    use actix_web::{test, web, App};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::Value;
+
+    #[derive(Serialize, Deserialize)]
+    struct User {
+        sub: String,
+        exp: u64,
+    }
+
+    async fn whoami(user: Claims<User>) -> String {
+        user.sub.clone()
+    }
+
+    fn token(exp: u64, secret: &[u8]) -> String {
+        let user = User {
+            sub: "operator".to_string(),
+            exp,
+        };
+        encode(&Header::default(), &user, &EncodingKey::from_secret(secret)).unwrap()
+    }
+
+    fn now() -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    async fn call(token: &str) -> (StatusCode, web::Bytes) {
+        let app = test::init_service(
+            App::new()
+                .app_data(JwtConfig::hs256(b"secret"))
+                .route("/", web::get().to(whoami)),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/")
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        (res.status(), test::read_body(res).await)
+    }
+
+    fn code(body: &[u8]) -> Value {
+        let body: Value = serde_json::from_slice(body).unwrap();
+        body["error"]["details"][0]["code"].clone()
+    }
+
+    #[actix_web::test]
+    async fn test_valid_claims() {
+        let (status, body) = call(&token(now() + 600, b"secret")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "operator");
+    }
+
+    #[actix_web::test]
+    async fn test_expired_token() {
+        let (status, body) = call(&token(now() - 600, b"secret")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(code(&body), 6005);
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_token() {
+        let (_, body) = call(&token(now() + 600, b"other")).await;
+        assert_eq!(code(&body), 6006);
+        let (_, body) = call("not-a-jwt").await;
+        assert_eq!(code(&body), 6004);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::define::{ConfigurationInvalid, InvalidInput, Result};
+use crate::err::Error;
+use crate::middleware::request_locale;
+use actix_web::dev::Payload;
+use actix_web::http::{header, StatusCode};
+use actix_web::{FromRequest, HttpRequest};
+use futures_util::future::{ready, Ready};
+use std::fmt::{self, Display};
+use std::net::{IpAddr, SocketAddr};
+use std::ops::Deref;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    fn parse(value: &str) -> Option<Cidr> {
+        let (addr, prefix) = match value.trim().split_once('/') {
+            Some((addr, prefix)) => (addr.parse::<IpAddr>().ok()?, Some(prefix.parse().ok()?)),
+            None => (value.trim().parse::<IpAddr>().ok()?, None),
+        };
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Cidr { addr, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 受信任的反向代理网段，通过app_data设置，未设置时不信任任何代理头
+///
+/// # Example
+///
+/// ```ignore
+/// App::new().app_data(TrustedProxies::parse(["10.0.0.0/8", "::1"])?)
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustedProxies(Vec<Cidr>);
+
+impl TrustedProxies {
+    /// 解析CIDR列表，不带前缀长度时表示单个地址
+    pub fn parse<'a, I: IntoIterator<Item = &'a str>>(cidrs: I) -> Result<Self> {
+        cidrs
+            .into_iter()
+            .map(|cidr| {
+                Cidr::parse(cidr).ok_or_else(|| {
+                    ConfigurationInvalid.from_desc(format!("无效的代理网段: {}", cidr))
+                })
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(TrustedProxies)
+    }
+
+    pub fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|cidr| cidr.contains(ip))
+    }
+}
+
+/// 解析单个节点地址，支持IPv4、IPv6、带端口和方括号的形式
+fn parse_node(value: &str) -> Option<IpAddr> {
+    let value = value.trim().trim_matches('"');
+    if let Some(rest) = value.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+    value
+        .parse::<IpAddr>()
+        .ok()
+        .or_else(|| value.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+/// 从Forwarded（RFC 7239）中读取for参数，从左到右依次为客户端和各级代理
+fn forwarded_chain(value: &str) -> Option<Vec<IpAddr>> {
+    value
+        .split(',')
+        .map(|element| {
+            element
+                .split(';')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case("for"))
+                .and_then(|(_, value)| parse_node(value))
+        })
+        .collect()
+}
+
+fn forwarded_for_chain(value: &str) -> Option<Vec<IpAddr>> {
+    value.split(',').map(parse_node).collect()
+}
+
+/// 对端是受信任的代理时，从右向左跳过受信任的代理找到客户端地址；代理头格式错误时使用对端地址
+pub(crate) fn client_ip(req: &HttpRequest) -> Option<IpAddr> {
+    let peer = req.peer_addr()?.ip();
+    let trusted = match req.app_data::<TrustedProxies>() {
+        Some(trusted) if trusted.is_trusted(peer) => trusted,
+        _ => return Some(peer),
+    };
+    let header_value = |name| {
+        let values: Vec<&str> = req
+            .headers()
+            .get_all(name)
+            .filter_map(|value| value.to_str().ok())
+            .collect();
+        (!values.is_empty()).then(|| values.join(","))
+    };
+    let chain = match header_value(header::FORWARDED) {
+        Some(value) => forwarded_chain(&value),
+        None => header_value(header::X_FORWARDED_FOR).and_then(|value| forwarded_for_chain(&value)),
+    };
+    let chain = match chain {
+        Some(chain) if !chain.is_empty() => chain,
+        _ => return Some(peer),
+    };
+    chain
+        .iter()
+        .rev()
+        .find(|ip| !trusted.is_trusted(**ip))
+        .or_else(|| chain.first())
+        .copied()
+}
+
+/// 客户端的真实IP，根据app_data中的TrustedProxies处理X-Forwarded-For和Forwarded头
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+impl ClientIp {
+    pub fn into_inner(self) -> IpAddr {
+        self.0
+    }
+}
+
+impl Deref for ClientIp {
+    type Target = IpAddr;
+
+    fn deref(&self) -> &IpAddr {
+        &self.0
+    }
+}
+
+impl Display for ClientIp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromRequest for ClientIp {
+    type Error = actix_web::Error;
+    type Future = Ready<std::result::Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(client_ip(req).map(ClientIp).ok_or_else(|| {
+            let desc = request_locale(req).pick("无法获取客户端地址", "client address unavailable");
+            Error::new(StatusCode::BAD_REQUEST)
+                .err(InvalidInput.from_desc(desc))
+                .into()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn resolve(peer: &str, headers: &[(&str, &str)]) -> IpAddr {
+        let mut req = TestRequest::default()
+            .peer_addr(peer.parse().unwrap())
+            .app_data(TrustedProxies::parse(["10.0.0.0/8", "fd00::/8"]).unwrap());
+        for header in headers {
+            req = req.insert_header(*header);
+        }
+        client_ip(&req.to_http_request()).unwrap()
+    }
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_direct_connection() {
+        assert_eq!(resolve("203.0.113.7:5000", &[]), ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn test_trusted_hops() {
+        let headers = [("x-forwarded-for", "198.51.100.1, 10.1.2.3")];
+        assert_eq!(resolve("10.0.0.1:5000", &headers), ip("198.51.100.1"));
+
+        let headers = [(
+            "forwarded",
+            r#"for="[2001:db8::1]:4711";proto=https, for=10.1.2.3"#,
+        )];
+        assert_eq!(resolve("[fd00::1]:5000", &headers), ip("2001:db8::1"));
+    }
+
+    #[test]
+    fn test_spoofed_header_from_untrusted_peer() {
+        let headers = [("x-forwarded-for", "1.1.1.1")];
+        assert_eq!(resolve("203.0.113.7:5000", &headers), ip("203.0.113.7"));
+
+        let headers = [("x-forwarded-for", "1.1.1.1, 203.0.113.9")];
+        assert_eq!(resolve("10.0.0.1:5000", &headers), ip("203.0.113.9"));
+    }
+
+    #[test]
+    fn test_malformed_header() {
+        let headers = [("x-forwarded-for", "not-an-ip, 10.1.2.3")];
+        assert_eq!(resolve("10.0.0.1:5000", &headers), ip("10.0.0.1"));
+        let headers = [("forwarded", "for=unknown")];
+        assert_eq!(resolve("10.0.0.1:5000", &headers), ip("10.0.0.1"));
+    }
+
+    #[test]
+    fn test_cidr() {
+        let proxies = TrustedProxies::parse(["192.168.0.0/16", "::1"]).unwrap();
+        assert!(proxies.is_trusted(ip("192.168.3.4")));
+        assert!(!proxies.is_trusted(ip("192.169.0.1")));
+        assert!(proxies.is_trusted(ip("::1")));
+        assert!(TrustedProxies::parse(["10.0.0.0/33"]).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use crate::config::JsonConfigBuilder;
+use actix_web::dev::Payload;
+use actix_web::error::JsonPayloadError;
+use actix_web::http::header;
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::{FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use futures_util::StreamExt;
+use serde::de::DeserializeOwned;
+use std::ops::{Deref, DerefMut};
+use std::sync::OnceLock;
+
+/// 请求体小于该字节数时直接使用serde_json，simd-json的优势不足以抵消额外开销
+pub const SIMD_MIN_BYTES: usize = 4 * 1024;
+
+/// 使用simd-json解析请求体的json提取器，用法与web::Json相同
+///
+/// 大小限制、Content-Type和错误响应使用app_data中的JsonConfigBuilder（DefaultConfigs会自动注册），
+/// 出错时返回与web::Json相同的标准错误结构和错误码。请求体过小或CPU不支持simd时使用serde_json解析
+///
+/// # Example
+///
+/// ```ignore
+/// async fn ingest(body: FastJson<TelemetryBatch>) -> HttpResult<HttpResponse> {
+///     save_telemetry(body.into_inner()).await?;
+///     Ok(HttpResponse::NoContent().finish())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FastJson<T>(pub T);
+
+impl<T> FastJson<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for FastJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for FastJson<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+/// 运行时检测CPU是否支持simd-json使用的指令集
+fn simd_supported() -> bool {
+    static SUPPORTED: OnceLock<bool> = OnceLock::new();
+    *SUPPORTED.get_or_init(|| {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            std::is_x86_feature_detected!("avx2")
+                || (std::is_x86_feature_detected!("sse4.2")
+                    && std::is_x86_feature_detected!("pclmulqdq"))
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            true
+        }
+        #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+        {
+            false
+        }
+    })
+}
+
+/// 解析json，simd-json会原地修改缓冲区，解析失败时用原始数据块交给serde_json重新解析，
+/// 使错误信息与web::Json完全相同；成功时不需要额外复制
+fn parse<T: DeserializeOwned>(
+    mut body: BytesMut,
+    chunks: &[Bytes],
+) -> Result<T, serde_json::Error> {
+    if body.len() >= SIMD_MIN_BYTES && simd_supported() {
+        if let Ok(value) = simd_json::serde::from_slice(&mut body) {
+            return Ok(value);
+        }
+        return serde_json::from_slice(&chunks.concat());
+    }
+    serde_json::from_slice(&body)
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for FastJson<T> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let config = req
+            .app_data::<JsonConfigBuilder>()
+            .cloned()
+            .unwrap_or_default();
+        let req = req.clone();
+        let limit = config.body_limit();
+        let length = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<usize>().ok());
+        let mut payload = payload.take();
+
+        Box::pin(async move {
+            if !config.accepts_content_type(&req) {
+                return Err(config.handle_error(JsonPayloadError::ContentType, &req));
+            }
+            if let Some(length) = length.filter(|length| *length > limit) {
+                let err = JsonPayloadError::OverflowKnownLength { length, limit };
+                return Err(config.handle_error(err, &req));
+            }
+
+            let mut body = BytesMut::with_capacity(length.unwrap_or(0).min(limit));
+            let mut chunks = Vec::new();
+            while let Some(chunk) = payload.next().await {
+                let chunk =
+                    chunk.map_err(|e| config.handle_error(JsonPayloadError::Payload(e), &req))?;
+                if body.len() + chunk.len() > limit {
+                    let err = JsonPayloadError::Overflow { limit };
+                    return Err(config.handle_error(err, &req));
+                }
+                body.extend_from_slice(&chunk);
+                chunks.push(chunk);
+            }
+            match parse(body, &chunks) {
+                Ok(value) => Ok(FastJson(value)),
+                Err(e) => Err(config.handle_error(JsonPayloadError::Deserialize(e), &req)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DefaultConfigs;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, web, App};
+    use serde_json::{json, Value};
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Sample {
+        device: String,
+        values: Vec<f64>,
+    }
+
+    async fn fast(body: FastJson<Sample>) -> String {
+        body.values.len().to_string()
+    }
+
+    async fn standard(body: web::Json<Sample>) -> String {
+        body.values.len().to_string()
+    }
+
+    async fn call(path: &str, content_type: &str, body: Vec<u8>) -> (StatusCode, Value) {
+        let app = test::init_service(
+            App::new()
+                .configure(|cfg| DefaultConfigs::new().json_limit(64 * 1024).apply(cfg))
+                .route("/fast", web::post().to(fast))
+                .route("/standard", web::post().to(standard)),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri(path)
+            .insert_header(("content-type", content_type))
+            .set_payload(body)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let status = res.status();
+        let body = test::read_body(res).await;
+        let body = serde_json::from_slice(&body)
+            .unwrap_or(Value::String(String::from_utf8_lossy(&body).into_owned()));
+        (status, body)
+    }
+
+    async fn assert_parity(content_type: &str, body: Vec<u8>) -> (StatusCode, Value) {
+        let fast = call("/fast", content_type, body.clone()).await;
+        let standard = call("/standard", content_type, body).await;
+        assert_eq!(fast, standard);
+        fast
+    }
+
+    fn sample(count: usize) -> Vec<u8> {
+        serde_json::to_vec(&json!({"device": "router-01", "values": vec![1.5; count]})).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_parse() {
+        // 小请求体走serde_json，大请求体走simd-json
+        for count in [2, 4000] {
+            let (status, body) = assert_parity("application/json", sample(count)).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body, json!(count));
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_malformed_parity() {
+        let (status, body) = assert_parity("application/json", b"{\"device\": 1}".to_vec()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["details"][0]["code"], 2006);
+
+        // 超过SIMD_MIN_BYTES的错误数据走simd-json，错误响应同样与web::Json完全相同
+        let mut large = sample(4000);
+        large.truncate(large.len() - 3);
+        let (status, body) = assert_parity("application/json", large).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"]["details"][0]["desc"]
+            .as_str()
+            .unwrap()
+            .contains("EOF while parsing"));
+
+        let mut large = sample(4000);
+        large[20] = b'x';
+        let (status, _) = assert_parity("application/json", large).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_overflow_parity() {
+        let (status, body) = assert_parity("application/json", sample(20000)).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body["error"]["details"][0]["code"], 2009);
+    }
+
+    #[actix_web::test]
+    async fn test_content_type_parity() {
+        let (status, _) = assert_parity("text/plain", sample(2)).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[actix_web::test]
+    async fn test_content_type_not_required() {
+        let app = test::init_service(
+            App::new()
+                .configure(|cfg| {
+                    DefaultConfigs::new()
+                        .json(JsonConfigBuilder::default().content_type_required(false))
+                        .apply(cfg)
+                })
+                .route("/fast", web::post().to(fast))
+                .route("/standard", web::post().to(standard)),
+        )
+        .await;
+        // 不要求Content-Type时任意类型都按json解析，与web::Json一致
+        for path in ["/fast", "/standard"] {
+            let req = test::TestRequest::post()
+                .uri(path)
+                .insert_header(("content-type", "text/plain"))
+                .set_payload(sample(2))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), StatusCode::OK, "{}", path);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use crate::config::{unsupported_content_type, JsonConfigBuilder};
+use crate::err::Error;
+use crate::middleware::request_locale;
+use actix_web::dev::Payload;
+use actix_web::http::{header, StatusCode};
+use actix_web::web::{Form, Json, JsonBody};
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+use std::ops::{Deref, DerefMut};
+
+/// JsonOrForm接受的Content-Type
+const ACCEPTED: [&str; 2] = ["application/json", "application/x-www-form-urlencoded"];
+
+/// JsonOrForm的配置，通过app_data设置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JsonOrFormConfig {
+    /// 缺少Content-Type时按json解析，为false时返回415，默认true
+    pub missing_as_json: bool,
+}
+
+impl Default for JsonOrFormConfig {
+    fn default() -> Self {
+        JsonOrFormConfig {
+            missing_as_json: true,
+        }
+    }
+}
+
+/// 根据Content-Type按json或urlencoded表单解析请求体
+///
+/// 分别使用JsonConfig和FormConfig的大小限制和错误处理，错误响应与web::Json、web::Form相同；
+/// 缺少Content-Type时按JsonConfigBuilder（DefaultConfigs会自动注册）的设置解析json。
+/// 其它Content-Type返回415，desc和extra.allowed中列出可接受的类型
+///
+/// # Example
+///
+/// ```ignore
+/// async fn notify(body: JsonOrForm<PartnerEvent>) -> HttpResult<HttpResponse> {
+///     handle_event(body.into_inner()).await?;
+///     Ok(HttpResponse::NoContent().finish())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonOrForm<T>(pub T);
+
+impl<T> JsonOrForm<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for JsonOrForm<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for JsonOrForm<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+fn unsupported(req: &HttpRequest) -> actix_web::Error {
+    let locale = request_locale(req);
+    let mut detail = unsupported_content_type(locale, req);
+    detail.desc = format!(
+        "{}, {}: {}",
+        detail.desc,
+        locale.pick("可选", "allowed"),
+        ACCEPTED.join(", ")
+    );
+    Error::new(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+        .err(detail.with_extra("allowed", ACCEPTED))
+        .into()
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for JsonOrForm<T> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        if !req.headers().contains_key(header::CONTENT_TYPE) {
+            let config = req
+                .app_data::<JsonOrFormConfig>()
+                .copied()
+                .unwrap_or_default();
+            if !config.missing_as_json {
+                let err = unsupported(req);
+                return Box::pin(async move { Err(err) });
+            }
+            let json = req
+                .app_data::<JsonConfigBuilder>()
+                .cloned()
+                .unwrap_or_default();
+            let body = JsonBody::<T>::new(req, payload, None, false).limit(json.body_limit());
+            let req = req.clone();
+            return Box::pin(async move {
+                body.await
+                    .map(JsonOrForm)
+                    .map_err(|e| json.handle_error(e, &req))
+            });
+        }
+
+        let mime = req.mime_type().ok().flatten();
+        let is_json = mime.as_ref().is_some_and(|mime| {
+            mime.subtype() == actix_web::mime::JSON || mime.suffix() == Some(actix_web::mime::JSON)
+        });
+        let is_form = mime
+            .as_ref()
+            .is_some_and(|mime| mime.essence_str() == ACCEPTED[1]);
+        if is_json {
+            let json = Json::<T>::from_request(req, payload);
+            Box::pin(async move { json.await.map(|json| JsonOrForm(json.into_inner())) })
+        } else if is_form {
+            let form = Form::<T>::from_request(req, payload);
+            Box::pin(async move { form.await.map(|form| JsonOrForm(form.into_inner())) })
+        } else {
+            let err = unsupported(req);
+            Box::pin(async move { Err(err) })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DefaultConfigs;
+    use actix_web::{test, web, App};
+    use serde_json::{json, Value};
+
+    #[derive(Deserialize)]
+    struct Event {
+        device: String,
+        level: u8,
+    }
+
+    async fn notify(body: JsonOrForm<Event>) -> String {
+        format!("{}:{}", body.device, body.level)
+    }
+
+    async fn call(
+        config: JsonOrFormConfig,
+        content_type: Option<&str>,
+        body: &str,
+    ) -> (StatusCode, Value) {
+        let app = test::init_service(
+            App::new()
+                .configure(|cfg| DefaultConfigs::new().json_limit(64).apply(cfg))
+                .app_data(config)
+                .route("/notify", web::post().to(notify)),
+        )
+        .await;
+        let mut req = test::TestRequest::post()
+            .uri("/notify")
+            .set_payload(body.to_string());
+        if let Some(content_type) = content_type {
+            req = req.insert_header(("content-type", content_type));
+        }
+        let res = test::call_service(&app, req.to_request()).await;
+        let status = res.status();
+        let body = test::read_body(res).await;
+        let body = serde_json::from_slice(&body)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&body).into_owned()));
+        (status, body)
+    }
+
+    #[actix_web::test]
+    async fn test_json_and_form() {
+        let config = JsonOrFormConfig::default();
+        let json_body = r#"{"device": "router-01", "level": 2}"#;
+        for content_type in ["application/json", "application/json; charset=utf-8"] {
+            let (status, body) = call(config, Some(content_type), json_body).await;
+            assert_eq!(status, StatusCode::OK, "{}", content_type);
+            assert_eq!(body, json!("router-01:2"));
+        }
+        for content_type in [
+            "application/x-www-form-urlencoded",
+            "application/x-www-form-urlencoded; charset=utf-8",
+        ] {
+            let (status, body) = call(config, Some(content_type), "device=router-01&level=2").await;
+            assert_eq!(status, StatusCode::OK, "{}", content_type);
+            assert_eq!(body, json!("router-01:2"));
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_parse_errors() {
+        let config = JsonOrFormConfig::default();
+        let (status, body) = call(config, Some("application/json"), r#"{"device": 1}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["details"][0]["code"], 2006);
+
+        let (status, body) = call(
+            config,
+            Some("application/x-www-form-urlencoded"),
+            "device=router-01&level=high",
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["status"], 400);
+
+        // 使用JsonConfig的大小限制
+        let long = format!(r#"{{"device": "{}", "level": 2}}"#, "x".repeat(100));
+        let (status, _) = call(config, Some("application/json"), &long).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_web::test]
+    async fn test_unsupported() {
+        let (status, body) = call(
+            JsonOrFormConfig::default(),
+            Some("text/plain"),
+            "device=router-01",
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let detail = &body["error"]["details"][0];
+        assert_eq!(
+            detail["extra"]["allowed"],
+            json!(["application/json", "application/x-www-form-urlencoded"])
+        );
+        assert!(detail["desc"]
+            .as_str()
+            .unwrap()
+            .contains("application/x-www-form-urlencoded"));
+    }
+
+    #[actix_web::test]
+    async fn test_missing_content_type() {
+        let json_body = r#"{"device": "router-01", "level": 2}"#;
+        let (status, body) = call(JsonOrFormConfig::default(), None, json_body).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!("router-01:2"));
+
+        let (status, body) = call(JsonOrFormConfig::default(), None, "{").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["details"][0]["code"], 2006);
+
+        let config = JsonOrFormConfig {
+            missing_as_json: false,
+        };
+        let (status, _) = call(config, None, json_body).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
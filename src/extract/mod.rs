@@ -0,0 +1,30 @@
+mod bearer;
+#[cfg(feature = "jwt")]
+mod claims;
+mod client_ip;
+#[cfg(feature = "simd")]
+mod fast_json;
+mod json_or_form;
+mod ndjson;
+mod pagination;
+mod payload;
+#[cfg(feature = "qs")]
+mod qs;
+#[cfg(feature = "validator")]
+mod validated;
+
+pub use bearer::BearerToken;
+#[cfg(feature = "jwt")]
+pub use claims::{Claims, JwtConfig};
+pub(crate) use client_ip::client_ip;
+pub use client_ip::{ClientIp, TrustedProxies};
+#[cfg(feature = "simd")]
+pub use fast_json::{FastJson, SIMD_MIN_BYTES};
+pub use json_or_form::{JsonOrForm, JsonOrFormConfig};
+pub use ndjson::{NdJson, NdJsonConfig};
+pub use pagination::{Pagination, PaginationConfig};
+pub use payload::{LimitedBytes, LimitedString, PayloadLimit};
+#[cfg(feature = "qs")]
+pub use qs::{QsQuery, QsQueryConfig};
+#[cfg(feature = "validator")]
+pub use validated::ValidatedJson;
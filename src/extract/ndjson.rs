@@ -0,0 +1,379 @@
+use crate::config::{payload_too_large, unsupported_content_type};
+use crate::define::{ExtraDescError, InvalidData, InvalidMessageData, Locale, Result};
+use crate::err::{Error, ErrorList};
+use crate::middleware::request_locale;
+use actix_web::dev::Payload;
+use actix_web::http::{header, StatusCode};
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::{FromRequest, HttpRequest};
+use futures_util::future::{ready, Ready};
+use futures_util::stream::{self, LocalBoxStream};
+use futures_util::StreamExt;
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+
+/// NdJson的大小限制，通过app_data设置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NdJsonConfig {
+    /// 单行最大字节数，默认64KB
+    pub max_line: usize,
+    /// 请求体最大字节数，默认100MB
+    pub limit: usize,
+}
+
+impl Default for NdJsonConfig {
+    fn default() -> Self {
+        NdJsonConfig {
+            max_line: 64 * 1024,
+            limit: 100 * 1024 * 1024,
+        }
+    }
+}
+
+/// 解析某一行时的错误，fatal表示无法继续读取后续行
+struct LineError {
+    err: ExtraDescError,
+    fatal: bool,
+}
+
+struct LineReader {
+    payload: Payload,
+    buf: BytesMut,
+    line: usize,
+    total: usize,
+    eof: bool,
+    done: bool,
+    /// 正在丢弃超长行中还没有读到的部分
+    skipping: bool,
+    config: NdJsonConfig,
+    locale: Locale,
+}
+
+impl LineReader {
+    fn line_too_long(&self, line: usize) -> ExtraDescError {
+        let desc = format!(
+            "{} {} {} {}",
+            self.locale.pick("第", "line"),
+            line,
+            self.locale.pick("行超过长度限制", "exceeds the limit of"),
+            self.config.max_line
+        );
+        InvalidMessageData.from_desc(desc).with_extra("line", line)
+    }
+
+    fn fatal(&mut self, err: ExtraDescError) -> LineError {
+        self.done = true;
+        LineError { err, fatal: true }
+    }
+
+    /// 读取下一个非空行，返回行号和内容
+    async fn next_line(&mut self) -> Option<std::result::Result<(usize, Bytes), LineError>> {
+        loop {
+            if self.done {
+                return None;
+            }
+            let newline = self.buf.iter().position(|b| *b == b'\n');
+            if self.skipping {
+                match newline {
+                    Some(pos) => {
+                        let _ = self.buf.split_to(pos + 1);
+                        self.skipping = false;
+                        continue;
+                    }
+                    None => self.buf.clear(),
+                }
+            } else if let Some(pos) = newline {
+                let line = self.buf.split_to(pos + 1).freeze();
+                self.line += 1;
+                let line = line.slice(..pos);
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+                if line.len() > self.config.max_line {
+                    let err = self.line_too_long(self.line);
+                    return Some(Err(LineError { err, fatal: false }));
+                }
+                return Some(Ok((self.line, line)));
+            } else if self.buf.len() > self.config.max_line {
+                // 超长行跨越多个数据块时丢弃到下一个换行，与在同一块中读到换行时的结果一致
+                self.line += 1;
+                self.buf.clear();
+                self.skipping = true;
+                let err = self.line_too_long(self.line);
+                return Some(Err(LineError { err, fatal: false }));
+            }
+            if self.eof {
+                self.done = true;
+                if self.buf.iter().all(u8::is_ascii_whitespace) {
+                    return None;
+                }
+                self.line += 1;
+                return Some(Ok((self.line, self.buf.split().freeze())));
+            }
+            match self.payload.next().await {
+                Some(Ok(chunk)) => {
+                    self.total += chunk.len();
+                    if self.total > self.config.limit {
+                        let err = payload_too_large(self.locale, self.config.limit, None);
+                        return Some(Err(self.fatal(err)));
+                    }
+                    self.buf.extend_from_slice(&chunk);
+                }
+                Some(Err(e)) => {
+                    let desc = format!(
+                        "{}: {}",
+                        self.locale
+                            .pick("请求数据读取失败", "failed to read payload"),
+                        e
+                    );
+                    let err = InvalidData.from_desc(desc);
+                    return Some(Err(self.fatal(err)));
+                }
+                None => self.eof = true,
+            }
+        }
+    }
+}
+
+fn parse_line<T: DeserializeOwned>(
+    locale: Locale,
+    line: usize,
+    bytes: &[u8],
+) -> std::result::Result<T, LineError> {
+    serde_json::from_slice(bytes).map_err(|e| {
+        let desc = format!(
+            "{} {} {}: {}",
+            locale.pick("第", "line"),
+            line,
+            locale.pick("行json解析错误", "json parse error"),
+            e
+        );
+        LineError {
+            err: InvalidMessageData.from_desc(desc).with_extra("line", line),
+            fatal: false,
+        }
+    })
+}
+
+/// 逐行读取application/x-ndjson请求体的提取器，不会把整个请求体缓存在内存中
+///
+/// into_stream遇到第一个错误行即停止；collect_lenient跳过错误行，并把错误收集到ErrorList中
+///
+/// # Example
+///
+/// ```ignore
+/// async fn ingest(body: NdJson<Telemetry>) -> HttpResult<HttpResponse> {
+///     let mut stream = body.into_stream();
+///     while let Some(item) = stream.next().await {
+///         save(item?)?;
+///     }
+///     Ok(HttpResponse::NoContent().finish())
+/// }
+/// ```
+pub struct NdJson<T> {
+    reader: LineReader,
+    _marker: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned + 'static> NdJson<T> {
+    fn lines(self) -> LocalBoxStream<'static, std::result::Result<T, LineError>> {
+        let locale = self.reader.locale;
+        stream::unfold(self.reader, move |mut reader| async move {
+            let item = match reader.next_line().await? {
+                Ok((line, bytes)) => parse_line(locale, line, &bytes),
+                Err(err) => Err(err),
+            };
+            Some((item, reader))
+        })
+        .boxed_local()
+    }
+
+    /// 严格模式，返回第一个错误后结束
+    pub fn into_stream(self) -> LocalBoxStream<'static, Result<T>> {
+        self.lines()
+            .scan(false, |failed, item| {
+                if *failed {
+                    return ready(None);
+                }
+                *failed = item.is_err();
+                ready(Some(item.map_err(|e| e.err)))
+            })
+            .boxed_local()
+    }
+
+    /// 宽松模式，跳过无法解析的行；请求体过大或读取失败时停止
+    pub async fn collect_lenient(self) -> (Vec<T>, ErrorList) {
+        let mut items = Vec::new();
+        let mut errors = ErrorList::new();
+        let mut lines = self.lines();
+        while let Some(item) = lines.next().await {
+            match item {
+                Ok(item) => items.push(item),
+                Err(LineError { err, fatal }) => {
+                    errors.push(err);
+                    if fatal {
+                        break;
+                    }
+                }
+            }
+        }
+        (items, errors)
+    }
+}
+
+fn is_ndjson(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|mime| {
+            let mime = mime.trim();
+            mime.eq_ignore_ascii_case("application/x-ndjson")
+                || mime.eq_ignore_ascii_case("application/jsonl")
+        })
+        .unwrap_or(false)
+}
+
+impl<T> FromRequest for NdJson<T> {
+    type Error = actix_web::Error;
+    type Future = Ready<std::result::Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let locale = request_locale(req);
+        if !is_ndjson(req) {
+            let detail = unsupported_content_type(locale, req);
+            return ready(Err(Error::new(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+                .err(detail)
+                .into()));
+        }
+        let config = req.app_data::<NdJsonConfig>().copied().unwrap_or_default();
+        ready(Ok(NdJson {
+            reader: LineReader {
+                payload: payload.take(),
+                buf: BytesMut::new(),
+                line: 0,
+                total: 0,
+                eof: false,
+                done: false,
+                skipping: false,
+                config,
+                locale,
+            },
+            _marker: PhantomData,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::err::HttpResult;
+    use actix_web::{test, web, App, HttpResponse};
+    use serde_json::Value;
+
+    #[derive(Deserialize, Serialize)]
+    struct Telemetry {
+        id: u32,
+    }
+
+    const BODY: &str = "{\"id\": 1}\n{\"id\": 2}\n{\"id\": \n\n{\"id\": 4}";
+
+    async fn strict(body: NdJson<Telemetry>) -> HttpResult<HttpResponse> {
+        let mut stream = body.into_stream();
+        let mut ids = Vec::new();
+        while let Some(item) = stream.next().await {
+            ids.push(item?.id);
+        }
+        Ok(HttpResponse::Ok().json(ids))
+    }
+
+    async fn lenient(body: NdJson<Telemetry>) -> HttpResponse {
+        let (items, errors) = body.collect_lenient().await;
+        let errors: Vec<String> = errors.iter().map(|e| e.desc.clone()).collect();
+        HttpResponse::Ok().json(serde_json::json!({"items": items, "errors": errors}))
+    }
+
+    async fn post(path: &str, config: NdJsonConfig, body: &'static str) -> (StatusCode, Value) {
+        let app = test::init_service(
+            App::new()
+                .app_data(config)
+                .route("/strict", web::post().to(strict))
+                .route("/lenient", web::post().to(lenient)),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri(path)
+            .insert_header((header::CONTENT_TYPE, "application/x-ndjson"))
+            .set_payload(body)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let status = res.status();
+        (status, test::read_body_json(res).await)
+    }
+
+    #[actix_web::test]
+    async fn test_strict() {
+        let (status, body) = post("/strict", NdJsonConfig::default(), BODY).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let detail = &body["error"]["details"][0];
+        assert_eq!(detail["code"], 2006);
+        assert_eq!(detail["extra"]["line"], 3);
+        assert!(detail["desc"].as_str().unwrap().starts_with("第 3 行"));
+    }
+
+    #[actix_web::test]
+    async fn test_lenient() {
+        let (status, body) = post("/lenient", NdJsonConfig::default(), BODY).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body["items"],
+            serde_json::json!([{"id": 1}, {"id": 2}, {"id": 4}])
+        );
+        assert_eq!(body["errors"].as_array().unwrap().len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_line_limit() {
+        let config = NdJsonConfig {
+            max_line: 12,
+            ..Default::default()
+        };
+        let (_, body) = post("/lenient", config, "{\"id\": 1}\n{\"id\":          2}\n").await;
+        assert_eq!(body["items"], serde_json::json!([{"id": 1}]));
+        assert!(body["errors"][0]
+            .as_str()
+            .unwrap()
+            .contains("行超过长度限制"));
+    }
+
+    #[actix_web::test]
+    async fn test_line_limit_across_chunks() {
+        let config = NdJsonConfig {
+            max_line: 12,
+            ..Default::default()
+        };
+        let req = test::TestRequest::default()
+            .insert_header((header::CONTENT_TYPE, "application/x-ndjson"))
+            .app_data(config)
+            .to_http_request();
+        let body = "{\"id\": 1}\n{\"id\":                    2}\n{\"id\": 3}\n";
+        let chunks = body
+            .as_bytes()
+            .chunks(4)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect::<Vec<_>>();
+        let mut payload = Payload::from(stream::iter(chunks).boxed_local());
+        let body = NdJson::<Telemetry>::from_request(&req, &mut payload)
+            .await
+            .unwrap();
+        let (items, errors) = body.collect_lenient().await;
+        assert_eq!(
+            items.iter().map(|item| item.id).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+        assert_eq!(errors.len(), 1);
+        let err = errors.iter().next().unwrap();
+        assert_eq!(err.extra.as_ref().unwrap()["line"], 2);
+        assert!(err.desc.contains("行超过长度限制"), "{}", err.desc);
+    }
+}
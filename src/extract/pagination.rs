@@ -0,0 +1,320 @@
+use crate::define::Result as StdResult;
+use crate::define::{ExtraDescError, InvalidInput};
+use crate::err::Error;
+use crate::middleware::request_locale;
+use crate::query::{OutOfRange, QueryOutput};
+use actix_web::dev::Payload;
+use actix_web::http::StatusCode;
+use actix_web::{web, FromRequest, HttpRequest};
+use futures_util::future::{ready, Ready};
+
+/// Pagination的默认值和上限，通过app_data设置
+///
+/// # Example
+///
+/// ```ignore
+/// App::new().app_data(PaginationConfig { default_limit: 50, max_limit: 500, ..Default::default() })
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaginationConfig {
+    pub default_limit: usize,
+    pub max_limit: usize,
+    /// limit超过上限时截断为上限，为false时返回400
+    pub clamp: bool,
+    /// offset超出数据范围时的处理方式
+    pub out_of_range: OutOfRange,
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        PaginationConfig {
+            default_limit: 20,
+            max_limit: 100,
+            clamp: true,
+            out_of_range: OutOfRange::default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawPagination {
+    limit: Option<String>,
+    offset: Option<String>,
+    page: Option<String>,
+}
+
+/// 从查询参数中读取limit、offset和page（从1开始），缺省时使用PaginationConfig中的默认值
+///
+/// 同时给出offset和page时以offset为准
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pagination {
+    pub limit: usize,
+    pub offset: usize,
+    pub page: Option<usize>,
+    /// 来自PaginationConfig，传给paginate_vec_with或Paginated::out_of_range
+    pub out_of_range: OutOfRange,
+}
+
+impl Pagination {
+    /// 将limit、offset和page写入查询结果
+    pub fn apply_to<T>(&self, output: &mut QueryOutput<T>) {
+        output.limit = self.limit;
+        output.offset = Some(self.offset);
+        output.page = self.page;
+        output.update_total_pages();
+    }
+
+    /// 按配置的OutOfRange对内存中的完整列表分页，limit为0（不限制）时page为1
+    pub fn paginate_vec<T>(&self, items: Vec<T>) -> StdResult<QueryOutput<T>> {
+        let output =
+            QueryOutput::paginate_vec_with(items, self.offset, self.limit, self.out_of_range)?;
+        Ok(match (self.page, output.offset) {
+            (Some(_), Some(offset)) => output.page(offset.checked_div(self.limit).unwrap_or(0) + 1),
+            _ => output,
+        })
+    }
+
+    /// 供diesel的limit/offset使用，超出i64范围时取i64::MAX
+    pub fn sql_limit_offset(&self) -> (i64, i64) {
+        (
+            i64::try_from(self.limit).unwrap_or(i64::MAX),
+            i64::try_from(self.offset).unwrap_or(i64::MAX),
+        )
+    }
+
+    fn parse(req: &HttpRequest) -> Result<Self, ExtraDescError> {
+        let config = req
+            .app_data::<PaginationConfig>()
+            .copied()
+            .unwrap_or_default();
+        let locale = request_locale(req);
+        let raw = web::Query::<RawPagination>::from_query(req.query_string())
+            .map_err(|e| {
+                InvalidInput.from_desc(format!(
+                    "{}: {}",
+                    locale.pick("分页参数错误", "invalid pagination parameters"),
+                    e
+                ))
+            })?
+            .into_inner();
+
+        let invalid = |name: &str, value: &str| {
+            InvalidInput
+                .from_desc(format!(
+                    "{} {}: {}",
+                    locale.pick("分页参数错误", "invalid pagination parameter"),
+                    name,
+                    value
+                ))
+                .with_field(name)
+        };
+        let parse = |name: &str, value: Option<String>, min: usize| {
+            value
+                .map(|value| match value.trim().parse::<usize>() {
+                    Ok(n) if n >= min => Ok(n),
+                    _ => Err(invalid(name, &value)),
+                })
+                .transpose()
+        };
+
+        let mut limit = parse("limit", raw.limit, 1)?.unwrap_or(config.default_limit);
+        if limit > config.max_limit {
+            if !config.clamp {
+                return Err(InvalidInput
+                    .from_desc(format!(
+                        "{}: {}",
+                        locale.pick("limit超过上限", "limit exceeds maximum"),
+                        config.max_limit
+                    ))
+                    .with_field("limit"));
+            }
+            limit = config.max_limit;
+        }
+        let page = parse("page", raw.page, 1)?;
+        let offset = match parse("offset", raw.offset, 0)? {
+            Some(offset) => offset,
+            None => match page {
+                Some(page) => (page - 1)
+                    .checked_mul(limit)
+                    .ok_or_else(|| invalid("page", &page.to_string()))?,
+                None => 0,
+            },
+        };
+        Ok(Pagination {
+            limit,
+            offset,
+            page,
+            out_of_range: config.out_of_range,
+        })
+    }
+}
+
+impl FromRequest for Pagination {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            Pagination::parse(req)
+                .map_err(|detail| Error::new(StatusCode::BAD_REQUEST).err(detail).into()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App, HttpResponse};
+    use serde_json::Value;
+
+    async fn list(pagination: Pagination) -> HttpResponse {
+        let mut output = QueryOutput::<u32>::default();
+        pagination.apply_to(&mut output);
+        HttpResponse::Ok().json(serde_json::json!({
+            "limit": output.limit,
+            "offset": pagination.offset,
+            "sql": pagination.sql_limit_offset(),
+        }))
+    }
+
+    async fn call(config: PaginationConfig, query: &str) -> (StatusCode, Value) {
+        let app = test::init_service(
+            App::new()
+                .app_data(config)
+                .route("/devices", web::get().to(list)),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri(&format!("/devices{}", query))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let status = res.status();
+        (status, test::read_body_json(res).await)
+    }
+
+    #[actix_web::test]
+    async fn test_defaults() {
+        let (status, body) = call(PaginationConfig::default(), "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["limit"], 20);
+        assert_eq!(body["offset"], 0);
+
+        let (_, body) = call(PaginationConfig::default(), "?limit=10&page=3").await;
+        assert_eq!(body["offset"], 20);
+        assert_eq!(body["sql"], serde_json::json!([10, 20]));
+    }
+
+    #[actix_web::test]
+    async fn test_clamp_at_max() {
+        let config = PaginationConfig {
+            max_limit: 50,
+            ..Default::default()
+        };
+        let (status, body) = call(config, "?limit=1000000").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["limit"], 50);
+
+        let config = PaginationConfig {
+            clamp: false,
+            ..config
+        };
+        let (status, body) = call(config, "?limit=1000000").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["details"][0]["field"], "limit");
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_values() {
+        for query in ["?limit=-1", "?offset=abc", "?limit=0", "?page=0"] {
+            let (status, body) = call(PaginationConfig::default(), query).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", query);
+            assert_eq!(body["error"]["details"][0]["code"], 1012);
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_overflow() {
+        let query = format!("?page={}&limit=20", usize::MAX);
+        let (status, body) = call(PaginationConfig::default(), &query).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["details"][0]["field"], "page");
+
+        let query = format!("?offset={}", usize::MAX);
+        let (status, body) = call(PaginationConfig::default(), &query).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["sql"], serde_json::json!([20, i64::MAX]));
+    }
+
+    async fn devices(pagination: Pagination) -> crate::err::HttpResult<QueryOutput<u32>> {
+        Ok(pagination.paginate_vec((1..=10).collect())?)
+    }
+
+    async fn call_devices(policy: OutOfRange, query: &str) -> (StatusCode, Value) {
+        let config = PaginationConfig {
+            out_of_range: policy,
+            ..Default::default()
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(config)
+                .route("/devices", web::get().to(devices)),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri(&format!("/devices{}", query))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let status = res.status();
+        (status, test::read_body_json(res).await)
+    }
+
+    #[actix_web::test]
+    async fn test_out_of_range() {
+        // 第3页是最后一页
+        let (status, body) = call_devices(OutOfRange::Reject, "?limit=4&page=3").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["items"], serde_json::json!([9, 10]));
+
+        let (_, body) = call_devices(OutOfRange::EmptyWithFlag, "?limit=4&page=4").await;
+        assert_eq!(body["items"], serde_json::json!([]));
+        assert_eq!(body["meta"]["out_of_range"], true);
+
+        let (_, body) = call_devices(OutOfRange::ClampToLastPage, "?limit=4&page=4").await;
+        assert_eq!(body["items"], serde_json::json!([9, 10]));
+        assert_eq!(body["offset"], 8);
+        assert_eq!(body["page"], 3);
+
+        let (status, body) = call_devices(OutOfRange::Reject, "?limit=4&offset=10").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let detail = &body["error"]["details"][0];
+        assert_eq!(detail["code"], 1012);
+        assert_eq!(detail["field"], "offset");
+        assert_eq!(detail["extra"]["max_offset"], 8);
+    }
+
+    #[actix_web::test]
+    async fn test_zero_limit() {
+        let pagination = Pagination {
+            limit: 0,
+            offset: 0,
+            page: Some(1),
+            out_of_range: OutOfRange::default(),
+        };
+        let output = pagination.paginate_vec(vec![1, 2, 3]).unwrap();
+        assert_eq!(output.page, Some(1));
+
+        let config = PaginationConfig {
+            max_limit: 0,
+            ..Default::default()
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(config)
+                .route("/devices", web::get().to(devices)),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/devices?page=1").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
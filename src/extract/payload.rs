@@ -0,0 +1,199 @@
+use crate::config::payload_too_large;
+use crate::define::InvalidData;
+use crate::err::Error;
+use crate::middleware::request_locale;
+use actix_web::dev::Payload;
+use actix_web::http::{header, StatusCode};
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::{FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use futures_util::StreamExt;
+use std::ops::Deref;
+
+/// LimitedBytes和LimitedString的大小限制，通过app_data设置，默认256KB
+///
+/// # Example
+///
+/// ```ignore
+/// App::new().app_data(PayloadLimit(64 * 1024 * 1024))
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadLimit(pub usize);
+
+impl Default for PayloadLimit {
+    fn default() -> Self {
+        PayloadLimit(256 * 1024)
+    }
+}
+
+/// 带大小限制的原始请求体，超出限制时返回413标准错误结构
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitedBytes(pub Bytes);
+
+impl LimitedBytes {
+    pub fn into_inner(self) -> Bytes {
+        self.0
+    }
+}
+
+impl Deref for LimitedBytes {
+    type Target = Bytes;
+
+    fn deref(&self) -> &Bytes {
+        &self.0
+    }
+}
+
+impl FromRequest for LimitedBytes {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let limit = req
+            .app_data::<PayloadLimit>()
+            .copied()
+            .unwrap_or_default()
+            .0;
+        let locale = request_locale(req);
+        let length = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<usize>().ok());
+        let mut payload = payload.take();
+
+        Box::pin(async move {
+            if let Some(length) = length.filter(|length| *length > limit) {
+                let detail = payload_too_large(locale, limit, Some(length));
+                return Err(Error::new(StatusCode::PAYLOAD_TOO_LARGE).err(detail).into());
+            }
+
+            let mut body = BytesMut::new();
+            while let Some(chunk) = payload.next().await {
+                let chunk = chunk.map_err(|e| {
+                    let desc = format!(
+                        "{}: {}",
+                        locale.pick("请求数据读取失败", "failed to read payload"),
+                        e
+                    );
+                    Error::new(StatusCode::BAD_REQUEST).err(InvalidData.from_desc(desc))
+                })?;
+                if body.len() + chunk.len() > limit {
+                    let detail = payload_too_large(locale, limit, None);
+                    return Err(Error::new(StatusCode::PAYLOAD_TOO_LARGE).err(detail).into());
+                }
+                body.extend_from_slice(&chunk);
+            }
+            Ok(LimitedBytes(body.freeze()))
+        })
+    }
+}
+
+/// 带大小限制的UTF-8文本请求体，非UTF-8内容返回400和InvalidData
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitedString(pub String);
+
+impl LimitedString {
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl Deref for LimitedString {
+    type Target = String;
+
+    fn deref(&self) -> &String {
+        &self.0
+    }
+}
+
+impl FromRequest for LimitedString {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let bytes = LimitedBytes::from_request(req, payload);
+        let locale = request_locale(req);
+
+        Box::pin(async move {
+            let bytes = bytes.await?.into_inner();
+            // 请求体唯一持有时转换为Vec不会复制
+            match String::from_utf8(Vec::from(bytes)) {
+                Ok(text) => Ok(LimitedString(text)),
+                Err(e) => {
+                    let desc = format!(
+                        "{}: {}",
+                        locale.pick("请求数据不是有效的UTF-8", "payload is not valid UTF-8"),
+                        e
+                    );
+                    Err(Error::new(StatusCode::BAD_REQUEST)
+                        .err(InvalidData.from_desc(desc))
+                        .into())
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, web, App};
+    use serde_json::Value;
+
+    async fn upload(body: LimitedBytes) -> String {
+        body.len().to_string()
+    }
+
+    async fn frame(body: LimitedString) -> String {
+        body.into_inner()
+    }
+
+    async fn post(uri: &str, body: Vec<u8>) -> (StatusCode, Bytes) {
+        let app = test::init_service(
+            App::new()
+                .app_data(PayloadLimit(8))
+                .route("/upload", web::post().to(upload))
+                .route("/frame", web::post().to(frame)),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri(uri)
+            .set_payload(body)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        (res.status(), test::read_body(res).await)
+    }
+
+    #[actix_web::test]
+    async fn test_overflow() {
+        let (status, body) = post("/upload", vec![0u8; 16]).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["status"], 413);
+        assert_eq!(body["error"]["details"][0]["code"], 2009);
+        assert!(body["error"]["details"][0]["desc"]
+            .as_str()
+            .unwrap()
+            .contains("8"));
+    }
+
+    #[actix_web::test]
+    async fn test_within_limit() {
+        let (status, body) = post("/upload", vec![0u8; 8]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "8");
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_utf8() {
+        let (status, body) = post("/frame", vec![0xff, 0xfe]).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["details"][0]["code"], 1013);
+
+        let (status, body) = post("/frame", b"frame".to_vec()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "frame");
+    }
+}
@@ -0,0 +1,221 @@
+use crate::config::deserialize_error;
+use crate::define::InvalidInput;
+use crate::err::Error;
+use crate::middleware::request_locale;
+use actix_web::dev::Payload;
+use actix_web::http::StatusCode;
+use actix_web::{FromRequest, HttpRequest};
+use futures_util::future::{ready, Ready};
+use serde::de::DeserializeOwned;
+use std::ops::Deref;
+
+/// QsQuery的解析配置，通过app_data设置
+///
+/// # Example
+///
+/// ```ignore
+/// App::new().app_data(QsQueryConfig::default().max_depth(3))
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QsQueryConfig {
+    max_depth: usize,
+    strict: bool,
+}
+
+impl Default for QsQueryConfig {
+    fn default() -> Self {
+        QsQueryConfig {
+            max_depth: 5,
+            strict: true,
+        }
+    }
+}
+
+impl QsQueryConfig {
+    /// 嵌套的最大层数，默认5
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// 严格模式下不接受编码后的方括号（%5B、%5D），默认开启
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+}
+
+/// 支持`ids[]=1&ids[]=2`、`filter[status]=up`等写法的查询参数提取器
+///
+/// 解析失败时返回400标准错误结构，field为出错的参数路径。可以和web::Query、Pagination同时使用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QsQuery<T>(pub T);
+
+impl<T> QsQuery<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for QsQuery<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+/// 返回第一个嵌套层数超过限制的参数名
+fn too_deep(query: &str, config: &QsQueryConfig) -> Option<String> {
+    query
+        .split('&')
+        .map(|pair| pair.split('=').next().unwrap_or_default())
+        .map(|key| {
+            if config.strict {
+                key.to_string()
+            } else {
+                key.replace("%5B", "[")
+                    .replace("%5b", "[")
+                    .replace("%5D", "]")
+                    .replace("%5d", "]")
+            }
+        })
+        .find(|key| key.matches('[').count() > config.max_depth)
+}
+
+impl<T: DeserializeOwned> QsQuery<T> {
+    fn parse(req: &HttpRequest) -> Result<Self, actix_web::Error> {
+        let config = req.app_data::<QsQueryConfig>().copied().unwrap_or_default();
+        let locale = request_locale(req);
+        let invalid = |detail| Error::new(StatusCode::BAD_REQUEST).err(detail).into();
+
+        if let Some(key) = too_deep(req.query_string(), &config) {
+            let desc = format!(
+                "{} {}: {} {}",
+                locale.pick("查询参数错误", "invalid query parameter"),
+                key,
+                locale.pick("嵌套层数超过", "nesting depth exceeds"),
+                config.max_depth
+            );
+            return Err(invalid(InvalidInput.from_desc(desc).with_field(key)));
+        }
+
+        // serde_qs把`key[]`也算作一层，已在too_deep中检查过层数，这里放宽一层
+        let qs_config = serde_qs::Config::new(config.max_depth + 1, config.strict);
+        serde_qs::Deserializer::with_config(&qs_config, req.query_string().as_bytes())
+            .map_err(|e| (String::new(), e))
+            .and_then(|de| {
+                serde_path_to_error::deserialize(de)
+                    .map_err(|e| (e.path().to_string(), e.into_inner()))
+            })
+            .map(QsQuery)
+            .map_err(|(path, e)| {
+                let detail = if path.is_empty() || path == "." {
+                    deserialize_error(
+                        InvalidInput,
+                        locale,
+                        locale.pick("查询参数错误", "invalid query parameters"),
+                        &e.to_string(),
+                    )
+                } else {
+                    let prefix = format!(
+                        "{} {}",
+                        locale.pick("查询参数错误", "invalid query parameter"),
+                        path
+                    );
+                    deserialize_error(InvalidInput, locale, &prefix, &e.to_string())
+                        .with_field(path)
+                };
+                invalid(detail)
+            })
+    }
+}
+
+impl<T: DeserializeOwned> FromRequest for QsQuery<T> {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Self::parse(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::Pagination;
+    use actix_web::{test, web, App, HttpResponse};
+    use serde_json::{json, Value};
+    use std::collections::BTreeMap;
+
+    #[derive(Deserialize, Serialize)]
+    struct Filter {
+        #[serde(default)]
+        ids: Vec<u32>,
+        #[serde(default)]
+        filter: BTreeMap<String, String>,
+    }
+
+    #[derive(Deserialize, Serialize)]
+    struct Deep {
+        a: BTreeMap<String, BTreeMap<String, u32>>,
+    }
+
+    async fn list(query: QsQuery<Filter>, pagination: Pagination) -> HttpResponse {
+        HttpResponse::Ok().json(json!({"query": query.into_inner(), "limit": pagination.limit}))
+    }
+
+    async fn deep(query: QsQuery<Deep>) -> HttpResponse {
+        HttpResponse::Ok().json(query.into_inner())
+    }
+
+    async fn call(uri: &str) -> (StatusCode, Value) {
+        let app = test::init_service(
+            App::new()
+                .app_data(QsQueryConfig::default().max_depth(2))
+                .route("/devices", web::get().to(list))
+                .route("/deep", web::get().to(deep)),
+        )
+        .await;
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let res = test::call_service(&app, req).await;
+        let status = res.status();
+        (status, test::read_body_json(res).await)
+    }
+
+    #[actix_web::test]
+    async fn test_arrays_and_maps() {
+        let (status, body) =
+            call("/devices?ids[]=1&ids[]=2&filter[status]=up&filter[type]=cam&limit=5").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["query"]["ids"], json!([1, 2]));
+        assert_eq!(
+            body["query"]["filter"],
+            json!({"status": "up", "type": "cam"})
+        );
+        assert_eq!(body["limit"], 5);
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_value_names_key() {
+        let (status, body) = call("/devices?ids[]=1&ids[]=x").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let detail = &body["error"]["details"][0];
+        assert_eq!(detail["code"], 1012);
+        assert_eq!(detail["field"], "ids[1]");
+    }
+
+    #[actix_web::test]
+    async fn test_depth_limit() {
+        let (status, body) = call("/deep?a[b][c]=1").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, json!({"a": {"b": {"c": 1}}}));
+
+        let (status, body) = call("/deep?a[b][c][d]=1").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["status"], 400);
+        let detail = &body["error"]["details"][0];
+        assert_eq!(detail["code"], 1012);
+        assert_eq!(detail["field"], "a[b][c][d]");
+    }
+}
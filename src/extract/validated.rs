@@ -0,0 +1,207 @@
+use crate::define::{ExtraDescError, Locale, ValidationFailed};
+use crate::err::{render_error, status_for_code, ErrorDetail, ErrorOutTpl};
+use crate::middleware::request_locale;
+use actix_web::dev::Payload;
+use actix_web::error::InternalError;
+use actix_web::{web, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use serde::de::DeserializeOwned;
+use std::ops::Deref;
+use validator::{Validate, ValidationErrors, ValidationErrorsKind};
+
+/// 字段名包含这些词时不在错误详情中返回被拒绝的值
+const SENSITIVE_FIELDS: [&str; 4] = ["password", "secret", "token", "key"];
+
+/// 解析json后执行validator::Validate的提取器
+///
+/// 解析失败沿用JsonConfig的错误处理（400），校验失败返回422，每个未通过的字段一条错误详情
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(Deserialize, Validate)]
+/// struct CreateDevice {
+///     #[validate(length(min = 1, max = 32))]
+///     name: String,
+/// }
+///
+/// async fn create(device: ValidatedJson<CreateDevice>) -> HttpResult<HttpResponse> {
+///     let device = device.into_inner();
+///     ...
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatedJson<T>(pub T);
+
+impl<T> ValidatedJson<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for ValidatedJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+fn is_sensitive(field: &str) -> bool {
+    let field = field.to_ascii_lowercase();
+    SENSITIVE_FIELDS.iter().any(|word| field.contains(word))
+}
+
+fn collect_details(
+    locale: Locale,
+    prefix: &str,
+    errors: &ValidationErrors,
+    out: &mut Vec<ExtraDescError>,
+) {
+    let mut fields: Vec<_> = errors.errors().iter().collect();
+    fields.sort_by(|a, b| a.0.cmp(b.0));
+    for (field, kind) in fields {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", prefix, field)
+        };
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                for error in errors {
+                    let desc = match &error.message {
+                        Some(message) => message.to_string(),
+                        None => format!(
+                            "{} {}: {}",
+                            path,
+                            locale.pick("校验失败", "failed validation"),
+                            error.code
+                        ),
+                    };
+                    let mut detail = ValidationFailed
+                        .from_desc(desc)
+                        .with_field(path.clone())
+                        .with_extra("rule", error.code.to_string());
+                    if let Some(value) = error.params.get("value") {
+                        if !is_sensitive(&path) && !value.is_null() {
+                            detail = detail.with_extra("value", value.clone());
+                        }
+                    }
+                    out.push(detail);
+                }
+            }
+            ValidationErrorsKind::Struct(errors) => collect_details(locale, &path, errors, out),
+            ValidationErrorsKind::List(items) => {
+                for (index, errors) in items {
+                    collect_details(locale, &format!("{}[{}]", path, index), errors, out);
+                }
+            }
+        }
+    }
+}
+
+fn validation_error(locale: Locale, errors: ValidationErrors) -> actix_web::Error {
+    let mut details = Vec::new();
+    collect_details(locale, "", &errors, &mut details);
+    let status = status_for_code(&ValidationFailed);
+    let details: Vec<ErrorDetail> = details.iter().map(ErrorDetail::new_from_extra).collect();
+    let response = render_error(
+        status,
+        &ErrorOutTpl::new_from_details(status, details),
+        ValidationFailed.code(),
+    );
+    InternalError::from_response(errors, response).into()
+}
+
+impl<T> FromRequest for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let json = web::Json::<T>::from_request(req, payload);
+        let locale = request_locale(req);
+        Box::pin(async move {
+            let value = json.await?.into_inner();
+            match value.validate() {
+                Ok(()) => Ok(ValidatedJson(value)),
+                Err(errors) => Err(validation_error(locale, errors)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_default_jsonconfig;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use serde_json::Value;
+
+    #[derive(Deserialize, Validate)]
+    struct CreateDevice {
+        #[validate(length(min = 1, max = 8))]
+        name: String,
+        #[validate(range(min = 1, max = 100))]
+        port: u32,
+        #[validate(length(min = 8))]
+        password: String,
+    }
+
+    async fn create(device: ValidatedJson<CreateDevice>) -> String {
+        device.name.clone()
+    }
+
+    async fn post(body: &str) -> (StatusCode, actix_web::web::Bytes) {
+        let app = test::init_service(
+            App::new()
+                .app_data(get_default_jsonconfig())
+                .route("/", web::post().to(create)),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/")
+            .insert_header(("content-type", "application/json"))
+            .set_payload(body.to_string())
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        (res.status(), test::read_body(res).await)
+    }
+
+    #[actix_web::test]
+    async fn test_invalid_json() {
+        let (status, body) = post(r#"{"name": }"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["details"][0]["code"], 2006);
+    }
+
+    #[actix_web::test]
+    async fn test_validation_failed() {
+        let (status, body) =
+            post(r#"{"name": "a-very-long-name", "port": 0, "password": "short"}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let details = body["error"]["details"].as_array().unwrap();
+        assert_eq!(details.len(), 3);
+        assert_eq!(details[0]["code"], 2013);
+        assert_eq!(details[0]["field"], "name");
+        assert_eq!(details[0]["extra"]["rule"], "length");
+        assert_eq!(details[0]["extra"]["value"], "a-very-long-name");
+        assert_eq!(details[1]["field"], "password");
+        assert!(details[1]["extra"].get("value").is_none());
+        assert_eq!(details[2]["field"], "port");
+        assert_eq!(details[2]["extra"]["value"], 0);
+    }
+
+    #[actix_web::test]
+    async fn test_valid_payload() {
+        let (status, body) =
+            post(r#"{"name": "cam", "port": 80, "password": "long-enough"}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "cam");
+    }
+}
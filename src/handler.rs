@@ -0,0 +1,113 @@
+use super::define::{MethodNotAllowed, RouteNotFound};
+use super::err::Error;
+use super::middleware::{current_locale, request_locale};
+use actix_web::dev::ServiceResponse;
+use actix_web::http::{header, StatusCode};
+use actix_web::middleware::ErrorHandlerResponse;
+use actix_web::{web, HttpResponse, ResponseError, Route};
+
+/// 未匹配到路由时返回404标准错误结构
+pub async fn default_not_found() -> HttpResponse {
+    let locale = current_locale();
+    let detail = RouteNotFound.from_desc(locale.pick("请求的路由不存在", "route not found"));
+    Error::new(StatusCode::NOT_FOUND)
+        .err(detail)
+        .error_response()
+}
+
+/// 默认服务，用于App::default_service
+///
+/// # Example
+///
+/// ```ignore
+/// App::new()
+///     .wrap(ErrorHandlers::new().handler(StatusCode::METHOD_NOT_ALLOWED, actix_util::method_not_allowed))
+///     .default_service(actix_util::default_service())
+/// ```
+pub fn default_service() -> Route {
+    web::route().to(default_not_found)
+}
+
+/// actix在路径匹配但方法不匹配时返回空的405，通过ErrorHandlers改写为标准错误结构，
+/// 并在desc和extra.allowed_methods中列出Allow头中的方法
+pub fn method_not_allowed<B>(
+    res: ServiceResponse<B>,
+) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    let locale = request_locale(res.request());
+    let allow = res.headers().get(header::ALLOW).cloned();
+    let allowed: Vec<String> = allow
+        .as_ref()
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(',').map(|m| m.trim().to_string()).collect())
+        .unwrap_or_default();
+
+    let mut detail =
+        MethodNotAllowed.from_desc(locale.pick("请求方法不允许", "method not allowed"));
+    if !allowed.is_empty() {
+        detail.desc = format!(
+            "{}, {}: {}",
+            detail.desc,
+            locale.pick("允许的方法", "allowed methods"),
+            allowed.join(", ")
+        );
+        detail = detail.with_extra("allowed_methods", allowed);
+    }
+
+    let mut response = Error::new(StatusCode::METHOD_NOT_ALLOWED)
+        .err(detail)
+        .error_response();
+    if let Some(allow) = allow {
+        response.headers_mut().insert(header::ALLOW, allow);
+    }
+    let (req, _) = res.into_parts();
+    Ok(ErrorHandlerResponse::Response(
+        ServiceResponse::new(req, response).map_into_right_body(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::middleware::ErrorHandlers;
+    use actix_web::{test, App};
+    use serde_json::Value;
+
+    #[actix_web::test]
+    async fn test_not_found_and_method_not_allowed() {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    ErrorHandlers::new()
+                        .handler(StatusCode::METHOD_NOT_ALLOWED, method_not_allowed),
+                )
+                .service(
+                    web::resource("/devices")
+                        .route(web::get().to(HttpResponse::Ok))
+                        .route(web::delete().to(HttpResponse::Ok)),
+                )
+                .default_service(default_service()),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/unknown").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["error"]["status"], 404);
+        assert_eq!(body["error"]["details"][0]["code"], 2011);
+
+        let req = test::TestRequest::post().uri("/devices").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(res.headers().get(header::ALLOW).unwrap(), "GET, DELETE");
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["error"]["status"], 405);
+        let detail = &body["error"]["details"][0];
+        assert_eq!(detail["code"], 2012);
+        assert_eq!(detail["extra"]["allowed_methods"][1], "DELETE");
+
+        let req = test::TestRequest::get().uri("/devices").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+}
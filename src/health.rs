@@ -0,0 +1,224 @@
+use super::define::{Result, TimedOut};
+use super::err::ErrorDetail;
+use super::middleware::{current_locale, SkipNormalize};
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse, Route};
+use futures_util::future::{join_all, LocalBoxFuture};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::rc::Rc;
+use std::time::Duration;
+
+type Check = Rc<dyn Fn() -> LocalBoxFuture<'static, Result<()>>>;
+
+/// 单项检查的结果，通过时为"ok"，失败时为标准错误详情
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum CheckStatus {
+    Ok(&'static str),
+    Failed(ErrorDetail),
+}
+
+#[derive(Debug, Serialize)]
+struct HealthOutTpl {
+    status: &'static str,
+    checks: BTreeMap<String, CheckStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+}
+
+/// 健康检查注册表，通过app_data注册后由health_handler执行所有检查
+///
+/// 各项检查并发执行，超过超时时间（默认5秒）的检查记为TimedOut
+///
+/// # Example
+///
+/// ```ignore
+/// let health = HealthCheck::new()
+///     .version(env!("CARGO_PKG_VERSION"))
+///     .register("postgres", move || {
+///         let pool = pool.clone();
+///         async move { pool.get().map(|_| ()).map_err(Into::into) }
+///     });
+/// App::new()
+///     .app_data(health)
+///     .route("/healthz", actix_util::health_handler())
+/// ```
+#[derive(Clone)]
+pub struct HealthCheck {
+    checks: Vec<(String, Check)>,
+    timeout: Duration,
+    version: Option<String>,
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        HealthCheck {
+            checks: Vec::new(),
+            timeout: Duration::from_secs(5),
+            version: None,
+        }
+    }
+}
+
+impl HealthCheck {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<S, F, Fut>(mut self, name: S, check: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn() -> Fut + 'static,
+        Fut: Future<Output = Result<()>> + 'static,
+    {
+        self.checks
+            .push((name.into(), Rc::new(move || Box::pin(check()))));
+        self
+    }
+
+    /// 单项检查的超时时间
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn version<S: Into<String>>(mut self, version: S) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    async fn run(&self) -> HttpResponse {
+        let timeout = self.timeout;
+        let results = join_all(self.checks.iter().map(|(name, check)| {
+            let fut = tokio::time::timeout(timeout, check());
+            async move {
+                let status = match fut.await {
+                    Ok(Ok(())) => CheckStatus::Ok("ok"),
+                    Ok(Err(err)) => CheckStatus::Failed(ErrorDetail::new_from_extra(&err)),
+                    Err(_) => {
+                        let desc = format!(
+                            "{} {}ms",
+                            current_locale().pick("健康检查超时", "health check timed out after"),
+                            timeout.as_millis()
+                        );
+                        CheckStatus::Failed(ErrorDetail::new_from_extra(&TimedOut.from_desc(desc)))
+                    }
+                };
+                (name.clone(), status)
+            }
+        }))
+        .await;
+
+        let healthy = results
+            .iter()
+            .all(|(_, status)| matches!(status, CheckStatus::Ok(_)));
+        let (status, text) = if healthy {
+            (StatusCode::OK, "ok")
+        } else {
+            (StatusCode::SERVICE_UNAVAILABLE, "error")
+        };
+        let mut response = HttpResponse::build(status).json(HealthOutTpl {
+            status: text,
+            checks: results.into_iter().collect(),
+            version: self.version.clone(),
+        });
+        // 503时保留每项检查的结果，不被normalize_errors改写
+        response.extensions_mut().insert(SkipNormalize);
+        response
+    }
+}
+
+/// 健康检查端点，执行app_data中注册的HealthCheck，未注册时直接返回ok
+pub fn health_handler() -> Route {
+    web::get().to(|req: HttpRequest| async move {
+        match req.app_data::<HealthCheck>() {
+            Some(health) => health.run().await,
+            None => HealthCheck::new().run().await,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::define::DataBaseError;
+    use actix_web::{test, App};
+    use serde_json::{json, Value};
+
+    async fn call(health: HealthCheck) -> (StatusCode, Value) {
+        let app = test::init_service(
+            App::new()
+                .app_data(health)
+                .route("/healthz", health_handler()),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/healthz").to_request();
+        let res = test::call_service(&app, req).await;
+        let status = res.status();
+        (status, test::read_body_json(res).await)
+    }
+
+    #[actix_web::test]
+    async fn test_all_pass() {
+        let health = HealthCheck::new()
+            .version("1.0.0")
+            .register("postgres", || async { Ok(()) })
+            .register("redis", || async { Ok(()) });
+        let (status, body) = call(health).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            body,
+            json!({"status": "ok", "checks": {"postgres": "ok", "redis": "ok"}, "version": "1.0.0"})
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_one_failing() {
+        let health = HealthCheck::new()
+            .register("postgres", || async {
+                Err(DataBaseError.from_desc("连接池耗尽"))
+            })
+            .register("redis", || async { Ok(()) });
+        let (status, body) = call(health).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "error");
+        assert_eq!(body["checks"]["redis"], "ok");
+        assert_eq!(body["checks"]["postgres"]["code"], 3002);
+        assert_eq!(body["checks"]["postgres"]["desc"], "连接池耗尽");
+    }
+
+    #[actix_web::test]
+    async fn test_one_timing_out() {
+        let health = HealthCheck::new()
+            .timeout(Duration::from_millis(20))
+            .register("slow", || async {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                Ok(())
+            })
+            .register("fast", || async { Ok(()) });
+        let (status, body) = call(health).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["checks"]["fast"], "ok");
+        assert_eq!(body["checks"]["slow"]["code"], 1014);
+    }
+
+    #[actix_web::test]
+    async fn test_with_normalize_errors() {
+        let health = HealthCheck::new().register("postgres", || async {
+            Err(DataBaseError.from_desc("连接池耗尽"))
+        });
+        let app = test::init_service(
+            App::new()
+                .wrap(crate::middleware::normalize_errors())
+                .app_data(health)
+                .route("/healthz", health_handler()),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/healthz").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["status"], "error");
+        assert_eq!(body["checks"]["postgres"]["code"], 3002);
+    }
+}
@@ -0,0 +1,571 @@
+use actix_web::HttpResponse;
+use serde::ser::{self, Serialize, Serializer};
+use std::fmt::Display;
+
+/// JavaScript能精确表示的最大整数 2^53 - 1
+pub const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// 64位整数转换为字符串的策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JsSafeMode {
+    /// 只转换超出 ±(2^53 - 1) 的整数
+    #[default]
+    Unsafe,
+    /// 转换所有 i64/u64/i128/u128 整数
+    All,
+}
+
+impl JsSafeMode {
+    fn stringify(self, abs: u128) -> bool {
+        match self {
+            JsSafeMode::Unsafe => abs > MAX_SAFE_INTEGER as u128,
+            JsSafeMode::All => true,
+        }
+    }
+}
+
+/// 序列化时把大整数输出为字符串，避免JavaScript客户端丢失精度
+///
+/// 对嵌套的结构体、Vec、Map递归生效，可以直接包裹QueryOutput
+///
+/// # Example
+///
+/// ```ignore
+/// let output = QueryOutput::default().items(devices);
+/// HttpResponse::Ok().json(JsSafe::new(output))
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct JsSafe<T> {
+    value: T,
+    mode: JsSafeMode,
+}
+
+impl<T> JsSafe<T> {
+    pub fn new(value: T) -> Self {
+        JsSafe {
+            value,
+            mode: JsSafeMode::Unsafe,
+        }
+    }
+
+    pub fn mode(mut self, mode: JsSafeMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: Serialize> Serialize for JsSafe<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(JsSafeSerializer {
+            inner: serializer,
+            mode: self.mode,
+        })
+    }
+}
+
+/// 返回200，并以JsSafe方式序列化数据
+pub fn ok_json_js_safe<T: Serialize>(data: T) -> HttpResponse {
+    HttpResponse::Ok().json(JsSafe::new(data))
+}
+
+struct Wrap<'a, T: ?Sized> {
+    value: &'a T,
+    mode: JsSafeMode,
+}
+
+impl<T: Serialize + ?Sized> Serialize for Wrap<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(JsSafeSerializer {
+            inner: serializer,
+            mode: self.mode,
+        })
+    }
+}
+
+struct JsSafeSerializer<S> {
+    inner: S,
+    mode: JsSafeMode,
+}
+
+impl<S: Serializer> JsSafeSerializer<S> {
+    fn wrap<'a, T: ?Sized>(&self, value: &'a T) -> Wrap<'a, T> {
+        Wrap {
+            value,
+            mode: self.mode,
+        }
+    }
+
+    fn integer<V: Display>(
+        self,
+        value: V,
+        abs: u128,
+        plain: impl FnOnce(S) -> Result<S::Ok, S::Error>,
+    ) -> Result<S::Ok, S::Error> {
+        if self.mode.stringify(abs) {
+            self.inner.collect_str(&value)
+        } else {
+            plain(self.inner)
+        }
+    }
+}
+
+impl<S: Serializer> Serializer for JsSafeSerializer<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Compound<S::SerializeSeq>;
+    type SerializeTuple = Compound<S::SerializeTuple>;
+    type SerializeTupleStruct = Compound<S::SerializeTupleStruct>;
+    type SerializeTupleVariant = Compound<S::SerializeTupleVariant>;
+    type SerializeMap = Compound<S::SerializeMap>;
+    type SerializeStruct = Compound<S::SerializeStruct>;
+    type SerializeStructVariant = Compound<S::SerializeStructVariant>;
+
+    fn serialize_bool(self, v: bool) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_bool(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i8(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i16(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i32(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<S::Ok, S::Error> {
+        self.integer(v, v.unsigned_abs() as u128, |s| s.serialize_i64(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<S::Ok, S::Error> {
+        self.integer(v, v.unsigned_abs(), |s| s.serialize_i128(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u8(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u16(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u32(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<S::Ok, S::Error> {
+        self.integer(v, v as u128, |s| s.serialize_u64(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<S::Ok, S::Error> {
+        self.integer(v, v, |s| s.serialize_u128(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_f32(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_f64(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_char(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_str(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_bytes(v)
+    }
+
+    fn serialize_none(self) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_none()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<S::Ok, S::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_some(&value)
+    }
+
+    fn serialize_unit(self) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit()
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner
+            .serialize_unit_variant(name, variant_index, variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<S::Ok, S::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_newtype_struct(name, &value)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<S::Ok, S::Error> {
+        let value = self.wrap(value);
+        self.inner
+            .serialize_newtype_variant(name, variant_index, variant, &value)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        let mode = self.mode;
+        Ok(Compound {
+            inner: self.inner.serialize_seq(len)?,
+            mode,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, S::Error> {
+        let mode = self.mode;
+        Ok(Compound {
+            inner: self.inner.serialize_tuple(len)?,
+            mode,
+        })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, S::Error> {
+        let mode = self.mode;
+        Ok(Compound {
+            inner: self.inner.serialize_tuple_struct(name, len)?,
+            mode,
+        })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, S::Error> {
+        let mode = self.mode;
+        Ok(Compound {
+            inner: self
+                .inner
+                .serialize_tuple_variant(name, variant_index, variant, len)?,
+            mode,
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        let mode = self.mode;
+        Ok(Compound {
+            inner: self.inner.serialize_map(len)?,
+            mode,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, S::Error> {
+        let mode = self.mode;
+        Ok(Compound {
+            inner: self.inner.serialize_struct(name, len)?,
+            mode,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, S::Error> {
+        let mode = self.mode;
+        Ok(Compound {
+            inner: self
+                .inner
+                .serialize_struct_variant(name, variant_index, variant, len)?,
+            mode,
+        })
+    }
+
+    fn collect_str<T: Display + ?Sized>(self, value: &T) -> Result<S::Ok, S::Error> {
+        self.inner.collect_str(value)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+struct Compound<C> {
+    inner: C,
+    mode: JsSafeMode,
+}
+
+impl<C> Compound<C> {
+    fn wrap<'a, T: ?Sized>(&self, value: &'a T) -> Wrap<'a, T> {
+        Wrap {
+            value,
+            mode: self.mode,
+        }
+    }
+}
+
+impl<C: ser::SerializeSeq> ser::SerializeSeq for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_element(&value)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: ser::SerializeTuple> ser::SerializeTuple for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_element(&value)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: ser::SerializeTupleStruct> ser::SerializeTupleStruct for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_field(&value)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: ser::SerializeTupleVariant> ser::SerializeTupleVariant for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_field(&value)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: ser::SerializeMap> ser::SerializeMap for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), C::Error> {
+        let key = self.wrap(key);
+        self.inner.serialize_key(&key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_value(&value)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: ser::SerializeStruct> ser::SerializeStruct for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), C::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_field(key, &value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), C::Error> {
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: ser::SerializeStructVariant> ser::SerializeStructVariant for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), C::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_field(key, &value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), C::Error> {
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::QueryOutput;
+    use serde::de::DeserializeOwned;
+    use serde_json::Value;
+    use std::collections::BTreeMap;
+    use std::fmt::Debug;
+    use std::str::FromStr;
+
+    #[derive(Debug, Default, PartialEq, Serialize)]
+    struct Device {
+        id: u64,
+        port: u16,
+        offset: i64,
+        tags: BTreeMap<String, u64>,
+        parent: Option<u64>,
+    }
+
+    fn device() -> Device {
+        let mut tags = BTreeMap::new();
+        tags.insert("big".to_string(), u64::MAX);
+        tags.insert("small".to_string(), 7);
+        Device {
+            id: 1_234_567_890_123_456_789,
+            port: 8080,
+            offset: -(1 << 60),
+            tags,
+            parent: Some(42),
+        }
+    }
+
+    #[test]
+    fn test_large_numbers_quoted() {
+        let json = serde_json::to_string(&JsSafe::new(device())).unwrap();
+        assert_eq!(
+            json,
+            r#"{"id":"1234567890123456789","port":8080,"offset":"-1152921504606846976","tags":{"big":"18446744073709551615","small":7},"parent":42}"#
+        );
+    }
+
+    #[test]
+    fn test_boundary() {
+        let json =
+            serde_json::to_string(&JsSafe::new(vec![MAX_SAFE_INTEGER, MAX_SAFE_INTEGER + 1]))
+                .unwrap();
+        assert_eq!(json, r#"[9007199254740991,"9007199254740992"]"#);
+    }
+
+    #[test]
+    fn test_all_mode() {
+        let value = JsSafe::new(device()).mode(JsSafeMode::All);
+        let json = serde_json::to_value(&value).unwrap();
+        assert_eq!(json["port"], 8080);
+        assert_eq!(json["parent"], "42");
+        assert_eq!(json["tags"]["small"], "7");
+    }
+
+    #[test]
+    fn test_query_output() {
+        let output = QueryOutput::default().items(vec![device()]).limit(10);
+        let json = serde_json::to_value(JsSafe::new(output)).unwrap();
+        assert_eq!(json["items"][0]["id"], "1234567890123456789");
+        assert_eq!(json["limit"], 10);
+        assert_eq!(json["total"], 1);
+    }
+
+    /// 按客户端的方式读取整数：字符串按十进制解析，数字直接反序列化
+    fn read_int<T>(value: &Value) -> T
+    where
+        T: FromStr + DeserializeOwned,
+        T::Err: Debug,
+    {
+        match value {
+            Value::String(text) => text.parse().unwrap(),
+            other => serde_json::from_value(other.clone()).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for mode in [JsSafeMode::Unsafe, JsSafeMode::All] {
+            let json = serde_json::to_string(&JsSafe::new(device()).mode(mode)).unwrap();
+            let json: Value = serde_json::from_str(&json).unwrap();
+            let decoded = Device {
+                id: read_int(&json["id"]),
+                port: read_int(&json["port"]),
+                offset: read_int(&json["offset"]),
+                tags: json["tags"]
+                    .as_object()
+                    .unwrap()
+                    .iter()
+                    .map(|(key, value)| (key.clone(), read_int(value)))
+                    .collect(),
+                parent: Some(read_int(&json["parent"])),
+            };
+            assert_eq!(decoded, device());
+        }
+
+        let extremes = (i64::MIN, u64::MAX, i128::MIN, u128::MAX);
+        let json = serde_json::to_value(JsSafe::new(extremes)).unwrap();
+        assert!(json.as_array().unwrap().iter().all(Value::is_string));
+        let decoded = (
+            read_int::<i64>(&json[0]),
+            read_int::<u64>(&json[1]),
+            read_int::<i128>(&json[2]),
+            read_int::<u128>(&json[3]),
+        );
+        assert_eq!(decoded, extremes);
+    }
+}
@@ -0,0 +1,323 @@
+use crate::define::{InvalidInput, Result};
+use crate::middleware::current_locale;
+#[cfg(feature = "cursor")]
+use crate::query::CursorCodec;
+use crate::query::{CursorOutput, SortOrder};
+use diesel::backend::Backend;
+use diesel::dsl::{Eq, Gt, Lt};
+use diesel::expression::{AsExpression, BoxableExpression};
+use diesel::query_builder::BoxedSelectStatement;
+use diesel::query_dsl::methods::{LimitDsl, OrderDsl, ThenOrderDsl};
+use diesel::sql_types::{Bool, NotNull};
+use diesel::{BoolExpressionMethods, Column, ExpressionMethods, QueryDsl};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+type Predicate<'a, QS, DB> = Box<dyn BoxableExpression<QS, DB, SqlType = Bool> + 'a>;
+
+/// 键集（seek）分页：按(排序列, 唯一列)定位上一页的最后一行，代替深度OFFSET
+///
+/// 两列都必须是NOT NULL，可空列在编译期被拒绝。游标默认是最后一行键值的json的十六进制编码，
+/// 客户端应当把它当作不透明的字符串；开启cursor特性后可以通过codec()使用签名游标
+///
+/// # Example
+///
+/// ```ignore
+/// let keyset = Keyset::new(events::created_at, events::id, SortOrder::Desc);
+/// let after = keyset.decode_cursor::<i64, i32>(query.cursor.as_deref())?;
+/// let rows: Vec<Event> = keyset
+///     .apply(events::table.filter(events::device_id.eq(id)).into_boxed(), after, limit)
+///     .load(&conn)?;
+/// let output = keyset.output(rows, limit, |event| (event.created_at, event.id));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Keyset<C1, C2> {
+    primary: C1,
+    tiebreak: C2,
+    order: SortOrder,
+    #[cfg(feature = "cursor")]
+    codec: Option<CursorCodec>,
+}
+
+impl<C1, C2> Keyset<C1, C2>
+where
+    C1: Column + ExpressionMethods + Copy,
+    C2: Column + ExpressionMethods + Copy,
+    C1::SqlType: NotNull,
+    C2::SqlType: NotNull,
+{
+    /// primary为排序列，tiebreak为区分排序值相同的行的唯一列（通常是主键）
+    pub fn new(primary: C1, tiebreak: C2, order: SortOrder) -> Self {
+        Keyset {
+            primary,
+            tiebreak,
+            order,
+            #[cfg(feature = "cursor")]
+            codec: None,
+        }
+    }
+
+    /// 使用签名游标，篡改或过期的游标在decode_cursor时返回InvalidInput
+    #[cfg(feature = "cursor")]
+    pub fn codec(mut self, codec: CursorCodec) -> Self {
+        self.codec = Some(codec);
+        self
+    }
+
+    /// 增加定位条件和排序，并多取一行用于判断是否还有下一页
+    pub fn apply<'a, ST, QS: 'a, DB, V1, V2>(
+        &self,
+        query: BoxedSelectStatement<'a, ST, QS, DB>,
+        after: Option<(V1, V2)>,
+        limit: i64,
+    ) -> BoxedSelectStatement<'a, ST, QS, DB>
+    where
+        DB: Backend + 'a,
+        V1: AsExpression<C1::SqlType> + Clone,
+        V2: AsExpression<C2::SqlType>,
+        Lt<C1, V1>: BoxableExpression<QS, DB, SqlType = Bool> + 'a,
+        Gt<C1, V1>: BoxableExpression<QS, DB, SqlType = Bool> + 'a,
+        Eq<C1, V1>: BoxableExpression<QS, DB, SqlType = Bool> + 'a,
+        Lt<C2, V2>: BoxableExpression<QS, DB, SqlType = Bool> + 'a,
+        Gt<C2, V2>: BoxableExpression<QS, DB, SqlType = Bool> + 'a,
+        BoxedSelectStatement<'a, ST, QS, DB>: OrderDsl<diesel::dsl::Desc<C1>, Output = BoxedSelectStatement<'a, ST, QS, DB>>
+            + OrderDsl<diesel::dsl::Asc<C1>, Output = BoxedSelectStatement<'a, ST, QS, DB>>
+            + ThenOrderDsl<diesel::dsl::Desc<C2>, Output = BoxedSelectStatement<'a, ST, QS, DB>>
+            + ThenOrderDsl<diesel::dsl::Asc<C2>, Output = BoxedSelectStatement<'a, ST, QS, DB>>
+            + LimitDsl<Output = BoxedSelectStatement<'a, ST, QS, DB>>
+            + QueryDsl,
+    {
+        let mut query = query;
+        if let Some((v1, v2)) = after {
+            let (first, second): (Predicate<'a, QS, DB>, Predicate<'a, QS, DB>) = match self.order {
+                SortOrder::Asc => (
+                    Box::new(self.primary.gt(v1.clone())),
+                    Box::new(self.tiebreak.gt(v2)),
+                ),
+                SortOrder::Desc => (
+                    Box::new(self.primary.lt(v1.clone())),
+                    Box::new(self.tiebreak.lt(v2)),
+                ),
+            };
+            let same: Predicate<'a, QS, DB> = Box::new(self.primary.eq(v1));
+            query = query.filter(first.or(same.and(second)));
+        }
+        let query = match self.order {
+            SortOrder::Asc => ThenOrderDsl::then_order_by(
+                OrderDsl::order(query, self.primary.asc()),
+                self.tiebreak.asc(),
+            ),
+            SortOrder::Desc => ThenOrderDsl::then_order_by(
+                OrderDsl::order(query, self.primary.desc()),
+                self.tiebreak.desc(),
+            ),
+        };
+        LimitDsl::limit(query, limit.max(0).saturating_add(1))
+    }
+
+    /// 解析游标，没有游标时返回None（从第一页开始）
+    pub fn decode_cursor<V1, V2>(&self, cursor: Option<&str>) -> Result<Option<(V1, V2)>>
+    where
+        V1: DeserializeOwned,
+        V2: DeserializeOwned,
+    {
+        let cursor = match cursor.filter(|cursor| !cursor.is_empty()) {
+            Some(cursor) => cursor,
+            None => return Ok(None),
+        };
+        #[cfg(feature = "cursor")]
+        if let Some(codec) = &self.codec {
+            return codec.decode(cursor).map(Some);
+        }
+        decode_hex(cursor)
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .map(Some)
+            .ok_or_else(|| {
+                InvalidInput
+                    .from_desc(current_locale().pick("游标无效", "invalid cursor"))
+                    .with_field("cursor")
+            })
+    }
+
+    pub fn encode_cursor<V1: Serialize, V2: Serialize>(&self, key: &(V1, V2)) -> String {
+        #[cfg(feature = "cursor")]
+        if let Some(codec) = &self.codec {
+            return codec.encode(key);
+        }
+        encode_hex(&serde_json::to_vec(key).unwrap_or_default())
+    }
+
+    /// 根据apply查询出的行（最多limit + 1行）生成CursorOutput，next_cursor取自本页最后一行
+    pub fn output<T, V1, V2, F>(&self, mut rows: Vec<T>, limit: i64, key_fn: F) -> CursorOutput<T>
+    where
+        V1: Serialize,
+        V2: Serialize,
+        F: Fn(&T) -> (V1, V2),
+    {
+        let limit = usize::try_from(limit.max(0)).unwrap_or(usize::MAX);
+        // limit为0时取不到下一页的游标，不返回has_more以免客户端无法继续
+        let has_more = limit > 0 && rows.len() > limit;
+        rows.truncate(limit);
+        let next_cursor = if has_more {
+            rows.last().map(|row| self.encode_cursor(&key_fn(row)))
+        } else {
+            None
+        };
+        CursorOutput {
+            items: rows,
+            limit,
+            next_cursor,
+            has_more,
+        }
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(input: &str) -> Option<Vec<u8>> {
+    if !input.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..input.len())
+        .step_by(2)
+        .map(|i| {
+            input
+                .get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::prelude::*;
+    use diesel::sqlite::{Sqlite, SqliteConnection};
+
+    // diesel 1.4的table!宏展开后会触发non_local_definitions
+    #[allow(non_local_definitions)]
+    mod schema {
+        table! {
+            events (id) {
+                id -> Integer,
+                created_at -> BigInt,
+            }
+        }
+    }
+    use schema::events;
+
+    fn connection() -> SqliteConnection {
+        let conn = SqliteConnection::establish(":memory:").unwrap();
+        diesel::sql_query(
+            "CREATE TABLE events (id INTEGER PRIMARY KEY, created_at BIGINT NOT NULL)",
+        )
+        .execute(&conn)
+        .unwrap();
+        // 多行共享同一个created_at，检查相同排序值的行不会被跳过或重复
+        for id in 1..=10 {
+            diesel::insert_into(events::table)
+                .values((events::id.eq(id), events::created_at.eq(i64::from(id / 3))))
+                .execute(&conn)
+                .unwrap();
+        }
+        conn
+    }
+
+    fn page(
+        conn: &SqliteConnection,
+        order: SortOrder,
+        cursor: Option<&str>,
+    ) -> CursorOutput<(i32, i64)> {
+        let keyset = Keyset::new(events::created_at, events::id, order);
+        let after = keyset.decode_cursor::<i64, i32>(cursor).unwrap();
+        let query: events::BoxedQuery<'static, Sqlite> = events::table.into_boxed();
+        let rows: Vec<(i32, i64)> = keyset.apply(query, after, 4).load(conn).unwrap();
+        keyset.output(rows, 4, |row| (row.1, row.0))
+    }
+
+    fn walk(order: SortOrder) -> Vec<Vec<i32>> {
+        let conn = connection();
+        let mut pages = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let output = page(&conn, order, cursor.as_deref());
+            pages.push(output.items.iter().map(|row| row.0).collect());
+            match output.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        pages
+    }
+
+    #[test]
+    fn test_desc_pages() {
+        assert_eq!(
+            walk(SortOrder::Desc),
+            vec![vec![10, 9, 8, 7], vec![6, 5, 4, 3], vec![2, 1]]
+        );
+    }
+
+    #[test]
+    fn test_asc_pages() {
+        assert_eq!(
+            walk(SortOrder::Asc),
+            vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8], vec![9, 10]]
+        );
+    }
+
+    #[test]
+    fn test_limit_bounds() {
+        let conn = connection();
+        let keyset = Keyset::new(events::created_at, events::id, SortOrder::Asc);
+        let query: events::BoxedQuery<'static, Sqlite> = events::table.into_boxed();
+        let rows: Vec<(i32, i64)> = keyset
+            .apply(query, None::<(i64, i32)>, i64::MAX)
+            .load(&conn)
+            .unwrap();
+        assert_eq!(rows.len(), 10);
+
+        let query: events::BoxedQuery<'static, Sqlite> = events::table.into_boxed();
+        let rows: Vec<(i32, i64)> = keyset
+            .apply(query, None::<(i64, i32)>, 0)
+            .load(&conn)
+            .unwrap();
+        let output = keyset.output(rows, 0, |row| (row.1, row.0));
+        assert!(output.items.is_empty());
+        assert!(!output.has_more);
+        assert!(output.next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_invalid_cursor() {
+        let keyset = Keyset::new(events::created_at, events::id, SortOrder::Desc);
+        assert!(keyset.decode_cursor::<i64, i32>(None).unwrap().is_none());
+        let err = keyset.decode_cursor::<i64, i32>(Some("zz")).unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("cursor"));
+        let cursor = keyset.encode_cursor(&(5i64, 3i32));
+        assert_eq!(
+            keyset.decode_cursor::<i64, i32>(Some(&cursor)).unwrap(),
+            Some((5, 3))
+        );
+    }
+
+    #[cfg(feature = "cursor")]
+    #[test]
+    fn test_signed_cursor() {
+        let keyset = Keyset::new(events::created_at, events::id, SortOrder::Desc)
+            .codec(CursorCodec::new("secret"));
+        let cursor = keyset.encode_cursor(&(5i64, 3i32));
+        assert_eq!(
+            keyset.decode_cursor::<i64, i32>(Some(&cursor)).unwrap(),
+            Some((5, 3))
+        );
+
+        // 未签名的游标被拒绝
+        let plain = Keyset::new(events::created_at, events::id, SortOrder::Desc)
+            .encode_cursor(&(5i64, 3i32));
+        let err = keyset.decode_cursor::<i64, i32>(Some(&plain)).unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("cursor"));
+    }
+}
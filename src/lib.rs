@@ -1,38 +1,61 @@
+#[cfg(all(feature = "actix3", feature = "actix4"))]
+compile_error!("features `actix3` and `actix4` are mutually exclusive");
+
+#[cfg(not(any(feature = "actix3", feature = "actix4")))]
+compile_error!("one of the features `actix3` or `actix4` must be enabled");
+
+#[cfg(all(feature = "actix3", not(feature = "actix4")))]
+extern crate actix_web3 as actix_web;
+
+pub mod batch;
+#[cfg(feature = "actix4")]
+pub mod blocking;
+#[cfg(feature = "client")]
+pub mod client;
+pub(crate) mod compat;
+pub mod config;
 pub mod define;
 pub mod err;
+#[cfg(any(feature = "csv", feature = "xlsx"))]
+pub mod export;
+#[cfg(feature = "actix4")]
+pub mod extract;
+#[cfg(feature = "actix4")]
+pub mod handler;
+#[cfg(feature = "actix4")]
+pub mod health;
+pub mod js_safe;
+pub mod keyset;
+pub mod middleware;
+#[cfg(feature = "multipart")]
+pub mod multipart;
+#[cfg(feature = "utoipa")]
+pub mod openapi;
+pub mod output;
+pub mod paginate;
 pub mod query;
+#[cfg(feature = "ws")]
+pub mod ws;
 
 #[macro_use]
 extern crate serde_derive;
+#[cfg(test)]
+#[macro_use]
+extern crate diesel;
 extern crate serde_json;
 
-use actix_web::http::header;
-use actix_web::web::JsonConfig;
-use actix_web::{error::InternalError, HttpResponse};
-use serde_json::json;
-
-pub fn get_default_jsonconfig() -> JsonConfig {
-    JsonConfig::default()
-        .limit(1024 * 1024 * 1000)
-        .error_handler(|err, _| {
-            let err_msg = format!("{:?}", err);
-            InternalError::from_response(
-                err,
-                HttpResponse::BadRequest()
-                    .insert_header((header::CONTENT_TYPE, "application/json"))
-                    .body(
-                        json!({
-                            "error":{
-                                "status": 500,
-                                "details":{
-                                    "status_text": err_msg,
-                                    "desc": "json解析错误"
-                                }
-                            }
-                        })
-                        .to_string(),
-                    ),
-            )
-            .into()
-        })
-}
+#[cfg(feature = "actix4")]
+pub use blocking::blocking;
+#[cfg(feature = "actix4")]
+pub use config::{
+    get_default_formconfig, get_default_payloadconfig, register_default_configs, DefaultConfigs,
+    FormConfigBuilder,
+};
+pub use config::{
+    get_default_jsonconfig, get_default_pathconfig, get_default_queryconfig, JsonConfigBuilder,
+};
+#[cfg(feature = "actix4")]
+pub use handler::{default_not_found, default_service, method_not_allowed};
+#[cfg(feature = "actix4")]
+pub use health::{health_handler, HealthCheck};
+pub use output::DataOutTpl;
@@ -0,0 +1,416 @@
+use super::RequestId;
+use crate::err::{Error, ErrorCode, ErrorList};
+use actix_web::body::{to_bytes, BodySize, EitherBody, MessageBody};
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header;
+use actix_web::{HttpMessage, HttpRequest};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use serde_json::Value;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Instant;
+
+/// 需要解析响应体获取错误码时，最多读取的字节数
+const MAX_SNIFF_BODY: u64 = 4096;
+
+/// 一条访问日志
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AccessRecord {
+    pub method: String,
+    /// 路由模板（如`/devices/{id}`），未匹配到路由时为`unmatched`
+    pub route: String,
+    pub status: u16,
+    pub duration_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// 标准错误码
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<u16>,
+}
+
+type Sink = Rc<dyn Fn(&AccessRecord)>;
+
+#[cfg(not(feature = "tracing"))]
+fn default_sink(record: &AccessRecord) {
+    log::info!(
+        target: "access",
+        "{}",
+        serde_json::to_string(record).unwrap_or_default()
+    );
+}
+
+#[cfg(feature = "tracing")]
+fn default_sink(record: &AccessRecord) {
+    tracing::info!(
+        target: "access",
+        method = %record.method,
+        route = %record.route,
+        status = record.status,
+        duration_ms = record.duration_ms,
+        size = ?record.size,
+        request_id = ?record.request_id,
+        code = ?record.code,
+    );
+}
+
+/// 结构化访问日志中间件，每个请求结束后输出一条AccessRecord
+///
+/// 错误码优先读取err.rs写入的响应extensions，其他组件的小于4KB的JSON错误响应会解析响应体获取。
+/// 默认输出到log（target为access），开启tracing特性后输出到tracing
+///
+/// # Example
+///
+/// ```ignore
+/// App::new()
+///     .wrap(AccessLog::new().success_sampling(10))
+///     .wrap(AssignRequestId::new())
+/// ```
+#[derive(Clone)]
+pub struct AccessLog {
+    sink: Sink,
+    success_sampling: u64,
+}
+
+impl Default for AccessLog {
+    fn default() -> Self {
+        AccessLog {
+            sink: Rc::new(default_sink),
+            success_sampling: 1,
+        }
+    }
+}
+
+impl AccessLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 自定义日志输出
+    pub fn sink<F: Fn(&AccessRecord) + 'static>(mut self, sink: F) -> Self {
+        self.sink = Rc::new(sink);
+        self
+    }
+
+    /// 2xx响应每n条记录一条，0表示不记录2xx响应，默认全部记录
+    pub fn success_sampling(mut self, n: u64) -> Self {
+        self.success_sampling = n;
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for AccessLog
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = AccessLogMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AccessLogMiddleware {
+            service: Rc::new(service),
+            sink: Rc::clone(&self.sink),
+            success_sampling: self.success_sampling,
+            success_count: Rc::new(Cell::new(0)),
+        }))
+    }
+}
+
+pub struct AccessLogMiddleware<S> {
+    service: Rc<S>,
+    sink: Sink,
+    success_sampling: u64,
+    success_count: Rc<Cell<u64>>,
+}
+
+impl<S> AccessLogMiddleware<S> {
+    fn sampled(success_sampling: u64, success_count: &Cell<u64>) -> bool {
+        if success_sampling == 0 {
+            return false;
+        }
+        let count = success_count.get();
+        success_count.set(count.wrapping_add(1));
+        count.is_multiple_of(success_sampling)
+    }
+}
+
+fn sniff_code(body: &[u8]) -> Option<u16> {
+    let value: Value = serde_json::from_slice(body).ok()?;
+    let code = value.get("error")?.get("details")?.get(0)?.get("code")?;
+    code.as_u64().and_then(|code| u16::try_from(code).ok())
+}
+
+fn route(req: &HttpRequest) -> String {
+    req.match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string())
+}
+
+fn request_id(req: &HttpRequest) -> Option<String> {
+    req.extensions().get::<RequestId>().map(|id| id.0.clone())
+}
+
+/// 本crate的错误类型可以直接得到错误码，其他错误返回None
+fn error_code(err: &actix_web::Error) -> Option<u16> {
+    if let Some(err) = err.as_error::<Error>() {
+        return Some(err.code());
+    }
+    err.as_error::<ErrorList>().map(ErrorList::code)
+}
+
+fn body_size(size: BodySize) -> Option<u64> {
+    match size {
+        BodySize::Sized(size) => Some(size),
+        BodySize::None => Some(0),
+        BodySize::Stream => None,
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for AccessLogMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let start = Instant::now();
+        let method = req.method().to_string();
+        let service = Rc::clone(&self.service);
+        let sink = Rc::clone(&self.sink);
+        let success_sampling = self.success_sampling;
+        let success_count = Rc::clone(&self.success_count);
+
+        // 内层服务返回Err时拿不到请求，提前记录路由和请求ID（克隆HttpRequest会导致路由匹配失败）
+        let error_route = route(req.request());
+        let error_request_id = request_id(req.request());
+
+        Box::pin(async move {
+            let res = match service.call(req).await {
+                Ok(res) => res,
+                Err(err) => {
+                    // 内层服务返回Err时不能调用error_response：InternalError::from_response只能取出一次响应，
+                    // 这里取走后客户端会收到空的500。状态码和错误码直接从错误中读取，再原样向外传递
+                    sink(&AccessRecord {
+                        method,
+                        route: error_route,
+                        status: err.as_response_error().status_code().as_u16(),
+                        duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+                        size: None,
+                        request_id: error_request_id,
+                        code: error_code(&err),
+                    });
+                    return Err(err);
+                }
+            };
+            let status = res.status();
+            if status.is_success() && !Self::sampled(success_sampling, &success_count) {
+                return Ok(res.map_into_left_body());
+            }
+
+            let route = route(res.request());
+            let request_id = request_id(res.request());
+            let mut code = res
+                .response()
+                .extensions()
+                .get::<ErrorCode>()
+                .map(|code| code.0);
+            let mut size = body_size(res.response().body().size());
+            let is_json = res
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.starts_with("application/json"));
+
+            let res = if code.is_none()
+                && (status.is_client_error() || status.is_server_error())
+                && is_json
+                && size.is_some_and(|size| size <= MAX_SNIFF_BODY)
+            {
+                let (req, res) = res.into_parts();
+                let (res, body) = res.into_parts();
+                let bytes = to_bytes(body)
+                    .await
+                    .map_err(|e| actix_web::error::ErrorInternalServerError(e.into()))?;
+                code = sniff_code(&bytes);
+                size = Some(bytes.len() as u64);
+                ServiceResponse::new(req, res.set_body(bytes).map_into_boxed_body())
+                    .map_into_right_body()
+            } else {
+                res.map_into_left_body()
+            };
+
+            sink(&AccessRecord {
+                method,
+                route,
+                status: status.as_u16(),
+                duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+                size,
+                request_id,
+                code,
+            });
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::define::{DeviceNotFound, TokenMissing};
+    use crate::err::HttpResult;
+    use crate::extract::BearerToken;
+    use crate::middleware::AssignRequestId;
+    use actix_web::dev::Service;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, web, App, HttpResponse};
+    use std::cell::RefCell;
+
+    async fn detail(id: web::Path<u64>) -> HttpResult<String> {
+        if *id == 0 {
+            Err(DeviceNotFound.from_desc("设备不存在"))?
+        }
+        Ok(id.to_string())
+    }
+
+    async fn foreign() -> HttpResponse {
+        HttpResponse::BadRequest()
+            .content_type("application/json")
+            .body(r#"{"error":{"status":400,"details":[{"code":1012}]}}"#)
+    }
+
+    fn capture() -> (AccessLog, Rc<RefCell<Vec<AccessRecord>>>) {
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let captured = Rc::clone(&records);
+        let log = AccessLog::new().sink(move |record| captured.borrow_mut().push(record.clone()));
+        (log, records)
+    }
+
+    #[actix_web::test]
+    async fn test_access_log() {
+        let (log, records) = capture();
+        let app = test::init_service(
+            App::new()
+                .wrap(log)
+                .wrap(AssignRequestId::new())
+                .route("/devices/{id}", web::get().to(detail))
+                .route("/foreign", web::get().to(foreign)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/devices/7").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let req = test::TestRequest::get().uri("/devices/0").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let req = test::TestRequest::get().uri("/foreign").to_request();
+        let res = test::call_service(&app, req).await;
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["error"]["details"][0]["code"], 1012);
+
+        let records = records.borrow();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].route, "/devices/{id}");
+        assert_eq!(records[0].status, 200);
+        assert_eq!(records[0].code, None);
+        assert_eq!(records[0].size, Some(1));
+        assert!(records[0].request_id.is_some());
+        assert_eq!(records[1].status, 404);
+        assert_eq!(records[1].code, Some(4004));
+        assert_eq!(records[2].code, Some(1012));
+    }
+
+    #[actix_web::test]
+    async fn test_service_error() {
+        let (log, records) = capture();
+        let app = test::init_service(
+            App::new()
+                .wrap_fn(|req, srv| {
+                    let fail = req.path() == "/devices/9";
+                    let fut = srv.call(req);
+                    async move {
+                        if fail {
+                            return Err(crate::err::Error::new(StatusCode::NOT_FOUND)
+                                .err(DeviceNotFound.from_desc("设备不存在"))
+                                .into());
+                        }
+                        fut.await
+                    }
+                })
+                .wrap(log)
+                .route("/devices/{id}", web::get().to(detail)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/devices/9").to_request();
+        assert!(app.call(req).await.is_err());
+        let records = records.borrow();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].route, "/devices/{id}");
+        assert_eq!(records[0].status, 404);
+        assert_eq!(records[0].code, Some(4004));
+    }
+
+    #[actix_web::test]
+    async fn test_service_error_headers() {
+        let (log, records) = capture();
+        let app = test::init_service(
+            App::new()
+                .wrap_fn(|mut req, srv| {
+                    let token = req.extract::<BearerToken>();
+                    let fut = srv.call(req);
+                    async move {
+                        token.await?;
+                        fut.await
+                    }
+                })
+                .wrap(log)
+                .route("/devices/{id}", web::get().to(detail)),
+        )
+        .await;
+
+        // 服务端最终通过error_response把Err渲染给客户端
+        let req = test::TestRequest::get().uri("/devices/9").to_request();
+        let err = app.call(req).await.unwrap_err();
+        let res = err.error_response();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert!(res.headers().contains_key(header::WWW_AUTHENTICATE));
+        let body = to_bytes(res.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["status"], 401);
+        assert_eq!(body["error"]["details"][0]["code"], TokenMissing.code());
+        let records = records.borrow();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].status, 401);
+        assert_eq!(records[0].code, Some(TokenMissing.code()));
+        assert_eq!(records[0].size, None);
+    }
+
+    #[actix_web::test]
+    async fn test_success_sampling() {
+        let (log, records) = capture();
+        let app = test::init_service(
+            App::new()
+                .wrap(log.success_sampling(2))
+                .route("/devices/{id}", web::get().to(detail)),
+        )
+        .await;
+        for id in [1, 2, 3, 0] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/devices/{}", id))
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+        let statuses: Vec<u16> = records.borrow().iter().map(|r| r.status).collect();
+        assert_eq!(statuses, vec![200, 200, 404]);
+    }
+}
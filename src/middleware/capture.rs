@@ -0,0 +1,164 @@
+use actix_http::BoxedPayloadStream;
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::{HttpMessage, HttpRequest};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use futures_util::StreamExt;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// 请求体的前若干字节，由CaptureBody中间件在提取器读取请求体时同步记录
+#[derive(Clone)]
+pub(crate) struct CapturedBody {
+    buf: Rc<RefCell<BytesMut>>,
+    /// 请求体超过记录上限时为true
+    truncated: Rc<RefCell<bool>>,
+}
+
+impl CapturedBody {
+    /// 已记录的请求体和是否被截断
+    pub(crate) fn snapshot(&self) -> (Bytes, bool) {
+        (self.buf.borrow().clone().freeze(), *self.truncated.borrow())
+    }
+}
+
+/// 已记录的请求体和是否被截断，未使用CaptureBody中间件时返回None
+pub(crate) fn captured_body(req: &HttpRequest) -> Option<(Bytes, bool)> {
+    let captured = req.extensions().get::<CapturedBody>().cloned()?;
+    Some(captured.snapshot())
+}
+
+/// 替换请求的payload，在提取器读取请求体时同步记录前limit字节；已经记录过时直接返回
+pub(crate) fn capture_payload(req: &mut ServiceRequest, limit: usize) -> CapturedBody {
+    if let Some(captured) = req.extensions().get::<CapturedBody>() {
+        return captured.clone();
+    }
+    let captured = CapturedBody {
+        buf: Rc::new(RefCell::new(BytesMut::new())),
+        truncated: Rc::new(RefCell::new(false)),
+    };
+    req.extensions_mut().insert(captured.clone());
+
+    let tee = captured.clone();
+    let stream = req.take_payload().map(move |chunk| {
+        if let Ok(bytes) = &chunk {
+            let mut buf = tee.buf.borrow_mut();
+            let remaining = limit.saturating_sub(buf.len());
+            if bytes.len() > remaining {
+                *tee.truncated.borrow_mut() = true;
+            }
+            buf.extend_from_slice(&bytes[..bytes.len().min(remaining)]);
+        }
+        chunk
+    });
+    let stream: BoxedPayloadStream = Box::pin(stream);
+    req.set_payload(Payload::from(stream));
+    captured
+}
+
+/// 在不影响提取器的情况下记录请求体的前limit字节（默认4KB）
+///
+/// 配合JsonConfigBuilder::echo_body使用，解析失败时可以在错误详情中返回请求体预览
+///
+/// # Example
+///
+/// ```ignore
+/// App::new()
+///     .wrap(CaptureBody::new())
+///     .app_data(JsonConfigBuilder::new().echo_body(512).build())
+/// ```
+#[derive(Debug, Clone)]
+pub struct CaptureBody {
+    limit: usize,
+}
+
+impl Default for CaptureBody {
+    fn default() -> Self {
+        CaptureBody { limit: 4096 }
+    }
+}
+
+impl CaptureBody {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CaptureBody
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = CaptureBodyMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CaptureBodyMiddleware {
+            service,
+            limit: self.limit,
+        }))
+    }
+}
+
+pub struct CaptureBodyMiddleware<S> {
+    service: S,
+    limit: usize,
+}
+
+impl<S, B> Service<ServiceRequest> for CaptureBodyMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        capture_payload(&mut req, self.limit);
+        Box::pin(self.service.call(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, web, App, HttpResponse};
+
+    async fn echo(req: HttpRequest, body: web::Bytes) -> HttpResponse {
+        let (captured, truncated) = captured_body(&req).unwrap();
+        HttpResponse::Ok().json(serde_json::json!({
+            "len": body.len(),
+            "captured": String::from_utf8_lossy(&captured),
+            "truncated": truncated,
+        }))
+    }
+
+    #[actix_web::test]
+    async fn test_capture_body() {
+        let app = test::init_service(
+            App::new()
+                .wrap(CaptureBody::new().limit(5))
+                .route("/echo", web::post().to(echo)),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/echo")
+            .set_payload("hello world")
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["len"], 11);
+        assert_eq!(body["captured"], "hello");
+        assert_eq!(body["truncated"], true);
+    }
+}
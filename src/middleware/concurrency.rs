@@ -0,0 +1,254 @@
+use super::current_locale;
+use crate::define::ServerBusy;
+use crate::err::Error;
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::StatusCode;
+use actix_web::ResponseError;
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// 持有许可期间计入处理中的请求数
+struct InFlight {
+    _permit: OwnedSemaphorePermit,
+    #[cfg(feature = "metrics")]
+    gauge: Option<prometheus::IntGauge>,
+}
+
+impl InFlight {
+    #[cfg(feature = "metrics")]
+    fn new(permit: OwnedSemaphorePermit, gauge: Option<prometheus::IntGauge>) -> Self {
+        if let Some(gauge) = &gauge {
+            gauge.inc();
+        }
+        InFlight {
+            _permit: permit,
+            gauge,
+        }
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    fn new(permit: OwnedSemaphorePermit) -> Self {
+        InFlight { _permit: permit }
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if let Some(gauge) = &self.gauge {
+            gauge.dec();
+        }
+    }
+}
+
+/// 并发限制中间件，同时处理的请求超过上限时返回503标准错误结构（ServerBusy）和Retry-After头
+///
+/// 可以设置一个较短的等待时间，在此期间有请求完成则继续处理。
+/// 许可在处理函数返回响应后释放，不包括发送响应体的时间。
+/// ConcurrencyLimit在HttpServer::new外构造后clone给每个worker，所有worker共享同一组许可
+///
+/// # Example
+///
+/// ```ignore
+/// let limit = ConcurrencyLimit::new(256).max_wait(Duration::from_millis(50));
+/// HttpServer::new(move || App::new().wrap(limit.clone()))
+/// ```
+#[derive(Clone)]
+pub struct ConcurrencyLimit {
+    semaphore: Arc<Semaphore>,
+    max_wait: Duration,
+    retry_after: u64,
+    #[cfg(feature = "metrics")]
+    gauge: Option<prometheus::IntGauge>,
+}
+
+impl ConcurrencyLimit {
+    pub fn new(permits: usize) -> Self {
+        ConcurrencyLimit {
+            semaphore: Arc::new(Semaphore::new(permits)),
+            max_wait: Duration::ZERO,
+            retry_after: 1,
+            #[cfg(feature = "metrics")]
+            gauge: None,
+        }
+    }
+
+    /// 没有可用许可时最多等待的时间，默认不等待
+    pub fn max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+
+    /// 503响应中Retry-After的秒数，默认1秒
+    pub fn retry_after(mut self, seconds: u64) -> Self {
+        self.retry_after = seconds;
+        self
+    }
+
+    /// 在registry中注册http_requests_in_flight，记录正在处理的请求数
+    #[cfg(feature = "metrics")]
+    pub fn register_gauge(mut self, registry: &prometheus::Registry) -> prometheus::Result<Self> {
+        let gauge = prometheus::IntGauge::new(
+            "http_requests_in_flight",
+            "Number of HTTP requests being processed",
+        )?;
+        registry.register(Box::new(gauge.clone()))?;
+        self.gauge = Some(gauge);
+        Ok(self)
+    }
+
+    async fn acquire(&self) -> Option<InFlight> {
+        let permit = match Arc::clone(&self.semaphore).try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) if self.max_wait.is_zero() => return None,
+            Err(_) => {
+                let acquire = Arc::clone(&self.semaphore).acquire_owned();
+                actix_web::rt::time::timeout(self.max_wait, acquire)
+                    .await
+                    .ok()?
+                    .ok()?
+            }
+        };
+        #[cfg(feature = "metrics")]
+        return Some(InFlight::new(permit, self.gauge.clone()));
+        #[cfg(not(feature = "metrics"))]
+        Some(InFlight::new(permit))
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ConcurrencyLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = ConcurrencyLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ConcurrencyLimitMiddleware {
+            service: Rc::new(service),
+            limit: self.clone(),
+        }))
+    }
+}
+
+pub struct ConcurrencyLimitMiddleware<S> {
+    service: Rc<S>,
+    limit: ConcurrencyLimit,
+}
+
+impl<S, B> Service<ServiceRequest> for ConcurrencyLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let limit = self.limit.clone();
+
+        Box::pin(async move {
+            let Some(_in_flight) = limit.acquire().await else {
+                let desc = current_locale()
+                    .pick("服务繁忙，请稍后重试", "server is busy, please retry later");
+                let response = Error::new(StatusCode::SERVICE_UNAVAILABLE)
+                    .err(ServerBusy.from_desc(desc))
+                    .retry_after(limit.retry_after)
+                    .error_response();
+                return Ok(req.into_response(response).map_into_right_body());
+            };
+            Ok(service.call(req).await?.map_into_left_body())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::RETRY_AFTER;
+    use actix_web::{test, web, App, HttpResponse};
+    use futures_util::future::join_all;
+    use serde_json::Value;
+
+    async fn slow() -> HttpResponse {
+        actix_web::rt::time::sleep(Duration::from_millis(100)).await;
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_web::test]
+    async fn test_concurrency_limit() {
+        let app = test::init_service(
+            App::new()
+                .wrap(ConcurrencyLimit::new(2).retry_after(3))
+                .route("/", web::get().to(slow)),
+        )
+        .await;
+
+        let responses = join_all((0..3).map(|_| {
+            let req = test::TestRequest::get().uri("/").to_request();
+            test::call_service(&app, req)
+        }))
+        .await;
+        let mut statuses: Vec<StatusCode> = responses.iter().map(|res| res.status()).collect();
+        statuses.sort();
+        assert_eq!(
+            statuses,
+            vec![
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::SERVICE_UNAVAILABLE
+            ]
+        );
+        let busy = responses
+            .into_iter()
+            .find(|res| res.status() == StatusCode::SERVICE_UNAVAILABLE)
+            .unwrap();
+        assert_eq!(busy.headers().get(RETRY_AFTER).unwrap(), "3");
+        let body: Value = test::read_body_json(busy).await;
+        assert_eq!(body["error"]["details"][0]["code"], 5004);
+        assert_eq!(body["error"]["retry_after_secs"], 3);
+
+        let req = test::TestRequest::get().uri("/").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_bounded_wait() {
+        let app = test::init_service(
+            App::new()
+                .wrap(ConcurrencyLimit::new(1).max_wait(Duration::from_millis(500)))
+                .route("/", web::get().to(slow)),
+        )
+        .await;
+        let responses = join_all((0..2).map(|_| {
+            let req = test::TestRequest::get().uri("/").to_request();
+            test::call_service(&app, req)
+        }))
+        .await;
+        assert!(responses.iter().all(|res| res.status() == StatusCode::OK));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[actix_web::test]
+    async fn test_in_flight_gauge() {
+        let registry = prometheus::Registry::new();
+        let limit = ConcurrencyLimit::new(2).register_gauge(&registry).unwrap();
+        let gauge = limit.gauge.clone().unwrap();
+        let in_flight = limit.acquire().await.unwrap();
+        assert_eq!(gauge.get(), 1);
+        drop(in_flight);
+        assert_eq!(gauge.get(), 0);
+    }
+}
@@ -0,0 +1,255 @@
+use super::request_locale;
+use crate::config::unsupported_content_type;
+use crate::err::Error;
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::{Method, StatusCode};
+use actix_web::ResponseError;
+use futures_util::future::{ready, Either, LocalBoxFuture, Ready};
+use std::rc::Rc;
+
+/// 校验写请求的Content-Type，不在允许列表中时返回415标准错误结构
+///
+/// 默认只检查POST、PUT、PATCH，只允许application/json（可以带charset等参数）。
+/// 没有请求体的请求不检查。宽松模式下把text/plain改写为application/json而不是拒绝
+///
+/// # Example
+///
+/// ```ignore
+/// App::new().wrap(
+///     RequireContentType::new()
+///         .allow("application/x-ndjson")
+///         .exclude("/upload"),
+/// )
+/// ```
+#[derive(Debug, Clone)]
+pub struct RequireContentType {
+    methods: Vec<Method>,
+    allowed: Vec<String>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    lenient: bool,
+}
+
+impl Default for RequireContentType {
+    fn default() -> Self {
+        RequireContentType {
+            methods: vec![Method::POST, Method::PUT, Method::PATCH],
+            allowed: vec!["application/json".to_string()],
+            include: Vec::new(),
+            exclude: Vec::new(),
+            lenient: false,
+        }
+    }
+}
+
+impl RequireContentType {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 需要检查的请求方法，替换默认值
+    pub fn methods<I: IntoIterator<Item = Method>>(mut self, methods: I) -> Self {
+        self.methods = methods.into_iter().collect();
+        self
+    }
+
+    /// 增加允许的Content-Type
+    pub fn allow<S: Into<String>>(mut self, mime: S) -> Self {
+        self.allowed.push(mime.into().to_ascii_lowercase());
+        self
+    }
+
+    /// 只检查这些路径前缀下的请求，不设置时检查所有路径
+    pub fn include<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.include.push(prefix.into());
+        self
+    }
+
+    /// 不检查这些路径前缀下的请求
+    pub fn exclude<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.exclude.push(prefix.into());
+        self
+    }
+
+    /// 把text/plain改写为application/json
+    pub fn lenient(mut self) -> Self {
+        self.lenient = true;
+        self
+    }
+
+    fn applies(&self, req: &ServiceRequest) -> bool {
+        let path = req.path();
+        self.methods.contains(req.method())
+            && (self.include.is_empty() || self.include.iter().any(|p| path.starts_with(p)))
+            && !self.exclude.iter().any(|p| path.starts_with(p))
+            && has_body(req)
+    }
+}
+
+fn has_body(req: &ServiceRequest) -> bool {
+    let headers = req.headers();
+    if headers.contains_key(header::TRANSFER_ENCODING) {
+        return true;
+    }
+    headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .is_some_and(|length| length > 0)
+}
+
+/// Content-Type去掉参数后的小写形式
+fn essence(req: &ServiceRequest) -> Option<String> {
+    let value = req.headers().get(header::CONTENT_TYPE)?.to_str().ok()?;
+    Some(value.split(';').next()?.trim().to_ascii_lowercase())
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequireContentType
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = RequireContentTypeMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequireContentTypeMiddleware {
+            service: Rc::new(service),
+            config: Rc::new(self.clone()),
+        }))
+    }
+}
+
+pub struct RequireContentTypeMiddleware<S> {
+    service: Rc<S>,
+    config: Rc<RequireContentType>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequireContentTypeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = Either<
+        LocalBoxFuture<'static, Result<Self::Response, Self::Error>>,
+        Ready<Result<Self::Response, Self::Error>>,
+    >;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        if self.config.applies(&req) {
+            let essence = essence(&req);
+            let allowed = essence
+                .as_ref()
+                .is_some_and(|essence| self.config.allowed.contains(essence));
+            if !allowed {
+                if self.config.lenient && essence.as_deref() == Some("text/plain") {
+                    req.headers_mut().insert(
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static("application/json"),
+                    );
+                } else {
+                    let locale = request_locale(req.request());
+                    let mut detail = unsupported_content_type(locale, req.request());
+                    detail.desc = format!(
+                        "{}, {}: {}",
+                        detail.desc,
+                        locale.pick("允许的类型", "allowed types"),
+                        self.config.allowed.join(", ")
+                    );
+                    let detail = detail.with_extra("allowed", self.config.allowed.clone());
+                    let response = Error::new(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+                        .err(detail)
+                        .error_response();
+                    return Either::Right(ready(Ok(req
+                        .into_response(response)
+                        .map_into_right_body())));
+                }
+            }
+        }
+
+        let fut = self.service.call(req);
+        Either::Left(Box::pin(async move { Ok(fut.await?.map_into_left_body()) }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, web, App, HttpRequest};
+    use serde_json::Value;
+
+    async fn echo(req: HttpRequest) -> String {
+        req.headers()
+            .get(header::CONTENT_TYPE)
+            .map(|value| value.to_str().unwrap().to_string())
+            .unwrap_or_default()
+    }
+
+    async fn call(config: RequireContentType, req: test::TestRequest) -> (StatusCode, web::Bytes) {
+        let app = test::init_service(App::new().wrap(config).default_service(web::to(echo))).await;
+        let res = test::call_service(&app, req.to_request()).await;
+        (res.status(), test::read_body(res).await)
+    }
+
+    fn post(path: &str, content_type: &str) -> test::TestRequest {
+        test::TestRequest::post()
+            .uri(path)
+            .insert_header((header::CONTENT_TYPE, content_type))
+            .set_payload("{}")
+    }
+
+    #[actix_web::test]
+    async fn test_reject() {
+        let (status, body) = call(RequireContentType::new(), post("/devices", "text/plain")).await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let detail = &body["error"]["details"][0];
+        assert_eq!(detail["code"], 2010);
+        assert!(detail["desc"].as_str().unwrap().contains("text/plain"));
+        assert_eq!(
+            detail["extra"]["allowed"],
+            serde_json::json!(["application/json"])
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_lenient_rewrite() {
+        let config = RequireContentType::new().lenient();
+        let (status, body) = call(config, post("/devices", "text/plain")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "application/json");
+    }
+
+    #[actix_web::test]
+    async fn test_allowed_with_charset() {
+        let req = post("/devices", "Application/JSON; charset=utf-8");
+        let (status, _) = call(RequireContentType::new(), req).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_excluded_and_bodyless() {
+        let config = RequireContentType::new().exclude("/upload");
+        let (status, _) = call(config, post("/upload/file", "application/octet-stream")).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let req = test::TestRequest::post().uri("/devices/1/reboot");
+        let (status, _) = call(RequireContentType::new(), req).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let req = test::TestRequest::get()
+            .uri("/devices")
+            .insert_header((header::CONTENT_TYPE, "text/plain"));
+        let (status, _) = call(RequireContentType::new(), req).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
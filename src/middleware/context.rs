@@ -0,0 +1,45 @@
+use crate::define::{default_locale, explicit_default_locale, Locale};
+use actix_web::{HttpMessage, HttpRequest};
+
+tokio::task_local! {
+    pub(super) static REQUEST_LOCALE: Locale;
+    pub(super) static REQUEST_ID: String;
+}
+
+/// 当前请求的语言，不在AcceptLanguage中间件包裹的处理过程中时使用全局默认语言
+pub(crate) fn current_locale() -> Locale {
+    REQUEST_LOCALE
+        .try_with(|locale| *locale)
+        .unwrap_or_else(|_| default_locale())
+}
+
+/// 显式指定的语言：AcceptLanguage从请求中解析出的语言、with_locale指定的语言或set_default_locale设置的全局语言
+pub(crate) fn explicit_locale() -> Option<Locale> {
+    REQUEST_LOCALE
+        .try_with(|locale| *locale)
+        .ok()
+        .or_else(explicit_default_locale)
+}
+
+/// 优先读取request extensions中的语言，用于中间件作用域之外的错误处理
+pub(crate) fn request_locale(req: &HttpRequest) -> Locale {
+    explicit_request_locale(req).unwrap_or_else(default_locale)
+}
+
+/// 与request_locale相同，但没有显式指定语言时返回None
+pub(crate) fn explicit_request_locale(req: &HttpRequest) -> Option<Locale> {
+    HttpMessage::extensions(req)
+        .get::<Locale>()
+        .copied()
+        .or_else(explicit_locale)
+}
+
+/// 在指定语言下执行f，用于渲染提取器错误等不在中间件作用域中的响应
+pub(crate) fn with_locale<R>(locale: Locale, f: impl FnOnce() -> R) -> R {
+    REQUEST_LOCALE.sync_scope(locale, f)
+}
+
+/// 当前请求的RequestId，只在AssignRequestId中间件包裹的处理过程中有值
+pub(crate) fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
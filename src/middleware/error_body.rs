@@ -0,0 +1,266 @@
+use super::capture_payload;
+use crate::err::ErrorCode;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header;
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::rc::Rc;
+
+/// 不记录请求体的Content-Type前缀
+const SKIPPED_CONTENT_TYPES: [&str; 5] = [
+    "multipart/",
+    "application/octet-stream",
+    "image/",
+    "audio/",
+    "video/",
+];
+
+/// 错误响应对应的请求记录
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ErrorBodyRecord {
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    /// 标准错误码
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<u16>,
+    /// 请求体，超过上限或类型不记录时为说明文字
+    pub body: String,
+}
+
+type Sink = Rc<dyn Fn(&ErrorBodyRecord)>;
+type Redactor = Rc<dyn Fn(String) -> String>;
+
+#[cfg(not(feature = "tracing"))]
+fn default_sink(record: &ErrorBodyRecord) {
+    log::warn!(
+        target: "error_body",
+        "{}",
+        serde_json::to_string(record).unwrap_or_default()
+    );
+}
+
+#[cfg(feature = "tracing")]
+fn default_sink(record: &ErrorBodyRecord) {
+    tracing::warn!(
+        target: "error_body",
+        method = %record.method,
+        path = %record.path,
+        status = record.status,
+        code = ?record.code,
+        body = %record.body,
+    );
+}
+
+/// 响应状态码≥400时记录请求体，便于复现客户端反馈的错误
+///
+/// 请求体在提取器读取时同步记录前limit字节（默认4KB），不影响处理函数读取；
+/// 超过上限的请求体和multipart、二进制类型的请求体只记录说明文字
+///
+/// # Example
+///
+/// ```ignore
+/// App::new().wrap(ErrorBodyLog::new().redact(|body| scrub_password(body)))
+/// ```
+#[derive(Clone)]
+pub struct ErrorBodyLog {
+    limit: usize,
+    sink: Sink,
+    redact: Option<Redactor>,
+}
+
+impl Default for ErrorBodyLog {
+    fn default() -> Self {
+        ErrorBodyLog {
+            limit: 4096,
+            sink: Rc::new(default_sink),
+            redact: None,
+        }
+    }
+}
+
+impl ErrorBodyLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// 自定义日志输出
+    pub fn sink<F: Fn(&ErrorBodyRecord) + 'static>(mut self, sink: F) -> Self {
+        self.sink = Rc::new(sink);
+        self
+    }
+
+    /// 记录前对请求体脱敏
+    pub fn redact<F: Fn(String) -> String + 'static>(mut self, redact: F) -> Self {
+        self.redact = Some(Rc::new(redact));
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ErrorBodyLog
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = ErrorBodyLogMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ErrorBodyLogMiddleware {
+            service: Rc::new(service),
+            config: self.clone(),
+        }))
+    }
+}
+
+pub struct ErrorBodyLogMiddleware<S> {
+    service: Rc<S>,
+    config: ErrorBodyLog,
+}
+
+impl<S, B> Service<ServiceRequest> for ErrorBodyLogMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let method = req.method().to_string();
+        let path = req.path().to_string();
+        let content_type = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_ascii_lowercase());
+        let skipped = content_type.as_deref().filter(|content_type| {
+            SKIPPED_CONTENT_TYPES
+                .iter()
+                .any(|prefix| content_type.starts_with(prefix))
+        });
+        let skipped = skipped.map(String::from);
+        let captured = match skipped {
+            Some(_) => None,
+            None => Some(capture_payload(&mut req, self.config.limit)),
+        };
+        let service = Rc::clone(&self.service);
+        let config = self.config.clone();
+
+        Box::pin(async move {
+            let result = service.call(req).await;
+            let (status, code) = match &result {
+                Ok(res) => (
+                    res.status(),
+                    res.response().extensions().get::<ErrorCode>().map(|c| c.0),
+                ),
+                Err(err) => (err.as_response_error().status_code(), None),
+            };
+            if status.as_u16() < 400 {
+                return result;
+            }
+
+            let body = match (&captured, skipped) {
+                (_, Some(content_type)) => format!("[skipped: content-type {}]", content_type),
+                (Some(captured), None) => {
+                    let (bytes, truncated) = captured.snapshot();
+                    if truncated {
+                        format!("[skipped: body exceeds {} bytes]", config.limit)
+                    } else {
+                        let body = String::from_utf8_lossy(&bytes).into_owned();
+                        match &config.redact {
+                            Some(redact) => redact(body),
+                            None => body,
+                        }
+                    }
+                }
+                (None, None) => String::new(),
+            };
+            (config.sink)(&ErrorBodyRecord {
+                method,
+                path,
+                status: status.as_u16(),
+                code,
+                body,
+            });
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_default_jsonconfig;
+    use actix_web::{test, web, App};
+    use std::cell::RefCell;
+
+    #[derive(Deserialize)]
+    struct Login {
+        name: String,
+    }
+
+    async fn login(body: web::Json<Login>) -> String {
+        body.into_inner().name
+    }
+
+    async fn call(body: &str) -> (u16, Vec<ErrorBodyRecord>) {
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let captured = Rc::clone(&records);
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    ErrorBodyLog::new()
+                        .limit(48)
+                        .redact(|body| body.replace("hunter2", "***"))
+                        .sink(move |record| captured.borrow_mut().push(record.clone())),
+                )
+                .app_data(get_default_jsonconfig())
+                .route("/login", web::post().to(login)),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/login")
+            .insert_header((header::CONTENT_TYPE, "application/json"))
+            .set_payload(body.to_string())
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let status = res.status().as_u16();
+        let records = records.borrow().clone();
+        (status, records)
+    }
+
+    #[actix_web::test]
+    async fn test_error_logs_body() {
+        let (status, records) = call(r#"{"name": 1, "password": "hunter2"}"#).await;
+        assert_eq!(status, 400);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].path, "/login");
+        assert_eq!(records[0].code, Some(2006));
+        assert_eq!(records[0].body, r#"{"name": 1, "password": "***"}"#);
+    }
+
+    #[actix_web::test]
+    async fn test_success_not_logged() {
+        let (status, records) = call(r#"{"name": "admin"}"#).await;
+        assert_eq!(status, 200);
+        assert!(records.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_oversized_body_skipped() {
+        let body = format!(r#"{{"name": 1, "padding": "{}"}}"#, "x".repeat(64));
+        let (_, records) = call(&body).await;
+        assert_eq!(records[0].body, "[skipped: body exceeds 48 bytes]");
+    }
+}
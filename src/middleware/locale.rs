@@ -0,0 +1,229 @@
+use super::context::{request_locale, REQUEST_LOCALE};
+use crate::define::Locale;
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header;
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::convert::Infallible;
+use std::rc::Rc;
+
+impl FromRequest for Locale {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(request_locale(req)))
+    }
+}
+
+/// 按q值从高到低选择第一个支持的语言
+fn parse_accept_language(value: &str) -> Option<Locale> {
+    let mut tags: Vec<(&str, f32)> = value
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let tag = parts.next()?.trim();
+            let q = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (!tag.is_empty() && q > 0.0).then_some((tag, q))
+        })
+        .collect();
+    tags.sort_by(|a, b| b.1.total_cmp(&a.1));
+    tags.into_iter().find_map(|(tag, _)| Locale::from_tag(tag))
+}
+
+/// 根据Accept-Language选择错误信息的语言，结果保存在request extensions中
+///
+/// 查询参数（默认`lang`）优先于请求头，都没有可用的语言时使用全局默认语言
+///
+/// # Example
+///
+/// ```ignore
+/// App::new().wrap(AcceptLanguage::new())
+/// ```
+#[derive(Debug, Clone)]
+pub struct AcceptLanguage {
+    query_param: Option<String>,
+}
+
+impl Default for AcceptLanguage {
+    fn default() -> Self {
+        AcceptLanguage {
+            query_param: Some("lang".to_string()),
+        }
+    }
+}
+
+impl AcceptLanguage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 指定覆盖语言的查询参数名
+    pub fn query_param<S: Into<String>>(mut self, name: S) -> Self {
+        self.query_param = Some(name.into());
+        self
+    }
+
+    /// 不允许通过查询参数覆盖语言
+    pub fn disable_query_param(mut self) -> Self {
+        self.query_param = None;
+        self
+    }
+
+    /// 请求中没有可用的语言时返回None，由全局默认语言决定
+    fn resolve(&self, req: &ServiceRequest) -> Option<Locale> {
+        let from_query = self.query_param.as_ref().and_then(|name| {
+            req.query_string()
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| key == name)
+                .and_then(|(_, value)| Locale::from_tag(value))
+        });
+        from_query.or_else(|| {
+            req.headers()
+                .get(header::ACCEPT_LANGUAGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_accept_language)
+        })
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for AcceptLanguage
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = AcceptLanguageMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AcceptLanguageMiddleware {
+            service: Rc::new(service),
+            config: self.clone(),
+        }))
+    }
+}
+
+pub struct AcceptLanguageMiddleware<S> {
+    service: Rc<S>,
+    config: AcceptLanguage,
+}
+
+impl<S, B> Service<ServiceRequest> for AcceptLanguageMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let locale = self.config.resolve(&req);
+        let service = Rc::clone(&self.service);
+        match locale {
+            Some(locale) => {
+                req.extensions_mut().insert(locale);
+                Box::pin(async move { REQUEST_LOCALE.scope(locale, service.call(req)).await })
+            }
+            None => Box::pin(service.call(req)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_default_queryconfig;
+    use crate::handler::default_service;
+    use actix_web::{test, web, App, HttpResponse};
+    use serde_json::Value;
+
+    #[derive(Deserialize)]
+    struct Paging {
+        #[allow(dead_code)]
+        limit: u32,
+    }
+
+    async fn list(_: web::Query<Paging>) -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    async fn call(uri: &str, accept_language: Option<&str>) -> Value {
+        let app = test::init_service(
+            App::new()
+                .wrap(AcceptLanguage::new())
+                .app_data(get_default_queryconfig())
+                .route("/devices", web::get().to(list))
+                .default_service(default_service()),
+        )
+        .await;
+        let mut req = test::TestRequest::get().uri(uri);
+        if let Some(value) = accept_language {
+            req = req.insert_header((header::ACCEPT_LANGUAGE, value));
+        }
+        let res = test::call_service(&app, req.to_request()).await;
+        let body: Value = test::read_body_json(res).await;
+        body["error"]["details"][0].clone()
+    }
+
+    #[actix_web::test]
+    async fn test_parse_accept_language() {
+        assert_eq!(parse_accept_language("zh-TW"), Some(Locale::Zh));
+        assert_eq!(
+            parse_accept_language("fr, en;q=0.5, zh;q=0.8"),
+            Some(Locale::Zh)
+        );
+        assert_eq!(
+            parse_accept_language("zh;q=0, en-GB;q=0.3"),
+            Some(Locale::En)
+        );
+        assert_eq!(parse_accept_language("fr-FR, de"), None);
+    }
+
+    #[actix_web::test]
+    async fn test_locale_from_header() {
+        let detail = call("/missing", Some("zh-CN")).await;
+        assert_eq!(detail["desc"], "请求的路由不存在");
+        assert_eq!(detail["err_type"], "请求的路由不存在");
+        let detail = call("/missing", Some("en-US,en;q=0.9")).await;
+        assert_eq!(detail["desc"], "route not found");
+        assert_eq!(detail["err_type"], "route not found");
+
+        // 提取器错误同样按请求语言返回err_type
+        let detail = call("/devices?limit=x", Some("en-US")).await;
+        assert!(detail["desc"]
+            .as_str()
+            .unwrap()
+            .starts_with("invalid query parameters"));
+        assert_eq!(detail["err_type"], "invalid input parameter");
+        let detail = call("/devices?limit=x", Some("zh-CN")).await;
+        assert_eq!(detail["err_type"], "参数错误");
+    }
+
+    #[actix_web::test]
+    async fn test_locale_fallback_and_override() {
+        assert_eq!(
+            call("/missing", Some("fr-FR")).await["desc"],
+            "请求的路由不存在"
+        );
+        // 请求未指定语言时desc使用全局默认语言，err_type保持英文
+        let detail = call("/missing", None).await;
+        assert_eq!(detail["desc"], "请求的路由不存在");
+        assert_eq!(detail["err_type"], "route not found");
+        let detail = call("/devices?limit=x", Some("fr-FR")).await;
+        assert!(detail["desc"].as_str().unwrap().starts_with("查询参数错误"));
+        assert_eq!(detail["err_type"], "invalid input parameter");
+        let detail = call("/missing?lang=en", Some("zh-CN")).await;
+        assert_eq!(detail["desc"], "route not found");
+        assert_eq!(detail["err_type"], "route not found");
+    }
+}
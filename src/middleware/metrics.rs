@@ -0,0 +1,293 @@
+use crate::err::ErrorCode;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, Route};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use prometheus::{
+    default_registry, histogram_opts, opts, Encoder, HistogramVec, IntCounterVec, Registry,
+    TextEncoder, DEFAULT_BUCKETS,
+};
+use std::rc::Rc;
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+
+struct HttpMetrics {
+    requests: IntCounterVec,
+    duration: HistogramVec,
+    errors: IntCounterVec,
+}
+
+/// Metrics构造器，可指定histogram的桶和注册到的Registry
+pub struct MetricsBuilder {
+    buckets: Vec<f64>,
+    registry: Registry,
+}
+
+impl Default for MetricsBuilder {
+    fn default() -> Self {
+        MetricsBuilder {
+            buckets: DEFAULT_BUCKETS.to_vec(),
+            registry: default_registry().clone(),
+        }
+    }
+}
+
+impl MetricsBuilder {
+    /// http_request_duration_seconds的桶，单位秒
+    pub fn buckets(mut self, buckets: Vec<f64>) -> Self {
+        self.buckets = buckets;
+        self
+    }
+
+    pub fn registry(mut self, registry: Registry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// 同一个Registry只能构造一次，重复注册返回错误
+    pub fn build(self) -> prometheus::Result<Metrics> {
+        let requests = IntCounterVec::new(
+            opts!("http_requests_total", "Total number of HTTP requests"),
+            &["route", "method", "status"],
+        )?;
+        let duration = HistogramVec::new(
+            histogram_opts!(
+                "http_request_duration_seconds",
+                "HTTP request duration in seconds",
+                self.buckets
+            ),
+            &["route", "method"],
+        )?;
+        let errors = IntCounterVec::new(
+            opts!("errors_total", "Total number of standard error responses"),
+            &["code"],
+        )?;
+        self.registry.register(Box::new(requests.clone()))?;
+        self.registry.register(Box::new(duration.clone()))?;
+        self.registry.register(Box::new(errors.clone()))?;
+        Ok(Metrics {
+            inner: Arc::new(HttpMetrics {
+                requests,
+                duration,
+                errors,
+            }),
+        })
+    }
+}
+
+/// Prometheus指标中间件，记录http_requests_total、http_request_duration_seconds和errors_total
+///
+/// route标签使用路由模板，errors_total的code标签读取err.rs写入响应extensions的标准错误码。
+/// Metrics可以Clone，在HttpServer::new外构造后传给每个worker
+///
+/// # Example
+///
+/// ```ignore
+/// let metrics = Metrics::new();
+/// HttpServer::new(move || {
+///     App::new()
+///         .wrap(metrics.clone())
+///         .route("/metrics", actix_util::middleware::metrics_handler())
+/// })
+/// ```
+#[derive(Clone)]
+pub struct Metrics {
+    inner: Arc<HttpMetrics>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    /// 使用默认的桶注册到默认Registry，多次调用返回同一组指标
+    pub fn new() -> Self {
+        static DEFAULT: OnceLock<Metrics> = OnceLock::new();
+        DEFAULT
+            .get_or_init(|| {
+                MetricsBuilder::default()
+                    .build()
+                    .expect("register http metrics")
+            })
+            .clone()
+    }
+
+    pub fn builder() -> MetricsBuilder {
+        MetricsBuilder::default()
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Metrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = MetricsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MetricsMiddleware {
+            service: Rc::new(service),
+            inner: Arc::clone(&self.inner),
+        }))
+    }
+}
+
+pub struct MetricsMiddleware<S> {
+    service: Rc<S>,
+    inner: Arc<HttpMetrics>,
+}
+
+impl HttpMetrics {
+    fn observe(&self, route: &str, method: &str, status: StatusCode, start: Instant) {
+        self.requests
+            .with_label_values(&[route, method, status.as_str()])
+            .inc();
+        self.duration
+            .with_label_values(&[route, method])
+            .observe(start.elapsed().as_secs_f64());
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for MetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let start = Instant::now();
+        let method = req.method().to_string();
+        let route = req.match_pattern();
+        let service = Rc::clone(&self.service);
+        let inner = Arc::clone(&self.inner);
+
+        Box::pin(async move {
+            match service.call(req).await {
+                Ok(res) => {
+                    let route = res
+                        .request()
+                        .match_pattern()
+                        .unwrap_or_else(|| "unmatched".to_string());
+                    inner.observe(&route, &method, res.status(), start);
+                    if let Some(code) = res.response().extensions().get::<ErrorCode>() {
+                        inner.errors.with_label_values(&[&code.0.to_string()]).inc();
+                    }
+                    Ok(res)
+                }
+                Err(err) => {
+                    let route = route.unwrap_or_else(|| "unmatched".to_string());
+                    let status = err.as_response_error().status_code();
+                    inner.observe(&route, &method, status, start);
+                    Err(err)
+                }
+            }
+        })
+    }
+}
+
+fn encode(registry: &Registry) -> HttpResponse {
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    match encoder.encode(&registry.gather(), &mut buffer) {
+        Ok(()) => HttpResponse::Ok()
+            .content_type(encoder.format_type())
+            .body(buffer),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+/// 默认Registry的指标输出端点
+pub fn metrics_handler() -> Route {
+    web::get().to(|| async { encode(default_registry()) })
+}
+
+/// 指定Registry的指标输出端点，配合MetricsBuilder::registry使用
+pub fn metrics_handler_for(registry: Registry) -> Route {
+    web::get().to(move || {
+        let response = encode(&registry);
+        async move { response }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::define::DeviceNotFound;
+    use crate::err::HttpResult;
+    use actix_web::{test, App};
+
+    async fn detail(id: web::Path<u64>) -> HttpResult<String> {
+        if *id == 0 {
+            Err(DeviceNotFound.from_desc("设备不存在"))?
+        }
+        Ok(id.to_string())
+    }
+
+    async fn scrape<S>(app: &S) -> String
+    where
+        S: Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+    {
+        let req = test::TestRequest::get().uri("/metrics").to_request();
+        let body = test::call_and_read_body(app, req).await;
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_metrics() {
+        let registry = Registry::new();
+        let metrics = Metrics::builder()
+            .registry(registry.clone())
+            .buckets(vec![0.1, 1.0])
+            .build()
+            .unwrap();
+        let app = test::init_service(
+            App::new()
+                .wrap(metrics)
+                .route("/devices/{id}", web::get().to(detail))
+                .route("/metrics", metrics_handler_for(registry)),
+        )
+        .await;
+        for id in [1, 2, 0] {
+            let req = test::TestRequest::get()
+                .uri(&format!("/devices/{}", id))
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+
+        let text = scrape(&app).await;
+        assert!(text
+            .contains(r#"http_requests_total{method="GET",route="/devices/{id}",status="200"} 2"#));
+        assert!(text
+            .contains(r#"http_requests_total{method="GET",route="/devices/{id}",status="404"} 1"#));
+        assert!(text.contains(r#"errors_total{code="4004"} 1"#));
+        assert!(text.contains(
+            r#"http_request_duration_seconds_bucket{method="GET",route="/devices/{id}",le="1"} 3"#
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_default_registry() {
+        let app = test::init_service(
+            App::new()
+                .wrap(Metrics::new())
+                .wrap(Metrics::new())
+                .route("/devices/{id}", web::get().to(detail))
+                .route("/metrics", metrics_handler()),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/devices/0").to_request();
+        test::call_service(&app, req).await;
+        assert!(scrape(&app).await.contains("errors_total"));
+    }
+}
@@ -0,0 +1,58 @@
+#[cfg(feature = "actix4")]
+mod access_log;
+#[cfg(feature = "actix4")]
+mod capture;
+#[cfg(feature = "actix4")]
+mod concurrency;
+#[cfg(feature = "actix4")]
+mod content_type;
+mod context;
+#[cfg(feature = "actix4")]
+mod error_body;
+#[cfg(feature = "actix4")]
+mod locale;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "actix4")]
+mod normalize;
+#[cfg(feature = "actix4")]
+mod panic;
+#[cfg(feature = "actix4")]
+mod rate_limit;
+#[cfg(feature = "actix4")]
+mod request_id;
+#[cfg(feature = "actix4")]
+mod timeout;
+
+#[cfg(feature = "actix4")]
+pub use access_log::{AccessLog, AccessLogMiddleware, AccessRecord};
+#[cfg(feature = "actix4")]
+pub(crate) use capture::{capture_payload, captured_body};
+#[cfg(feature = "actix4")]
+pub use capture::{CaptureBody, CaptureBodyMiddleware};
+#[cfg(feature = "actix4")]
+pub use concurrency::{ConcurrencyLimit, ConcurrencyLimitMiddleware};
+#[cfg(feature = "actix4")]
+pub use content_type::{RequireContentType, RequireContentTypeMiddleware};
+pub(crate) use context::{
+    current_locale, current_request_id, explicit_locale, explicit_request_locale, request_locale,
+    with_locale,
+};
+#[cfg(feature = "actix4")]
+pub use error_body::{ErrorBodyLog, ErrorBodyLogMiddleware, ErrorBodyRecord};
+#[cfg(feature = "actix4")]
+pub use locale::{AcceptLanguage, AcceptLanguageMiddleware};
+#[cfg(feature = "metrics")]
+pub use metrics::{
+    metrics_handler, metrics_handler_for, Metrics, MetricsBuilder, MetricsMiddleware,
+};
+#[cfg(feature = "actix4")]
+pub use normalize::{normalize_errors, SkipNormalize};
+#[cfg(feature = "actix4")]
+pub use panic::{CatchPanic, CatchPanicMiddleware};
+#[cfg(feature = "actix4")]
+pub use rate_limit::{RateLimit, RateLimitMiddleware};
+#[cfg(feature = "actix4")]
+pub use request_id::{AssignRequestId, AssignRequestIdMiddleware, RequestId};
+#[cfg(feature = "actix4")]
+pub use timeout::{Timeout, TimeoutMiddleware};
@@ -0,0 +1,178 @@
+use super::request_locale;
+use crate::err::{code_for_status, Error, ErrorCode};
+use actix_web::body::MessageBody;
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header;
+use actix_web::middleware::{ErrorHandlerResponse, ErrorHandlers};
+use actix_web::ResponseError;
+use futures_util::future::poll_fn;
+use std::pin::pin;
+
+/// 原始响应体最多读取的字节数，超出部分截断并以省略号结尾
+const MAX_ORIGINAL_BODY: usize = 4096;
+
+/// 响应扩展中带有该标记时normalize_errors原样返回，用于自带结构化响应体的端点（如健康检查）
+#[derive(Debug, Clone, Copy)]
+pub struct SkipNormalize;
+
+/// 将其他组件（guard、认证中间件、未包装的提取器等）产生的4xx/5xx响应改写为标准错误结构
+///
+/// 错误码根据HTTP状态码选择，原始响应体放入desc（超过4096字节时截断）；已经是标准错误结构的响应原样返回
+///
+/// # Example
+///
+/// ```ignore
+/// App::new()
+///     .wrap(HttpAuthentication::bearer(validator))
+///     .wrap(actix_util::middleware::normalize_errors())
+/// ```
+pub fn normalize_errors<B: MessageBody + 'static>() -> ErrorHandlers<B> {
+    ErrorHandlers::new().default_handler(normalize_response)
+}
+
+fn normalize_response<B: MessageBody + 'static>(
+    res: ServiceResponse<B>,
+) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    let skip = {
+        let extensions = res.response().extensions();
+        extensions.get::<ErrorCode>().is_some() || extensions.get::<SkipNormalize>().is_some()
+    };
+    if skip {
+        return Ok(ErrorHandlerResponse::Response(res.map_into_left_body()));
+    }
+
+    Ok(ErrorHandlerResponse::Future(Box::pin(async move {
+        let (req, res) = res.into_parts();
+        let status = res.status();
+        let headers = res.headers().clone();
+        let (bytes, truncated) = read_prefix(res.into_body(), MAX_ORIGINAL_BODY).await;
+        let bytes = if truncated {
+            complete_utf8(&bytes)
+        } else {
+            &bytes
+        };
+        let mut original = String::from_utf8_lossy(bytes).trim().to_string();
+        if truncated && !original.is_empty() {
+            original.push('…');
+        }
+
+        let code = code_for_status(status);
+        let desc = if original.is_empty() {
+            code.reason(request_locale(&req))
+                .unwrap_or_default()
+                .to_string()
+        } else {
+            original
+        };
+        let mut response = Error::new(status)
+            .err(code.from_desc(desc))
+            .error_response();
+        for (name, value) in headers.iter() {
+            if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+                response.headers_mut().append(name.clone(), value.clone());
+            }
+        }
+        Ok(ServiceResponse::new(req, response).map_into_right_body())
+    })))
+}
+
+/// 读取响应体的前limit字节，返回内容和是否被截断；读取失败时返回已读到的部分
+async fn read_prefix<B: MessageBody>(body: B, limit: usize) -> (Vec<u8>, bool) {
+    let mut body = pin!(body);
+    let mut buf = Vec::new();
+    while let Some(Ok(chunk)) = poll_fn(|cx| body.as_mut().poll_next(cx)).await {
+        let remaining = limit - buf.len();
+        if chunk.len() > remaining {
+            buf.extend_from_slice(&chunk[..remaining]);
+            return (buf, true);
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    (buf, false)
+}
+
+/// 去掉截断处不完整的UTF-8字符
+fn complete_utf8(bytes: &[u8]) -> &[u8] {
+    match std::str::from_utf8(bytes) {
+        Err(e) if e.error_len().is_none() => &bytes[..e.valid_up_to()],
+        _ => bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handler::default_service;
+    use actix_web::dev::Service;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, web, App, HttpResponse};
+    use futures_util::future::{ready, Either};
+    use serde_json::Value;
+
+    #[actix_web::test]
+    async fn test_normalize_errors() {
+        let app = test::init_service(
+            App::new()
+                .wrap_fn(|req, srv| {
+                    if req.path() == "/secure" && !req.headers().contains_key("authorization") {
+                        let res = HttpResponse::Unauthorized()
+                            .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+                            .body("missing credentials");
+                        Either::Left(ready(Ok(req.into_response(res))))
+                    } else {
+                        Either::Right(srv.call(req))
+                    }
+                })
+                .wrap(normalize_errors())
+                .route("/secure", web::get().to(HttpResponse::Ok))
+                .default_service(default_service()),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/secure").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            res.headers().get(header::WWW_AUTHENTICATE).unwrap(),
+            "Bearer"
+        );
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["error"]["status"], 401);
+        assert_eq!(body["error"]["details"][0]["code"], 6002);
+        assert_eq!(body["error"]["details"][0]["desc"], "missing credentials");
+
+        let req = test::TestRequest::get().uri("/unknown").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["error"]["details"].as_array().unwrap().len(), 1);
+        assert_eq!(body["error"]["details"][0]["code"], 2011);
+        assert_eq!(body["error"]["details"][0]["desc"], "请求的路由不存在");
+    }
+
+    #[actix_web::test]
+    async fn test_large_original_body() {
+        let app = test::init_service(
+            App::new().wrap(normalize_errors()).route(
+                "/upstream",
+                web::get()
+                    .to(|| async { HttpResponse::BadGateway().body("上游错误".repeat(1000)) }),
+            ),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/upstream").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+        let body: Value = test::read_body_json(res).await;
+        let desc = body["error"]["details"][0]["desc"].as_str().unwrap();
+        assert!(desc.starts_with("上游错误上游错误"), "{}", desc);
+        assert!(desc.ends_with('…'), "{}", desc);
+        assert!(!desc.contains('\u{fffd}'));
+        assert!(desc.len() <= MAX_ORIGINAL_BODY + '…'.len_utf8());
+        assert!(desc.len() > MAX_ORIGINAL_BODY - 4);
+    }
+}
@@ -0,0 +1,180 @@
+use super::current_locale;
+use crate::define::UnexpectedErrorOccured;
+use crate::err::Error;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::StatusCode;
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use futures_util::FutureExt;
+use std::any::Any;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// 捕获处理函数中的panic，记录日志并返回500标准错误结构
+///
+/// 默认不在desc中返回panic信息，调试环境可以通过`redact(false)`关闭。
+/// panic时请求已经被内部服务消耗，因此以Err返回，由actix渲染为标准错误结构
+///
+/// # Example
+///
+/// ```ignore
+/// App::new().wrap(CatchPanic::new())
+/// ```
+#[derive(Debug, Clone)]
+pub struct CatchPanic {
+    redact: bool,
+}
+
+impl Default for CatchPanic {
+    fn default() -> Self {
+        CatchPanic { redact: true }
+    }
+}
+
+impl CatchPanic {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn redact(mut self, redact: bool) -> Self {
+        self.redact = redact;
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CatchPanic
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = CatchPanicMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CatchPanicMiddleware {
+            service,
+            redact: self.redact,
+        }))
+    }
+}
+
+pub struct CatchPanicMiddleware<S> {
+    service: S,
+    redact: bool,
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for CatchPanicMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let method = req.method().clone();
+        let path = req.path().to_string();
+        let redact = self.redact;
+        let fut = catch_unwind(AssertUnwindSafe(|| self.service.call(req)));
+
+        Box::pin(async move {
+            let result = match fut {
+                Ok(fut) => AssertUnwindSafe(fut).catch_unwind().await,
+                Err(payload) => Err(payload),
+            };
+            match result {
+                Ok(res) => res,
+                Err(payload) => {
+                    let message = panic_message(payload.as_ref());
+                    log::error!("请求处理发生panic: {} {} {}", method, path, message);
+                    let desc = if redact {
+                        current_locale()
+                            .pick("发生意外错误", "unexpected error occured")
+                            .to_string()
+                    } else {
+                        message.to_string()
+                    };
+                    Err(Error::new(StatusCode::INTERNAL_SERVER_ERROR)
+                        .err(UnexpectedErrorOccured.from_desc(desc))
+                        .into())
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body::to_bytes;
+    use actix_web::{test, web, App, HttpResponse};
+    use serde_json::Value;
+
+    async fn read_error(err: actix_web::Error) -> (StatusCode, Value) {
+        let res = err.error_response();
+        let status = res.status();
+        let body = to_bytes(res.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    async fn boom() -> HttpResponse {
+        panic!("database password is hunter2");
+    }
+
+    #[actix_web::test]
+    async fn test_catch_panic() {
+        let app = test::init_service(
+            App::new()
+                .wrap(CatchPanic::new())
+                .route("/boom", web::get().to(boom))
+                .route("/ok", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/boom").to_request();
+        let err = test::try_call_service(&app, req).await.unwrap_err();
+        let (status, body) = read_error(err).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["error"]["status"], 500);
+        assert_eq!(body["error"]["details"][0]["code"], 5001);
+        assert!(!body["error"]["details"][0]["desc"]
+            .as_str()
+            .unwrap()
+            .contains("hunter2"));
+
+        let req = test::TestRequest::get().uri("/ok").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_catch_panic_without_redaction() {
+        let app = test::init_service(
+            App::new()
+                .wrap(CatchPanic::new().redact(false))
+                .route("/boom", web::get().to(boom)),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/boom").to_request();
+        let err = test::try_call_service(&app, req).await.unwrap_err();
+        let (_, body) = read_error(err).await;
+        assert_eq!(
+            body["error"]["details"][0]["desc"],
+            "database password is hunter2"
+        );
+    }
+}
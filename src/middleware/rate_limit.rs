@@ -0,0 +1,360 @@
+use super::current_locale;
+use crate::define::RateLimited;
+use crate::err::Error;
+use crate::extract::client_ip;
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::ResponseError;
+use futures_util::future::{ready, Either, LocalBoxFuture, Ready};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+type KeyFn = Arc<dyn Fn(&ServiceRequest) -> Option<String> + Send + Sync>;
+
+const LIMIT_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+const REMAINING_HEADER: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// 令牌桶，按key记录剩余令牌数
+struct Buckets {
+    buckets: HashMap<String, Bucket>,
+    /// 每秒补充的令牌数
+    rate: f64,
+    burst: f64,
+    max_keys: usize,
+}
+
+enum Decision {
+    Allow { remaining: u64 },
+    Reject { retry_after: u64 },
+}
+
+impl Buckets {
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = now;
+    }
+
+    /// key数量达到上限时先清理已补满（长时间空闲）的桶，仍然超限时淘汰最久未访问的桶
+    ///
+    /// 每次至少腾出max_keys的十分之一，避免达到上限后每个新key都遍历全部桶
+    fn sweep(&mut self, now: Instant) {
+        let (rate, burst) = (self.rate, self.burst);
+        self.buckets.retain(|_, bucket| {
+            let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens + elapsed * rate < burst
+        });
+        let target = self.max_keys - (self.max_keys / 10).max(1);
+        if self.buckets.len() > target {
+            let excess = self.buckets.len() - target;
+            let mut oldest: Vec<(Instant, String)> = self
+                .buckets
+                .iter()
+                .map(|(key, bucket)| (bucket.updated, key.clone()))
+                .collect();
+            oldest.select_nth_unstable_by_key(excess - 1, |(updated, _)| *updated);
+            for (_, key) in &oldest[..excess] {
+                self.buckets.remove(key);
+            }
+        }
+    }
+
+    fn acquire(&mut self, key: String, now: Instant) -> Decision {
+        if !self.buckets.contains_key(&key) && self.buckets.len() >= self.max_keys {
+            self.sweep(now);
+        }
+        let burst = self.burst;
+        let mut bucket = self.buckets.remove(&key).unwrap_or(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        self.refill(&mut bucket, now);
+        let decision = if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Decision::Allow {
+                remaining: bucket.tokens.floor() as u64,
+            }
+        } else {
+            Decision::Reject {
+                retry_after: ((1.0 - bucket.tokens) / self.rate).ceil().max(1.0) as u64,
+            }
+        };
+        self.buckets.insert(key, bucket);
+        decision
+    }
+}
+
+/// 按key限流的中间件（令牌桶），超出限制时返回429标准错误结构
+///
+/// 默认按客户端IP（参考app_data中的TrustedProxies）限流，可以通过key自定义（如按API令牌），key返回None的请求不限流。
+/// 响应中带有X-RateLimit-Limit和X-RateLimit-Remaining头，429响应带有Retry-After头。
+/// RateLimit在HttpServer::new外构造后clone给每个worker，所有worker共享同一组计数
+///
+/// # Example
+///
+/// ```ignore
+/// // 每分钟100次，允许突发20次
+/// let limiter = RateLimit::new(100, Duration::from_secs(60)).burst(20);
+/// HttpServer::new(move || App::new().wrap(limiter.clone()))
+/// ```
+#[derive(Clone)]
+pub struct RateLimit {
+    key: KeyFn,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimit {
+    /// window时间内最多requests次请求，突发上限默认等于requests
+    ///
+    /// # Panics
+    ///
+    /// requests为0或window为0时panic
+    pub fn new(requests: u32, window: Duration) -> Self {
+        assert!(requests > 0, "RateLimit requests must be greater than 0");
+        assert!(!window.is_zero(), "RateLimit window must be greater than 0");
+        RateLimit {
+            key: Arc::new(|req: &ServiceRequest| client_ip(req.request()).map(|ip| ip.to_string())),
+            buckets: Arc::new(Mutex::new(Buckets {
+                buckets: HashMap::new(),
+                rate: requests as f64 / window.as_secs_f64(),
+                burst: requests as f64,
+                max_keys: 10_000,
+            })),
+        }
+    }
+
+    /// 突发上限
+    ///
+    /// # Panics
+    ///
+    /// burst为0时panic
+    pub fn burst(self, burst: u32) -> Self {
+        assert!(burst > 0, "RateLimit burst must be greater than 0");
+        self.buckets.lock().unwrap().burst = burst as f64;
+        self
+    }
+
+    /// 内存中最多保存的key数量，默认10000
+    pub fn max_keys(self, max_keys: usize) -> Self {
+        self.buckets.lock().unwrap().max_keys = max_keys.max(1);
+        self
+    }
+
+    pub fn key<F>(mut self, key: F) -> Self
+    where
+        F: Fn(&ServiceRequest) -> Option<String> + Send + Sync + 'static,
+    {
+        self.key = Arc::new(key);
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            limiter: self.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    limiter: RateLimit,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = Either<
+        LocalBoxFuture<'static, Result<Self::Response, Self::Error>>,
+        Ready<Result<Self::Response, Self::Error>>,
+    >;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let key = match (self.limiter.key)(&req) {
+            Some(key) => key,
+            None => {
+                let fut = self.service.call(req);
+                return Either::Left(Box::pin(async move { Ok(fut.await?.map_into_left_body()) }));
+            }
+        };
+
+        let (decision, limit) = {
+            let mut buckets = self.limiter.buckets.lock().unwrap();
+            (buckets.acquire(key, Instant::now()), buckets.burst as u64)
+        };
+        match decision {
+            Decision::Allow { remaining } => {
+                let fut = self.service.call(req);
+                Either::Left(Box::pin(async move {
+                    let mut res = fut.await?;
+                    let headers = res.headers_mut();
+                    headers.insert(LIMIT_HEADER, HeaderValue::from(limit));
+                    headers.insert(REMAINING_HEADER, HeaderValue::from(remaining));
+                    Ok(res.map_into_left_body())
+                }))
+            }
+            Decision::Reject { retry_after } => {
+                let locale = current_locale();
+                let desc = format!(
+                    "{}{}{}",
+                    locale.pick("请求过于频繁，请在", "too many requests, retry after "),
+                    retry_after,
+                    locale.pick("秒后重试", " seconds")
+                );
+                let mut response = Error::new(StatusCode::TOO_MANY_REQUESTS)
+                    .err(
+                        RateLimited
+                            .from_desc(desc)
+                            .with_extra("retry_after", retry_after),
+                    )
+                    .retry_after(retry_after)
+                    .error_response();
+                let headers = response.headers_mut();
+                headers.insert(LIMIT_HEADER, HeaderValue::from(limit));
+                headers.insert(REMAINING_HEADER, HeaderValue::from(0u64));
+                Either::Right(ready(Ok(req.into_response(response).map_into_right_body())))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::RETRY_AFTER;
+    use actix_web::{test, web, App, HttpResponse};
+    use serde_json::Value;
+
+    async fn index() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_web::test]
+    async fn test_rate_limit() {
+        let limiter = RateLimit::new(2, Duration::from_millis(200));
+        let app =
+            test::init_service(App::new().wrap(limiter).route("/", web::get().to(index))).await;
+        let request = || {
+            test::TestRequest::get()
+                .uri("/")
+                .peer_addr("10.0.0.1:4000".parse().unwrap())
+                .to_request()
+        };
+
+        let res = test::call_service(&app, request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(&REMAINING_HEADER).unwrap(), "1");
+        test::call_service(&app, request()).await;
+
+        let res = test::call_service(&app, request()).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers().get(RETRY_AFTER).unwrap(), "1");
+        assert_eq!(res.headers().get(&LIMIT_HEADER).unwrap(), "2");
+        assert_eq!(res.headers().get(&REMAINING_HEADER).unwrap(), "0");
+        let body: Value = test::read_body_json(res).await;
+        assert_eq!(body["error"]["status"], 429);
+        assert_eq!(body["error"]["details"][0]["code"], 2014);
+        assert_eq!(body["error"]["retryable"], true);
+        assert_eq!(body["error"]["retry_after_secs"], 1);
+
+        let other = test::TestRequest::get()
+            .uri("/")
+            .peer_addr("10.0.0.2:4000".parse().unwrap())
+            .to_request();
+        assert_eq!(
+            test::call_service(&app, other).await.status(),
+            StatusCode::OK
+        );
+
+        actix_web::rt::time::sleep(Duration::from_millis(250)).await;
+        let res = test::call_service(&app, request()).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_custom_key_and_bounded_memory() {
+        let limiter = RateLimit::new(1, Duration::from_secs(60))
+            .max_keys(2)
+            .key(|req| {
+                req.headers()
+                    .get("x-api-key")
+                    .and_then(|value| value.to_str().ok())
+                    .map(String::from)
+            });
+        let buckets = Arc::clone(&limiter.buckets);
+        let app =
+            test::init_service(App::new().wrap(limiter).route("/", web::get().to(index))).await;
+        for key in ["a", "b", "c"] {
+            let req = test::TestRequest::get()
+                .uri("/")
+                .insert_header(("x-api-key", key))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        }
+        assert_eq!(buckets.lock().unwrap().buckets.len(), 2);
+
+        let req = test::TestRequest::get().uri("/").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    #[should_panic(expected = "requests must be greater than 0")]
+    async fn test_zero_requests() {
+        RateLimit::new(0, Duration::from_secs(1));
+    }
+
+    #[actix_web::test]
+    #[should_panic(expected = "window must be greater than 0")]
+    async fn test_zero_window() {
+        RateLimit::new(1, Duration::ZERO);
+    }
+
+    #[actix_web::test]
+    #[should_panic(expected = "burst must be greater than 0")]
+    async fn test_zero_burst() {
+        RateLimit::new(1, Duration::from_secs(1)).burst(0);
+    }
+
+    #[actix_web::test]
+    async fn test_sweep_in_batches() {
+        let limiter = RateLimit::new(1, Duration::from_secs(60)).max_keys(100);
+        let mut buckets = limiter.buckets.lock().unwrap();
+        let start = Instant::now();
+        for i in 0..100u64 {
+            buckets.acquire(i.to_string(), start + Duration::from_millis(i));
+        }
+        // 达到上限后一次腾出10个位置，最早的key被淘汰
+        buckets.acquire("new".to_string(), start + Duration::from_millis(100));
+        assert_eq!(buckets.buckets.len(), 91);
+        assert!(!buckets.buckets.contains_key("0"));
+        assert!(!buckets.buckets.contains_key("9"));
+        assert!(buckets.buckets.contains_key("10"));
+    }
+}
@@ -0,0 +1,207 @@
+use super::context::REQUEST_ID;
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::convert::Infallible;
+use std::fmt::{self, Display};
+use std::rc::Rc;
+
+/// 请求的唯一标识，保存在request extensions中，也可以直接作为提取器使用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromRequest for RequestId {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let id = req
+            .extensions()
+            .get::<RequestId>()
+            .cloned()
+            .unwrap_or_else(|| RequestId(String::new()));
+        ready(Ok(id))
+    }
+}
+
+/// 为每个请求分配RequestId并在响应头中返回
+///
+/// 请求头中已有合法的X-Request-Id（1到128个字母、数字或`-_.:`）时沿用，否则生成UUIDv4。
+/// 处理过程中产生的标准错误结构会在`trace_id`中带上该值
+///
+/// # Example
+///
+/// ```ignore
+/// App::new().wrap(AssignRequestId::new())
+/// ```
+#[derive(Debug, Clone)]
+pub struct AssignRequestId {
+    header: HeaderName,
+}
+
+impl Default for AssignRequestId {
+    fn default() -> Self {
+        AssignRequestId {
+            header: HeaderName::from_static("x-request-id"),
+        }
+    }
+}
+
+impl AssignRequestId {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn header(mut self, header: HeaderName) -> Self {
+        self.header = header;
+        self
+    }
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 128
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+}
+
+impl<S, B> Transform<S, ServiceRequest> for AssignRequestId
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = AssignRequestIdMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AssignRequestIdMiddleware {
+            service: Rc::new(service),
+            header: self.header.clone(),
+        }))
+    }
+}
+
+pub struct AssignRequestIdMiddleware<S> {
+    service: Rc<S>,
+    header: HeaderName,
+}
+
+impl<S, B> Service<ServiceRequest> for AssignRequestIdMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let id = req
+            .headers()
+            .get(&self.header)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| is_valid_request_id(value))
+            .map(ToString::to_string)
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        req.extensions_mut().insert(RequestId(id.clone()));
+
+        let service = Rc::clone(&self.service);
+        let header = self.header.clone();
+        Box::pin(async move {
+            let mut res = REQUEST_ID.scope(id.clone(), service.call(req)).await?;
+            if let Ok(value) = HeaderValue::from_str(&id) {
+                res.headers_mut().insert(header, value);
+            }
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::define::DeviceNotFound;
+    use crate::err::HttpResult;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, web, App};
+    use serde_json::Value;
+
+    async fn echo(id: RequestId) -> String {
+        id.0
+    }
+
+    async fn missing() -> HttpResult<String> {
+        Err(DeviceNotFound.from_desc("设备不存在"))?
+    }
+
+    async fn call(uri: &str, header: Option<&str>) -> (StatusCode, String, actix_web::web::Bytes) {
+        let app = test::init_service(
+            App::new()
+                .wrap(AssignRequestId::new())
+                .route("/echo", web::get().to(echo))
+                .route("/missing", web::get().to(missing)),
+        )
+        .await;
+        let mut req = test::TestRequest::get().uri(uri);
+        if let Some(header) = header {
+            req = req.insert_header(("x-request-id", header));
+        }
+        let res = test::call_service(&app, req.to_request()).await;
+        let status = res.status();
+        let id = res
+            .headers()
+            .get("x-request-id")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        (status, id, test::read_body(res).await)
+    }
+
+    #[actix_web::test]
+    async fn test_generated_id() {
+        let (status, id, body) = call("/missing", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(uuid::Uuid::parse_str(&id).is_ok());
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["trace_id"], id.as_str());
+    }
+
+    #[actix_web::test]
+    async fn test_supplied_id() {
+        let (_, id, body) = call("/echo", Some("edge-42.abc")).await;
+        assert_eq!(id, "edge-42.abc");
+        assert_eq!(body, "edge-42.abc");
+    }
+
+    #[actix_web::test]
+    async fn test_malformed_id_replaced() {
+        let (_, id, body) = call("/echo", Some("bad id<script>")).await;
+        assert_ne!(id, "bad id<script>");
+        assert!(uuid::Uuid::parse_str(&id).is_ok());
+        assert_eq!(body, id.as_str());
+
+        let too_long = "a".repeat(129);
+        let (_, id, _) = call("/echo", Some(&too_long)).await;
+        assert_ne!(id, too_long);
+    }
+}
@@ -0,0 +1,147 @@
+use super::current_locale;
+use crate::define::TimedOut;
+use crate::err::Error;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::StatusCode;
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// 请求处理超时中间件，超时后取消处理并返回504标准错误结构（TimedOut）
+///
+/// 只限制处理函数生成响应的时间，已经开始发送的流式响应体不受影响。
+/// 可以按路由模板单独设置超时时间
+///
+/// # Example
+///
+/// ```ignore
+/// App::new().wrap(
+///     Timeout::new(Duration::from_secs(30))
+///         .route("/reports/{id}/export", Duration::from_secs(300)),
+/// )
+/// ```
+#[derive(Debug, Clone)]
+pub struct Timeout {
+    timeout: Duration,
+    routes: HashMap<String, Duration>,
+}
+
+impl Timeout {
+    pub fn new(timeout: Duration) -> Self {
+        Timeout {
+            timeout,
+            routes: HashMap::new(),
+        }
+    }
+
+    /// 为指定的路由模板（与注册路由时的写法一致）设置超时时间
+    pub fn route<S: Into<String>>(mut self, pattern: S, timeout: Duration) -> Self {
+        self.routes.insert(pattern.into(), timeout);
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Timeout
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = TimeoutMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TimeoutMiddleware {
+            service: Rc::new(service),
+            config: Rc::new(self.clone()),
+        }))
+    }
+}
+
+pub struct TimeoutMiddleware<S> {
+    service: Rc<S>,
+    config: Rc<Timeout>,
+}
+
+impl<S, B> Service<ServiceRequest> for TimeoutMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let timeout = req
+            .match_pattern()
+            .and_then(|pattern| self.config.routes.get(&pattern).copied())
+            .unwrap_or(self.config.timeout);
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            match actix_web::rt::time::timeout(timeout, fut).await {
+                Ok(res) => res,
+                Err(_) => {
+                    let desc = format!(
+                        "{} {:?}",
+                        current_locale().pick("请求处理超时", "request timed out after"),
+                        timeout
+                    );
+                    Err(Error::new(StatusCode::GATEWAY_TIMEOUT)
+                        .err(TimedOut.from_desc(desc))
+                        .into())
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, web, App, HttpResponse};
+    use serde_json::Value;
+
+    async fn sleep(ms: web::Path<u64>) -> HttpResponse {
+        actix_web::rt::time::sleep(Duration::from_millis(*ms)).await;
+        HttpResponse::Ok().body("done")
+    }
+
+    #[actix_web::test]
+    async fn test_timeout() {
+        let app = test::init_service(
+            App::new()
+                .wrap(
+                    Timeout::new(Duration::from_millis(50))
+                        .route("/slow/{ms}", Duration::from_millis(300)),
+                )
+                .route("/sleep/{ms}", web::get().to(sleep))
+                .route("/slow/{ms}", web::get().to(sleep)),
+        )
+        .await;
+
+        let req = test::TestRequest::get().uri("/sleep/200").to_request();
+        let err = test::try_call_service(&app, req).await.unwrap_err();
+        let res = err.error_response();
+        assert_eq!(res.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = actix_web::body::to_bytes(res.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["details"][0]["code"], 1014);
+        assert_eq!(body["error"]["details"][0]["desc"], "请求处理超时 50ms");
+
+        let req = test::TestRequest::get().uri("/sleep/0").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = test::TestRequest::get().uri("/slow/200").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(test::read_body(res).await, "done");
+    }
+}
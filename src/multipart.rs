@@ -0,0 +1,219 @@
+use super::define::{ExtraDescError, InvalidInput, Locale, PayloadTooLarge, Result};
+use super::middleware::current_locale;
+use actix_multipart::Multipart;
+use actix_web::mime::Mime;
+use actix_web::web::{Bytes, BytesMut};
+use futures_util::StreamExt;
+
+/// 上传文件的大小和类型限制
+#[derive(Debug, Clone)]
+pub struct MultipartLimits {
+    /// 单个字段的最大字节数
+    pub max_field_size: usize,
+    /// 所有字段合计的最大字节数
+    pub max_total_size: usize,
+    /// 允许的Content-Type，为空时不限制；只比较type/subtype，忽略参数
+    pub allowed_content_types: Vec<Mime>,
+}
+
+impl Default for MultipartLimits {
+    fn default() -> Self {
+        MultipartLimits {
+            max_field_size: 10 * 1024 * 1024,
+            max_total_size: 50 * 1024 * 1024,
+            allowed_content_types: Vec::new(),
+        }
+    }
+}
+
+impl MultipartLimits {
+    pub fn max_field_size(mut self, size: usize) -> Self {
+        self.max_field_size = size;
+        self
+    }
+
+    pub fn max_total_size(mut self, size: usize) -> Self {
+        self.max_total_size = size;
+        self
+    }
+
+    pub fn allow_content_type(mut self, mime: Mime) -> Self {
+        self.allowed_content_types.push(mime);
+        self
+    }
+
+    fn allows(&self, mime: Option<&Mime>) -> bool {
+        if self.allowed_content_types.is_empty() {
+            return true;
+        }
+        match mime {
+            Some(mime) => self
+                .allowed_content_types
+                .iter()
+                .any(|allowed| allowed.essence_str() == mime.essence_str()),
+            None => false,
+        }
+    }
+}
+
+/// 上传的字段
+#[derive(Debug, Clone)]
+pub struct UploadedField {
+    pub name: String,
+    pub filename: Option<String>,
+    pub content_type: Option<Mime>,
+    pub data: Bytes,
+}
+
+fn too_large(name: &str, limit: usize) -> ExtraDescError {
+    let locale = current_locale();
+    let desc = match locale {
+        Locale::Zh => format!("上传字段{}过大，限制{}字节", name, limit),
+        Locale::En => format!("field {} is too large, limit {} bytes", name, limit),
+    };
+    PayloadTooLarge.from_desc(desc)
+}
+
+/// 读取multipart请求的所有字段，超出大小限制返回PayloadTooLarge，类型不允许返回InvalidInput
+///
+/// # Example
+///
+/// ```ignore
+/// async fn upload(payload: Multipart) -> HttpResult<HttpResponse> {
+///     let limits = MultipartLimits::default().allow_content_type(mime::APPLICATION_OCTET_STREAM);
+///     let fields = collect_multipart(payload, limits).await?;
+///     ...
+/// }
+/// ```
+pub async fn collect_multipart(
+    mut payload: Multipart,
+    limits: MultipartLimits,
+) -> Result<Vec<UploadedField>> {
+    let locale = current_locale();
+    let mut fields = Vec::new();
+    let mut total = 0;
+
+    while let Some(field) = payload.next().await {
+        let mut field = field.map_err(|e| InvalidInput.from_desc(e.to_string()))?;
+        let name = field.name().unwrap_or_default().to_string();
+        let filename = field
+            .content_disposition()
+            .and_then(|cd| cd.get_filename())
+            .map(ToString::to_string);
+        let content_type = field.content_type().cloned();
+
+        if !limits.allows(content_type.as_ref()) {
+            let received = content_type
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_else(|| locale.pick("无", "none").to_string());
+            let desc = format!(
+                "{} {}: {}",
+                locale.pick("上传字段类型不允许", "content type not allowed for field"),
+                name,
+                received
+            );
+            return Err(InvalidInput.from_desc(desc).with_field(name));
+        }
+
+        let mut data = BytesMut::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(|e| InvalidInput.from_desc(e.to_string()))?;
+            if data.len() + chunk.len() > limits.max_field_size {
+                return Err(too_large(&name, limits.max_field_size).with_field(name));
+            }
+            total += chunk.len();
+            if total > limits.max_total_size {
+                return Err(too_large(&name, limits.max_total_size).with_field(name));
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        fields.push(UploadedField {
+            name,
+            filename,
+            content_type,
+            data: data.freeze(),
+        });
+    }
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::err::HttpResult;
+    use actix_multipart::test::create_form_data_payload_and_headers;
+    use actix_web::http::StatusCode;
+    use actix_web::{mime, test, web, App};
+    use serde_json::Value;
+
+    async fn upload(payload: Multipart, limits: web::Data<MultipartLimits>) -> HttpResult<String> {
+        let fields = collect_multipart(payload, limits.get_ref().clone()).await?;
+        Ok(format!(
+            "{}:{}:{}",
+            fields[0].name,
+            fields[0].filename.as_deref().unwrap_or_default(),
+            fields[0].data.len()
+        ))
+    }
+
+    async fn send(limits: MultipartLimits, content_type: Mime, size: usize) -> (StatusCode, Bytes) {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(limits))
+                .route("/upload", web::post().to(upload)),
+        )
+        .await;
+        let (body, headers) = create_form_data_payload_and_headers(
+            "firmware",
+            Some("fw.bin".to_string()),
+            Some(content_type),
+            Bytes::from(vec![0u8; size]),
+        );
+        let mut req = test::TestRequest::post().uri("/upload");
+        for (name, value) in headers.iter() {
+            req = req.insert_header((name.clone(), value.clone()));
+        }
+        let res = test::call_service(&app, req.set_payload(body).to_request()).await;
+        (res.status(), test::read_body(res).await)
+    }
+
+    #[actix_web::test]
+    async fn test_collect() {
+        let limits = MultipartLimits::default().allow_content_type(mime::APPLICATION_OCTET_STREAM);
+        let (status, body) = send(limits, mime::APPLICATION_OCTET_STREAM, 16).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "firmware:fw.bin:16");
+    }
+
+    #[actix_web::test]
+    async fn test_field_too_large() {
+        let limits = MultipartLimits::default().max_field_size(8);
+        let (status, body) = send(limits, mime::APPLICATION_OCTET_STREAM, 16).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["details"][0]["code"], 2009);
+        assert_eq!(body["error"]["details"][0]["field"], "firmware");
+    }
+
+    #[actix_web::test]
+    async fn test_total_too_large() {
+        let limits = MultipartLimits::default().max_total_size(8);
+        let (status, _) = send(limits, mime::APPLICATION_OCTET_STREAM, 16).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_web::test]
+    async fn test_disallowed_type() {
+        let limits = MultipartLimits::default().allow_content_type(mime::APPLICATION_OCTET_STREAM);
+        let (status, body) = send(limits, mime::TEXT_PLAIN, 4).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["details"][0]["code"], 1012);
+        assert!(body["error"]["details"][0]["desc"]
+            .as_str()
+            .unwrap()
+            .contains("text/plain"));
+    }
+}
@@ -0,0 +1,124 @@
+use super::err::{ErrorDetail, ErrorOutTpl, ErrorWrapper};
+use std::collections::BTreeMap;
+use utoipa::openapi::{
+    ComponentsBuilder, ContentBuilder, OpenApi, Ref, RefOr, Response, ResponseBuilder,
+};
+use utoipa::{IntoResponses, ToSchema};
+
+/// 标准错误响应（400、404、500），响应体引用ErrorOutTpl
+///
+/// # Example
+///
+/// ```ignore
+/// #[utoipa::path(get, path = "/devices/{id}", responses((status = 200, body = Device), StandardErrors))]
+/// async fn detail(id: web::Path<u64>) -> HttpResult<Json<Device>> { ... }
+///
+/// #[derive(OpenApi)]
+/// #[openapi(paths(detail), modifiers(&ErrorSchemas))]
+/// struct ApiDoc;
+/// ```
+pub struct StandardErrors;
+
+impl IntoResponses for StandardErrors {
+    fn responses() -> BTreeMap<String, RefOr<Response>> {
+        [
+            ("400", "请求参数错误"),
+            ("404", "资源不存在"),
+            ("500", "服务内部错误"),
+        ]
+        .into_iter()
+        .map(|(status, description)| {
+            let content = ContentBuilder::new()
+                .schema(Some(Ref::from_schema_name(ErrorOutTpl::name())))
+                .build();
+            let response = ResponseBuilder::new()
+                .description(description)
+                .content("application/json", content)
+                .build();
+            (status.to_string(), response.into())
+        })
+        .collect()
+    }
+}
+
+/// 把ErrorOutTpl、ErrorWrapper、ErrorDetail注册到OpenApi的components中
+pub struct ErrorSchemas;
+
+impl utoipa::Modify for ErrorSchemas {
+    fn modify(&self, openapi: &mut OpenApi) {
+        let components = openapi
+            .components
+            .take()
+            .unwrap_or_else(|| ComponentsBuilder::new().build());
+        let components = ComponentsBuilder::from(components)
+            .schema_from::<ErrorOutTpl>()
+            .schema_from::<ErrorWrapper>()
+            .schema_from::<ErrorDetail>()
+            .build();
+        openapi.components = Some(components);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::QueryOutput;
+    use serde_json::Value;
+    use utoipa::OpenApi as _;
+
+    #[derive(Serialize, utoipa::ToSchema)]
+    struct Device {
+        id: u64,
+    }
+
+    #[utoipa::path(
+        get,
+        path = "/devices",
+        responses((status = 200, body = QueryOutput<Device>), StandardErrors)
+    )]
+    #[allow(dead_code)]
+    async fn list() {}
+
+    #[derive(utoipa::OpenApi)]
+    #[openapi(paths(list), components(schemas(Device)), modifiers(&ErrorSchemas))]
+    struct ApiDoc;
+
+    fn required(doc: &Value, schema: &str) -> Vec<String> {
+        let mut fields: Vec<String> = doc["components"]["schemas"][schema]["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v.as_str().unwrap().to_string())
+            .collect();
+        fields.sort();
+        fields
+    }
+
+    #[test]
+    fn test_openapi_schemas() {
+        let doc: Value = serde_json::from_str(&ApiDoc::openapi().to_json().unwrap()).unwrap();
+        assert_eq!(required(&doc, "ErrorOutTpl"), vec!["error"]);
+        assert_eq!(required(&doc, "ErrorWrapper"), vec!["details", "status"]);
+        assert_eq!(
+            required(&doc, "ErrorDetail"),
+            vec!["code", "desc", "err_type"]
+        );
+
+        let responses = &doc["paths"]["/devices"]["get"]["responses"];
+        for status in ["400", "404", "500"] {
+            assert_eq!(
+                responses[status]["content"]["application/json"]["schema"]["$ref"],
+                "#/components/schemas/ErrorOutTpl"
+            );
+        }
+        let ok = &responses["200"]["content"]["application/json"]["schema"];
+        assert_eq!(ok["$ref"], "#/components/schemas/QueryOutput_Device");
+        assert_eq!(
+            required(&doc, "QueryOutput_Device"),
+            vec!["items", "limit", "total"]
+        );
+        let items = &doc["components"]["schemas"]["QueryOutput_Device"]["properties"]["items"];
+        assert_eq!(items["type"], "array");
+        assert_eq!(items["items"]["required"], serde_json::json!(["id"]));
+    }
+}
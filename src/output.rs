@@ -0,0 +1,140 @@
+use super::define::UnexpectedErrorOccured;
+use super::err::Error;
+use super::middleware::current_locale;
+use crate::compat::impl_json_responder;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use serde::Serialize;
+
+/// 标准成功响应结构：`{"data": ...}`
+///
+/// # Example
+///
+/// ```ignore
+/// async fn detail(id: web::Path<u64>) -> HttpResult<DataOutTpl<Device>> {
+///     Ok(DataOutTpl::new(find_device(*id)?))
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct DataOutTpl<T> {
+    pub data: T,
+}
+
+impl<T> DataOutTpl<T> {
+    pub fn new(data: T) -> Self {
+        DataOutTpl { data }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+impl_json_responder!(DataOutTpl);
+
+/// 序列化为200的json响应，序列化失败时返回500标准错误结构
+pub(crate) fn json_response<T: Serialize>(value: &T) -> HttpResponse {
+    match serde_json::to_vec(value) {
+        Ok(body) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(body),
+        Err(e) => {
+            log::error!("响应序列化失败: {}", e);
+            let desc = current_locale().pick("响应序列化失败", "failed to serialize response");
+            Error::new(StatusCode::INTERNAL_SERVER_ERROR)
+                .err(UnexpectedErrorOccured.from_desc(desc))
+                .error_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compat::{async_test, call_app};
+    use crate::err::HttpResult;
+    use crate::query::QueryOutput;
+    use actix_web::{test, web, App};
+    use serde::ser::Error as _;
+    use serde_json::{json, Value};
+
+    #[derive(Serialize)]
+    struct Device {
+        id: u32,
+    }
+
+    struct Broken;
+
+    impl Serialize for Broken {
+        fn serialize<S: serde::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+            Err(S::Error::custom("broken"))
+        }
+    }
+
+    async fn list() -> HttpResult<QueryOutput<Device>> {
+        Ok(QueryOutput::new(
+            vec![Device { id: 1 }, Device { id: 2 }],
+            12,
+            2,
+        ))
+    }
+
+    async fn detail(id: web::Path<u32>) -> HttpResult<DataOutTpl<Device>> {
+        match id.into_inner() {
+            0 => Err(Error::new(StatusCode::NOT_FOUND).not_find("device not found")),
+            id => Ok(DataOutTpl::new(Device { id })),
+        }
+    }
+
+    async fn broken() -> HttpResult<DataOutTpl<Broken>> {
+        Ok(DataOutTpl::new(Broken))
+    }
+
+    async fn call(uri: &str) -> (StatusCode, Option<String>, Value) {
+        let res = call_app!(
+            App::new()
+                .route("/devices", web::get().to(list))
+                .route("/devices/{id}", web::get().to(detail))
+                .route("/broken", web::get().to(broken)),
+            test::TestRequest::get().uri(uri).to_request()
+        );
+        let status = res.status();
+        let content_type = res
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        (status, content_type, test::read_body_json(res).await)
+    }
+
+    #[async_test]
+    async fn test_query_output_responder() {
+        let (status, content_type, body) = call("/devices").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type.as_deref(), Some("application/json"));
+        assert_eq!(
+            body,
+            json!({"items": [{"id": 1}, {"id": 2}], "limit": 2, "total": 12, "total_pages": 6})
+        );
+    }
+
+    #[async_test]
+    async fn test_data_out_tpl_responder() {
+        let (status, content_type, body) = call("/devices/3").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(content_type.as_deref(), Some("application/json"));
+        assert_eq!(body, json!({"data": {"id": 3}}));
+
+        let (status, _, body) = call("/devices/0").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["details"][0]["code"], 3003);
+    }
+
+    #[async_test]
+    async fn test_serialize_failure() {
+        let (status, _, body) = call("/broken").await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["error"]["details"][0]["code"], 5001);
+    }
+}
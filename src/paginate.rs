@@ -0,0 +1,462 @@
+use crate::define::Result;
+use crate::query::{OutOfRange, QueryOutput};
+use diesel::backend::Backend;
+use diesel::connection::Connection;
+use diesel::query_builder::{AstPass, Query, QueryFragment, QueryId};
+use diesel::query_dsl::methods::LoadQuery;
+use diesel::query_dsl::RunQueryDsl;
+use diesel::serialize::ToSql;
+use diesel::sql_types::BigInt;
+use diesel::QueryResult;
+
+/// 默认的最大limit
+pub const DEFAULT_MAX_LIMIT: i64 = 100;
+
+/// 为diesel查询增加分页，查询数据的同时通过`COUNT(*) OVER ()`取得总数
+///
+/// 默认需要数据库支持窗口函数（PostgreSQL、MySQL 8、SQLite 3.25及以上），
+/// 其他数据库通过`count_mode(CountMode::Separate)`改为分别查询数据和总数
+///
+/// # Example
+///
+/// ```ignore
+/// let (limit, offset) = pagination.sql_limit_offset();
+/// let output: QueryOutput<Device> = devices::table
+///     .filter(devices::status.eq("online"))
+///     .order(devices::id)
+///     .paginate(offset, limit)
+///     .load_and_count(&conn)?;
+/// ```
+pub trait Paginate: Sized {
+    fn paginate(self, offset: i64, limit: i64) -> Paginated<Self>;
+}
+
+impl<T: Query> Paginate for T {
+    fn paginate(self, offset: i64, limit: i64) -> Paginated<Self> {
+        Paginated {
+            query: self,
+            offset: offset.max(0),
+            limit: limit.clamp(1, DEFAULT_MAX_LIMIT),
+            requested_limit: limit,
+            out_of_range: OutOfRange::default(),
+            count_mode: CountMode::default(),
+        }
+    }
+}
+
+/// 取得总数的方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CountMode {
+    /// 在分页查询中通过`COUNT(*) OVER ()`取得总数，只执行一条查询
+    #[default]
+    Window,
+    /// 分别执行分页查询和COUNT查询，用于不支持窗口函数的数据库（MySQL 5.7、SQLite 3.25以下）
+    Separate,
+}
+
+/// 分页后的查询，由Paginate::paginate创建
+#[derive(Debug, Clone, Copy)]
+pub struct Paginated<T> {
+    query: T,
+    offset: i64,
+    limit: i64,
+    requested_limit: i64,
+    out_of_range: OutOfRange,
+    count_mode: CountMode,
+}
+
+/// i64转usize，负数按0处理
+fn to_usize(value: i64) -> usize {
+    usize::try_from(value.max(0)).unwrap_or(usize::MAX)
+}
+
+impl<T> Paginated<T> {
+    /// 修改最大limit，默认DEFAULT_MAX_LIMIT
+    ///
+    /// limit最小为1，LIMIT 0查不到行也就取不到总数，且QueryOutput中limit为0表示不分页
+    pub fn max_limit(mut self, max_limit: i64) -> Self {
+        self.limit = self.requested_limit.clamp(1, max_limit.max(1));
+        self
+    }
+
+    /// offset超出范围时的处理方式，默认OutOfRange::EmptyWithFlag
+    pub fn out_of_range(mut self, policy: OutOfRange) -> Self {
+        self.out_of_range = policy;
+        self
+    }
+
+    /// 取得总数的方式，默认CountMode::Window
+    pub fn count_mode(mut self, mode: CountMode) -> Self {
+        self.count_mode = mode;
+        self
+    }
+
+    /// 查询本页数据和总数
+    ///
+    /// 本页为空且offset大于0时（超出最后一页），窗口函数拿不到总数，会再执行一次COUNT查询；
+    /// 使用OutOfRange::ClampToLastPage时再查询一次最后一页
+    pub fn load_and_count<U, Conn>(self, conn: &Conn) -> Result<QueryOutput<U>>
+    where
+        Conn: Connection,
+        for<'a> Paginated<&'a T>: LoadQuery<Conn, (U, i64)>,
+        for<'a> Limited<&'a T>: LoadQuery<Conn, U>,
+        for<'a> Counted<&'a T>: LoadQuery<Conn, i64>,
+    {
+        let (mut rows, total) = self.load_page::<U, Conn>(conn, self.offset)?;
+        let offset = self.out_of_range.resolve(
+            to_usize(self.offset),
+            to_usize(self.limit),
+            to_usize(total),
+        )?;
+        if offset != to_usize(self.offset) {
+            rows = self.load_page::<U, Conn>(conn, offset as i64)?.0;
+        }
+        rows.into_iter()
+            .collect::<QueryOutput<U>>()
+            .limit(to_usize(self.limit))
+            .offset(offset)
+            .total_i64(total)
+            .map(QueryOutput::flag_out_of_range)
+    }
+
+    fn load_page<U, Conn>(&self, conn: &Conn, offset: i64) -> Result<(Vec<U>, i64)>
+    where
+        Conn: Connection,
+        for<'a> Paginated<&'a T>: LoadQuery<Conn, (U, i64)>,
+        for<'a> Limited<&'a T>: LoadQuery<Conn, U>,
+        for<'a> Counted<&'a T>: LoadQuery<Conn, i64>,
+    {
+        if self.count_mode == CountMode::Separate {
+            let rows = Limited {
+                query: &self.query,
+                offset,
+                limit: self.limit,
+            }
+            .load::<U>(conn)?;
+            let total = Counted { query: &self.query }.get_result::<i64>(conn)?;
+            return Ok((rows, total));
+        }
+
+        let rows = Paginated {
+            query: &self.query,
+            offset,
+            limit: self.limit,
+            requested_limit: self.requested_limit,
+            out_of_range: self.out_of_range,
+            count_mode: self.count_mode,
+        }
+        .load::<(U, i64)>(conn)?;
+        let total = match rows.first() {
+            Some((_, total)) => *total,
+            None if offset > 0 => Counted { query: &self.query }.get_result::<i64>(conn)?,
+            None => 0,
+        };
+        Ok((rows.into_iter().map(|(item, _)| item).collect(), total))
+    }
+}
+
+impl<T> QueryId for Paginated<T> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<T: Query> Query for Paginated<T> {
+    type SqlType = (T::SqlType, BigInt);
+}
+
+impl<T, Conn> RunQueryDsl<Conn> for Paginated<T> {}
+
+impl<T, DB> QueryFragment<DB> for Paginated<T>
+where
+    DB: Backend,
+    T: QueryFragment<DB>,
+    i64: ToSql<BigInt, DB>,
+{
+    fn walk_ast(&self, mut out: AstPass<DB>) -> QueryResult<()> {
+        out.push_sql("SELECT *, COUNT(*) OVER () FROM (");
+        self.query.walk_ast(out.reborrow())?;
+        out.push_sql(") paginated LIMIT ");
+        out.push_bind_param::<BigInt, _>(&self.limit)?;
+        out.push_sql(" OFFSET ");
+        out.push_bind_param::<BigInt, _>(&self.offset)?;
+        Ok(())
+    }
+}
+
+/// 只查询本页数据，用于CountMode::Separate
+#[derive(Debug, Clone, Copy)]
+pub struct Limited<T> {
+    query: T,
+    offset: i64,
+    limit: i64,
+}
+
+impl<T> QueryId for Limited<T> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<T: Query> Query for Limited<T> {
+    type SqlType = T::SqlType;
+}
+
+impl<T, Conn> RunQueryDsl<Conn> for Limited<T> {}
+
+impl<T, DB> QueryFragment<DB> for Limited<T>
+where
+    DB: Backend,
+    T: QueryFragment<DB>,
+    i64: ToSql<BigInt, DB>,
+{
+    fn walk_ast(&self, mut out: AstPass<DB>) -> QueryResult<()> {
+        out.push_sql("SELECT * FROM (");
+        self.query.walk_ast(out.reborrow())?;
+        out.push_sql(") paginated LIMIT ");
+        out.push_bind_param::<BigInt, _>(&self.limit)?;
+        out.push_sql(" OFFSET ");
+        out.push_bind_param::<BigInt, _>(&self.offset)?;
+        Ok(())
+    }
+}
+
+/// 统计查询的总行数，用于分页超出范围时补查总数，以及CountMode::Separate
+#[derive(Debug, Clone, Copy)]
+pub struct Counted<T> {
+    query: T,
+}
+
+impl<T> QueryId for Counted<T> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<T> Query for Counted<T> {
+    type SqlType = BigInt;
+}
+
+impl<T, Conn> RunQueryDsl<Conn> for Counted<T> {}
+
+impl<T, DB> QueryFragment<DB> for Counted<T>
+where
+    DB: Backend,
+    T: QueryFragment<DB>,
+{
+    fn walk_ast(&self, mut out: AstPass<DB>) -> QueryResult<()> {
+        out.push_sql("SELECT COUNT(*) FROM (");
+        self.query.walk_ast(out.reborrow())?;
+        out.push_sql(") counted");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::prelude::*;
+    use diesel::sqlite::SqliteConnection;
+
+    // diesel 1.4的table!宏展开后会触发non_local_definitions
+    #[allow(non_local_definitions)]
+    mod schema {
+        table! {
+            devices (id) {
+                id -> Integer,
+                name -> Text,
+            }
+        }
+    }
+    use schema::devices;
+
+    fn connection(count: i32) -> SqliteConnection {
+        let conn = SqliteConnection::establish(":memory:").unwrap();
+        diesel::sql_query("CREATE TABLE devices (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+            .execute(&conn)
+            .unwrap();
+        for id in 1..=count {
+            diesel::insert_into(devices::table)
+                .values((
+                    devices::id.eq(id),
+                    devices::name.eq(format!("device-{}", id)),
+                ))
+                .execute(&conn)
+                .unwrap();
+        }
+        conn
+    }
+
+    fn query() -> devices::BoxedQuery<'static, diesel::sqlite::Sqlite> {
+        devices::table.order(devices::id).into_boxed()
+    }
+
+    #[test]
+    fn test_empty() {
+        let conn = connection(0);
+        let output = query()
+            .paginate(0, 10)
+            .load_and_count::<(i32, String), _>(&conn)
+            .unwrap();
+        assert!(output.items.is_empty());
+        assert_eq!(output.total, 0);
+        assert_eq!(output.total_pages, Some(0));
+    }
+
+    #[test]
+    fn test_partial_last_page() {
+        let conn = connection(7);
+        let output = query()
+            .filter(devices::id.gt(0))
+            .paginate(5, 5)
+            .load_and_count::<(i32, String), _>(&conn)
+            .unwrap();
+        assert_eq!(
+            output.items,
+            vec![(6, "device-6".to_string()), (7, "device-7".to_string())]
+        );
+        assert_eq!(output.total, 7);
+        assert_eq!(output.limit, 5);
+        assert_eq!(output.offset, Some(5));
+        assert_eq!(output.total_pages, Some(2));
+
+        let output = query()
+            .paginate(20, 5)
+            .load_and_count::<(i32, String), _>(&conn)
+            .unwrap();
+        assert!(output.items.is_empty());
+        assert_eq!(output.total, 7);
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let conn = connection(7);
+        let load = |offset, policy| {
+            query()
+                .paginate(offset, 5)
+                .out_of_range(policy)
+                .load_and_count::<(i32, String), _>(&conn)
+        };
+        let ids = |output: &QueryOutput<(i32, String)>| {
+            output.items.iter().map(|(id, _)| *id).collect::<Vec<_>>()
+        };
+
+        // offset 6是最后一行
+        for policy in [
+            OutOfRange::EmptyWithFlag,
+            OutOfRange::ClampToLastPage,
+            OutOfRange::Reject,
+        ] {
+            let output = load(6, policy).unwrap();
+            assert_eq!(ids(&output), vec![7]);
+            assert_eq!(output.meta, None);
+        }
+
+        let output = load(7, OutOfRange::EmptyWithFlag).unwrap();
+        assert!(output.items.is_empty());
+        assert_eq!(output.total, 7);
+        assert_eq!(output.meta.unwrap()["out_of_range"], true);
+
+        let output = load(7, OutOfRange::ClampToLastPage).unwrap();
+        assert_eq!(ids(&output), vec![6, 7]);
+        assert_eq!(output.offset, Some(5));
+        assert_eq!(output.total, 7);
+
+        let err = load(7, OutOfRange::Reject).unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("offset"));
+        assert_eq!(err.extra.unwrap()["max_offset"], 5);
+    }
+
+    #[test]
+    fn test_separate_count() {
+        // 分开查询时分页查询不使用窗口函数
+        let boxed = query();
+        let limited = Limited {
+            query: &boxed,
+            offset: 0,
+            limit: 5,
+        };
+        let sql = diesel::debug_query::<diesel::sqlite::Sqlite, _>(&limited).to_string();
+        assert!(sql.starts_with("SELECT * FROM ("));
+        assert!(!sql.contains("OVER"));
+
+        let conn = connection(7);
+        let load = |offset, policy| {
+            query()
+                .paginate(offset, 5)
+                .count_mode(CountMode::Separate)
+                .out_of_range(policy)
+                .load_and_count::<(i32, String), _>(&conn)
+                .unwrap()
+        };
+        let output = load(5, OutOfRange::EmptyWithFlag);
+        assert_eq!(
+            output.items,
+            vec![(6, "device-6".to_string()), (7, "device-7".to_string())]
+        );
+        assert_eq!(output.total, 7);
+        assert_eq!(output.total_pages, Some(2));
+
+        let output = load(7, OutOfRange::EmptyWithFlag);
+        assert!(output.items.is_empty());
+        assert_eq!(output.total, 7);
+        assert_eq!(output.meta.unwrap()["out_of_range"], true);
+
+        let output = load(7, OutOfRange::ClampToLastPage);
+        assert_eq!(output.items.len(), 2);
+        assert_eq!(output.offset, Some(5));
+
+        let conn = connection(0);
+        let output = query()
+            .paginate(0, 5)
+            .count_mode(CountMode::Separate)
+            .load_and_count::<(i32, String), _>(&conn)
+            .unwrap();
+        assert!(output.items.is_empty());
+        assert_eq!(output.total, 0);
+    }
+
+    #[test]
+    fn test_max_limit() {
+        let conn = connection(150);
+        let output = query()
+            .paginate(-3, 1000)
+            .load_and_count::<(i32, String), _>(&conn)
+            .unwrap();
+        assert_eq!(output.items.len(), 100);
+        assert_eq!(output.limit, 100);
+        assert_eq!(output.offset, Some(0));
+        assert_eq!(output.total, 150);
+
+        let output = query()
+            .paginate(0, 1000)
+            .max_limit(30)
+            .load_and_count::<(i32, String), _>(&conn)
+            .unwrap();
+        assert_eq!(output.items.len(), 30);
+        assert_eq!(output.limit, 30);
+    }
+
+    #[test]
+    fn test_zero_limit() {
+        let conn = connection(7);
+        for limit in [0, -5] {
+            let output = query()
+                .paginate(0, limit)
+                .load_and_count::<(i32, String), _>(&conn)
+                .unwrap();
+            assert_eq!(output.items, vec![(1, "device-1".to_string())]);
+            assert_eq!(output.limit, 1);
+            assert_eq!(output.total, 7);
+        }
+
+        let output = query()
+            .paginate(0, 10)
+            .max_limit(0)
+            .load_and_count::<(i32, String), _>(&conn)
+            .unwrap();
+        assert_eq!(output.items.len(), 1);
+        assert_eq!(output.limit, 1);
+        assert_eq!(output.total, 7);
+    }
+}
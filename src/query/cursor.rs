@@ -0,0 +1,181 @@
+use crate::define::{ExtraDescError, InvalidInput, Result};
+use crate::middleware::current_locale;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::Sha256;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// HMAC-SHA256签名长度
+const TAG_LEN: usize = 32;
+
+/// 签名游标：json载荷后接HMAC-SHA256签名，整体使用URL安全的base64编码
+///
+/// 客户端无法解析或修改游标，修改、截断或使用其它密钥签名的游标在解码时返回InvalidInput
+pub struct Cursor;
+
+impl Cursor {
+    pub fn encode<T: Serialize>(fields: &T, key: &[u8]) -> String {
+        encode_envelope(fields, key, None)
+    }
+
+    /// 解码并校验签名，游标带有过期时间且已过期时同样返回InvalidInput
+    pub fn decode<T: DeserializeOwned>(cursor: &str, key: &[u8]) -> Result<T> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .filter(|bytes| bytes.len() > TAG_LEN)
+            .ok_or_else(|| invalid(current_locale().pick("游标无效", "invalid cursor")))?;
+        let (payload, tag) = bytes.split_at(bytes.len() - TAG_LEN);
+        mac(key, payload)
+            .verify_slice(tag)
+            .map_err(|_| invalid(current_locale().pick("游标无效", "invalid cursor")))?;
+        let envelope: Envelope<T> = serde_json::from_slice(payload)
+            .map_err(|_| invalid(current_locale().pick("游标无效", "invalid cursor")))?;
+        match envelope.exp {
+            Some(exp) if exp <= now() => Err(invalid(
+                current_locale().pick("游标已过期", "cursor expired"),
+            )),
+            _ => Ok(envelope.v),
+        }
+    }
+}
+
+/// 保存签名密钥和有效期的游标编解码器，用于CursorOutput和Keyset自动签名游标
+#[derive(Clone)]
+pub struct CursorCodec {
+    key: Vec<u8>,
+    ttl: Option<Duration>,
+}
+
+impl CursorCodec {
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        CursorCodec {
+            key: key.into(),
+            ttl: None,
+        }
+    }
+
+    /// 游标有效期，默认不过期
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn encode<T: Serialize>(&self, fields: &T) -> String {
+        let exp = self.ttl.map(|ttl| now().saturating_add(ttl.as_secs()));
+        encode_envelope(fields, &self.key, exp)
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, cursor: &str) -> Result<T> {
+        Cursor::decode(cursor, &self.key)
+    }
+}
+
+// 不在Debug中输出密钥
+impl std::fmt::Debug for CursorCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CursorCodec")
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Serialize)]
+struct EnvelopeRef<'a, T> {
+    v: &'a T,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exp: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct Envelope<T> {
+    v: T,
+    #[serde(default)]
+    exp: Option<u64>,
+}
+
+fn encode_envelope<T: Serialize>(fields: &T, key: &[u8], exp: Option<u64>) -> String {
+    let mut bytes = serde_json::to_vec(&EnvelopeRef { v: fields, exp }).unwrap_or_default();
+    let tag = mac(key, &bytes).finalize().into_bytes();
+    bytes.extend_from_slice(&tag);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn mac(key: &[u8], payload: &[u8]) -> HmacSha256 {
+    // HMAC接受任意长度的密钥
+    let mut mac = HmacSha256::new_from_slice(key).expect("hmac accepts any key length");
+    mac.update(payload);
+    mac
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+fn invalid(desc: &str) -> ExtraDescError {
+    InvalidInput.from_desc(desc).with_field("cursor")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = b"cursor-secret";
+
+    #[test]
+    fn test_round_trip() {
+        let cursor = Cursor::encode(&("2024-05-01T00:00:00Z", 12345), KEY);
+        assert!(!cursor.contains("12345"));
+        assert!(cursor
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        let fields: (String, u64) = Cursor::decode(&cursor, KEY).unwrap();
+        assert_eq!(fields, ("2024-05-01T00:00:00Z".to_string(), 12345));
+
+        let codec = CursorCodec::new(KEY).ttl(Duration::from_secs(60));
+        let fields: (i64, i32) = codec.decode(&codec.encode(&(7, 3))).unwrap();
+        assert_eq!(fields, (7, 3));
+    }
+
+    #[test]
+    fn test_tampered() {
+        let cursor = Cursor::encode(&(7, 3), KEY);
+        let mut bytes = URL_SAFE_NO_PAD.decode(&cursor).unwrap();
+        bytes[2] ^= 0x01;
+        let flipped = URL_SAFE_NO_PAD.encode(&bytes);
+        let err = Cursor::decode::<(i64, i32)>(&flipped, KEY).unwrap_err();
+        assert_eq!(err.err, InvalidInput);
+        assert_eq!(err.field.as_deref(), Some("cursor"));
+
+        let truncated = &cursor[..cursor.len() - 4];
+        assert!(Cursor::decode::<(i64, i32)>(truncated, KEY).is_err());
+        assert!(Cursor::decode::<(i64, i32)>("", KEY).is_err());
+        assert!(Cursor::decode::<(i64, i32)>("not a cursor!", KEY).is_err());
+    }
+
+    #[test]
+    fn test_wrong_key() {
+        let cursor = Cursor::encode(&(7, 3), KEY);
+        let err = Cursor::decode::<(i64, i32)>(&cursor, b"other-secret").unwrap_err();
+        assert_eq!(err.err, InvalidInput);
+    }
+
+    #[test]
+    fn test_expired() {
+        let cursor = encode_envelope(&(7, 3), KEY, Some(now() - 10));
+        let err = Cursor::decode::<(i64, i32)>(&cursor, KEY).unwrap_err();
+        assert_eq!(err.err, InvalidInput);
+        assert_eq!(err.desc, "游标已过期");
+
+        let cursor = encode_envelope(&(7, 3), KEY, Some(now() + 10));
+        assert_eq!(Cursor::decode::<(i64, i32)>(&cursor, KEY).unwrap(), (7, 3));
+    }
+}
@@ -0,0 +1,457 @@
+use crate::define::{ExtraDescError, InvalidInput, Locale, Result};
+use crate::middleware::current_locale;
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+use std::fmt::{self, Display};
+
+/// 过滤操作符
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    /// 字符串包含
+    Like,
+}
+
+impl FilterOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterOp::Eq => "eq",
+            FilterOp::Ne => "ne",
+            FilterOp::Gt => "gt",
+            FilterOp::Gte => "gte",
+            FilterOp::Lt => "lt",
+            FilterOp::Lte => "lte",
+            FilterOp::Like => "like",
+        }
+    }
+
+    pub fn from_name(op: &str) -> Option<FilterOp> {
+        [
+            FilterOp::Eq,
+            FilterOp::Ne,
+            FilterOp::Gt,
+            FilterOp::Gte,
+            FilterOp::Lt,
+            FilterOp::Lte,
+            FilterOp::Like,
+        ]
+        .into_iter()
+        .find(|candidate| candidate.as_str() == op)
+    }
+}
+
+impl Display for FilterOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 过滤值的类型，决定字符串如何转换为FilterValue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    Int,
+    Bool,
+    String,
+    /// RFC 3339格式，统一转换为UTC
+    #[cfg(feature = "chrono")]
+    DateTime,
+}
+
+impl ValueType {
+    fn name(&self, locale: Locale) -> &'static str {
+        match self {
+            ValueType::Int => locale.pick("整数", "an integer"),
+            ValueType::Bool => locale.pick("布尔值", "a boolean"),
+            ValueType::String => locale.pick("字符串", "a string"),
+            #[cfg(feature = "chrono")]
+            ValueType::DateTime => locale.pick("RFC 3339时间", "an RFC 3339 datetime"),
+        }
+    }
+
+    fn coerce(&self, value: &str) -> Option<FilterValue> {
+        match self {
+            ValueType::Int => value.parse().ok().map(FilterValue::Int),
+            ValueType::Bool => match value {
+                "true" | "1" => Some(FilterValue::Bool(true)),
+                "false" | "0" => Some(FilterValue::Bool(false)),
+                _ => None,
+            },
+            ValueType::String => Some(FilterValue::String(value.to_string())),
+            #[cfg(feature = "chrono")]
+            ValueType::DateTime => DateTime::parse_from_rfc3339(value)
+                .ok()
+                .map(|time| FilterValue::DateTime(time.with_timezone(&Utc))),
+        }
+    }
+}
+
+/// 转换后的过滤值
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterValue {
+    Int(i64),
+    Bool(bool),
+    String(String),
+    #[cfg(feature = "chrono")]
+    DateTime(DateTime<Utc>),
+}
+
+impl PartialOrd for FilterValue {
+    /// 只有相同类型的值可以比较
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (FilterValue::Int(a), FilterValue::Int(b)) => a.partial_cmp(b),
+            (FilterValue::Bool(a), FilterValue::Bool(b)) => a.partial_cmp(b),
+            (FilterValue::String(a), FilterValue::String(b)) => a.partial_cmp(b),
+            #[cfg(feature = "chrono")]
+            (FilterValue::DateTime(a), FilterValue::DateTime(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+}
+
+/// 一个过滤条件，如`port:gte:1000`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub field: String,
+    pub op: FilterOp,
+    pub value: FilterValue,
+}
+
+impl Filter {
+    /// 判断字段的实际值是否满足条件，类型不同时不满足
+    pub fn matches(&self, actual: &FilterValue) -> bool {
+        if self.op == FilterOp::Like {
+            return match (actual, &self.value) {
+                (FilterValue::String(actual), FilterValue::String(value)) => {
+                    actual.contains(value.as_str())
+                }
+                _ => false,
+            };
+        }
+        match actual.partial_cmp(&self.value) {
+            Some(ordering) => match self.op {
+                FilterOp::Eq => ordering.is_eq(),
+                FilterOp::Ne => ordering.is_ne(),
+                FilterOp::Gt => ordering.is_gt(),
+                FilterOp::Gte => ordering.is_ge(),
+                FilterOp::Lt => ordering.is_lt(),
+                FilterOp::Lte => ordering.is_le(),
+                FilterOp::Like => false,
+            },
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FieldRule {
+    field: String,
+    value_type: ValueType,
+    ops: Vec<FilterOp>,
+}
+
+/// 过滤条件的白名单，规定每个字段的值类型和允许的操作符
+///
+/// 条件的格式为`field:op:value`，多个条件用逗号分隔或重复传参，value中可以包含冒号但不能包含逗号
+///
+/// # Example
+///
+/// ```ignore
+/// let spec = FilterSpec::new()
+///     .field("status", ValueType::String, &[FilterOp::Eq, FilterOp::Ne])
+///     .field("port", ValueType::Int, &[FilterOp::Gte, FilterOp::Lte])
+///     .field("name", ValueType::String, &[FilterOp::Like]);
+/// let filters = spec.parse_query(req.query_string(), "filter")?;
+/// let devices = FilterSpec::apply(devices, &filters, |dev, field| match field {
+///     "status" => Some(FilterValue::String(dev.status.clone())),
+///     "port" => Some(FilterValue::Int(dev.port as i64)),
+///     "name" => Some(FilterValue::String(dev.name.clone())),
+///     _ => None,
+/// });
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FilterSpec {
+    rules: Vec<FieldRule>,
+}
+
+impl FilterSpec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn field(mut self, field: &str, value_type: ValueType, ops: &[FilterOp]) -> Self {
+        self.rules.push(FieldRule {
+            field: field.to_string(),
+            value_type,
+            ops: ops.to_vec(),
+        });
+        self
+    }
+
+    /// 解析逗号分隔的多个条件
+    pub fn parse(&self, input: &str) -> Result<Vec<Filter>> {
+        self.parse_all(std::iter::once(input))
+    }
+
+    /// 解析多个参数值，每个参数值中也可以用逗号分隔多个条件
+    pub fn parse_all<'a, I>(&self, inputs: I) -> Result<Vec<Filter>>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        inputs
+            .into_iter()
+            .flat_map(|input| input.split(','))
+            .map(str::trim)
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| self.parse_segment(segment))
+            .collect()
+    }
+
+    /// 从原始查询字符串中读取所有名为name的参数并解析
+    pub fn parse_query(&self, query: &str, name: &str) -> Result<Vec<Filter>> {
+        let pairs = actix_web::web::Query::<Vec<(String, String)>>::from_query(query)
+            .map_err(|e| InvalidInput.from_desc(e.to_string()).with_field(name))?
+            .into_inner();
+        self.parse_all(
+            pairs
+                .iter()
+                .filter(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str()),
+        )
+        .map_err(|e| e.with_field(name))
+    }
+
+    fn parse_segment(&self, segment: &str) -> Result<Filter> {
+        let locale = current_locale();
+        let invalid = |reason: String| -> ExtraDescError {
+            let desc = format!(
+                "{}`{}`: {}",
+                locale.pick("过滤条件错误", "invalid filter "),
+                segment,
+                reason
+            );
+            InvalidInput
+                .from_desc(desc)
+                .with_field("filter")
+                .with_extra("segment", segment)
+        };
+
+        let mut parts = segment.splitn(3, ':');
+        let (field, op, value) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(field), Some(op), Some(value)) => (field, op, value),
+            _ => {
+                return Err(invalid(
+                    locale
+                        .pick("格式应为field:op:value", "expected field:op:value")
+                        .to_string(),
+                ))
+            }
+        };
+        let rule = self
+            .rules
+            .iter()
+            .find(|rule| rule.field == field)
+            .ok_or_else(|| {
+                let allowed: Vec<&str> =
+                    self.rules.iter().map(|rule| rule.field.as_str()).collect();
+                invalid(format!(
+                    "{}{}{}: {}",
+                    locale.pick("不支持按", "unsupported field "),
+                    field,
+                    locale.pick("过滤，可选字段", ", allowed fields"),
+                    allowed.join(", ")
+                ))
+            })?;
+        let op = FilterOp::from_name(op)
+            .filter(|op| rule.ops.contains(op))
+            .ok_or_else(|| {
+                let allowed: Vec<&str> = rule.ops.iter().map(FilterOp::as_str).collect();
+                invalid(format!(
+                    "{}{}{}{}: {}",
+                    locale.pick("字段", "field "),
+                    field,
+                    locale.pick("不支持操作符", " does not support operator "),
+                    op,
+                    allowed.join(", ")
+                ))
+            })?;
+        let value = rule.value_type.coerce(value).ok_or_else(|| {
+            invalid(format!(
+                "{}{}{}",
+                value,
+                locale.pick("不是", " is not "),
+                rule.value_type.name(locale)
+            ))
+        })?;
+        Ok(Filter {
+            field: field.to_string(),
+            op,
+            value,
+        })
+    }
+
+    /// 在内存中过滤，保留满足全部条件的项
+    ///
+    /// accessor返回某一项在字段上的值，返回None时视为不满足
+    pub fn apply<T, F>(items: Vec<T>, filters: &[Filter], accessor: F) -> Vec<T>
+    where
+        F: Fn(&T, &str) -> Option<FilterValue>,
+    {
+        items
+            .into_iter()
+            .filter(|item| {
+                filters.iter().all(|filter| {
+                    accessor(item, &filter.field)
+                        .map(|actual| filter.matches(&actual))
+                        .unwrap_or(false)
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Device {
+        name: &'static str,
+        status: &'static str,
+        port: i64,
+        enabled: bool,
+    }
+
+    fn spec() -> FilterSpec {
+        FilterSpec::new()
+            .field("status", ValueType::String, &[FilterOp::Eq, FilterOp::Ne])
+            .field(
+                "port",
+                ValueType::Int,
+                &[
+                    FilterOp::Eq,
+                    FilterOp::Gt,
+                    FilterOp::Gte,
+                    FilterOp::Lt,
+                    FilterOp::Lte,
+                ],
+            )
+            .field("name", ValueType::String, &[FilterOp::Like])
+            .field("enabled", ValueType::Bool, &[FilterOp::Eq])
+    }
+
+    fn devices() -> Vec<Device> {
+        vec![
+            Device {
+                name: "核心路由器",
+                status: "online",
+                port: 80,
+                enabled: true,
+            },
+            Device {
+                name: "边缘路由器",
+                status: "offline",
+                port: 1000,
+                enabled: false,
+            },
+            Device {
+                name: "摄像头",
+                status: "online",
+                port: 8080,
+                enabled: true,
+            },
+        ]
+    }
+
+    fn names(input: &str) -> Vec<&'static str> {
+        let filters = spec().parse(input).unwrap();
+        FilterSpec::apply(devices(), &filters, |dev, field| match field {
+            "status" => Some(FilterValue::String(dev.status.to_string())),
+            "port" => Some(FilterValue::Int(dev.port)),
+            "name" => Some(FilterValue::String(dev.name.to_string())),
+            "enabled" => Some(FilterValue::Bool(dev.enabled)),
+            _ => None,
+        })
+        .into_iter()
+        .map(|dev| dev.name)
+        .collect()
+    }
+
+    #[test]
+    fn test_operators() {
+        assert_eq!(names("status:eq:online"), vec!["核心路由器", "摄像头"]);
+        assert_eq!(names("status:ne:online"), vec!["边缘路由器"]);
+        assert_eq!(names("port:eq:1000"), vec!["边缘路由器"]);
+        assert_eq!(names("port:gt:1000"), vec!["摄像头"]);
+        assert_eq!(names("port:gte:1000"), vec!["边缘路由器", "摄像头"]);
+        assert_eq!(names("port:lt:1000"), vec!["核心路由器"]);
+        assert_eq!(names("port:lte:1000"), vec!["核心路由器", "边缘路由器"]);
+        assert_eq!(names("name:like:路由器"), vec!["核心路由器", "边缘路由器"]);
+        assert_eq!(names("enabled:eq:false"), vec!["边缘路由器"]);
+        assert_eq!(names("status:eq:online,port:gte:1000"), vec!["摄像头"]);
+        assert_eq!(names(""), vec!["核心路由器", "边缘路由器", "摄像头"]);
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query = "filter=name%3Alike%3A%E8%B7%AF%E7%94%B1%E5%99%A8&limit=10&filter=port:lt:1000";
+        let filters = spec().parse_query(query, "filter").unwrap();
+        assert_eq!(
+            filters,
+            vec![
+                Filter {
+                    field: "name".into(),
+                    op: FilterOp::Like,
+                    value: FilterValue::String("路由器".into()),
+                },
+                Filter {
+                    field: "port".into(),
+                    op: FilterOp::Lt,
+                    value: FilterValue::Int(1000),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_disallowed_operator() {
+        let err = spec().parse("status:eq:online,name:eq:摄像头").unwrap_err();
+        assert_eq!(err.err.code(), 1012);
+        assert_eq!(err.field.as_deref(), Some("filter"));
+        assert_eq!(
+            err.desc,
+            "过滤条件错误`name:eq:摄像头`: 字段name不支持操作符eq: like"
+        );
+        assert!(spec().parse("status:in:online").is_err());
+        assert!(spec().parse("secret:eq:1").is_err());
+        assert!(spec().parse("status").is_err());
+    }
+
+    #[test]
+    fn test_bad_value_type() {
+        let err = spec().parse("port:gte:abc").unwrap_err();
+        assert_eq!(err.desc, "过滤条件错误`port:gte:abc`: abc不是整数");
+        let err = spec().parse("enabled:eq:yes").unwrap_err();
+        assert_eq!(err.desc, "过滤条件错误`enabled:eq:yes`: yes不是布尔值");
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_datetime() {
+        let spec = FilterSpec::new().field("created_at", ValueType::DateTime, &[FilterOp::Gte]);
+        let filters = spec
+            .parse("created_at:gte:2024-05-01T08:00:00+08:00")
+            .unwrap();
+        let expected = DateTime::parse_from_rfc3339("2024-05-01T00:00:00Z").unwrap();
+        assert_eq!(
+            filters[0].value,
+            FilterValue::DateTime(expected.with_timezone(&Utc))
+        );
+        assert!(spec.parse("created_at:gte:yesterday").is_err());
+    }
+}
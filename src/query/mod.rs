@@ -0,0 +1,1702 @@
+use crate::compat::{impl_json_responder, insert_header, HttpResponseBuilder};
+use crate::define::{DataBaseError, InvalidInput, Result};
+use crate::middleware::current_locale;
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::HttpRequest;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt::Display;
+use std::ops::Index;
+
+#[cfg(feature = "cursor")]
+mod cursor;
+mod filter;
+mod sort;
+#[cfg(feature = "chrono")]
+mod time_range;
+
+#[cfg(feature = "cursor")]
+pub use cursor::{Cursor, CursorCodec};
+pub use filter::{Filter, FilterOp, FilterSpec, FilterValue, ValueType};
+pub use sort::{sort_items, SortKey};
+#[cfg(feature = "chrono")]
+pub use time_range::TimeRange;
+
+/// 分页查询结果
+///
+/// offset、page、total_pages、links和meta未设置时不序列化。limit不为0时total_pages随total和limit自动计算。
+///
+/// 通过collect、From<Vec>创建时total为数据数量。total()设置过总数后，items()和extend不再修改total，
+/// 与调用顺序无关
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct QueryOutput<T> {
+    pub items: Vec<T>,
+
+    #[serde(default)]
+    #[cfg_attr(feature = "utoipa", schema(required = true))]
+    pub limit: usize,
+
+    #[serde(default, alias = "total_count", alias = "totalCount")]
+    #[cfg_attr(feature = "utoipa", schema(required = true))]
+    pub total: usize,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+
+    /// 当前页码，从1开始
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<usize>,
+
+    #[serde(default, alias = "totalPages", skip_serializing_if = "Option::is_none")]
+    pub total_pages: Option<usize>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<PageLinks>,
+
+    /// 附加信息，如生效的过滤条件、汇总值等
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "utoipa", schema(value_type = Option<Object>))]
+    pub meta: Option<Map<String, Value>>,
+
+    /// total是否已经显式设置，反序列化得到的total视为已设置
+    #[serde(skip, default = "explicit_total")]
+    total_set: bool,
+}
+
+fn explicit_total() -> bool {
+    true
+}
+
+// total_set只是构建时的标记，不参与比较
+impl<T: PartialEq> PartialEq for QueryOutput<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.items == other.items
+            && self.limit == other.limit
+            && self.total == other.total
+            && self.offset == other.offset
+            && self.page == other.page
+            && self.total_pages == other.total_pages
+            && self.links == other.links
+            && self.meta == other.meta
+    }
+}
+
+impl_json_responder!(QueryOutput);
+
+/// 分页导航链接，由QueryOutput::with_links生成，到达边界时不返回next/prev
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct PageLinks {
+    #[serde(rename = "self")]
+    pub self_: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev: Option<String>,
+
+    pub first: String,
+
+    pub last: String,
+}
+
+/// QueryOutput的序列化方式，默认与QueryOutput直接序列化的结果相同
+///
+/// 反序列化时QueryOutput同时接受各种命名，省略的limit、total视为0
+///
+/// # Example
+///
+/// ```ignore
+/// let profile = OutputProfile::new().camel_case(true).total_count(true);
+/// HttpResponse::Ok().json(output.serialize_with(profile))
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutputProfile {
+    camel_case: bool,
+    total_count: bool,
+    skip_zero: bool,
+}
+
+impl OutputProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 字段名使用camelCase，如totalPages
+    pub fn camel_case(mut self, camel_case: bool) -> Self {
+        self.camel_case = camel_case;
+        self
+    }
+
+    /// total改名为total_count（camelCase时为totalCount）
+    pub fn total_count(mut self, total_count: bool) -> Self {
+        self.total_count = total_count;
+        self
+    }
+
+    /// 省略值为0的limit、total、offset、total_pages和空的meta，items始终保留
+    pub fn skip_zero(mut self, skip_zero: bool) -> Self {
+        self.skip_zero = skip_zero;
+        self
+    }
+
+    fn key(&self, name: &'static str) -> &'static str {
+        match (name, self.camel_case, self.total_count) {
+            ("total", false, true) => "total_count",
+            ("total", true, true) => "totalCount",
+            ("total_pages", true, _) => "totalPages",
+            (name, _, _) => name,
+        }
+    }
+}
+
+/// 按OutputProfile序列化的QueryOutput，由QueryOutput::serialize_with创建
+pub struct ProfiledOutput<'a, T> {
+    output: &'a QueryOutput<T>,
+    profile: OutputProfile,
+}
+
+impl<T: Serialize> Serialize for ProfiledOutput<'_, T> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let output = self.output;
+        let profile = &self.profile;
+        let keep = |value: usize| !profile.skip_zero || value != 0;
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry(profile.key("items"), &output.items)?;
+        if keep(output.limit) {
+            map.serialize_entry(profile.key("limit"), &output.limit)?;
+        }
+        if keep(output.total) {
+            map.serialize_entry(profile.key("total"), &output.total)?;
+        }
+        for (name, value) in [
+            ("offset", output.offset),
+            ("page", output.page),
+            ("total_pages", output.total_pages),
+        ] {
+            if let Some(value) = value.filter(|value| keep(*value)) {
+                map.serialize_entry(profile.key(name), &value)?;
+            }
+        }
+        if let Some(links) = &output.links {
+            map.serialize_entry(profile.key("links"), links)?;
+        }
+        if let Some(meta) = output
+            .meta
+            .as_ref()
+            .filter(|meta| !profile.skip_zero || !meta.is_empty())
+        {
+            map.serialize_entry(profile.key("meta"), meta)?;
+        }
+        map.end()
+    }
+}
+
+/// 对URL中的非ASCII字节做百分号编码，保证可以放入响应头
+fn ascii_url(url: &str) -> String {
+    let mut encoded = String::with_capacity(url.len());
+    for byte in url.bytes() {
+        if byte.is_ascii() && !byte.is_ascii_control() {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// 替换查询参数中的key，其它参数保持原样（不重新编码）
+fn replace_param(path: &str, query: &str, key: &str, value: usize) -> String {
+    let mut replaced = false;
+    let mut pairs = Vec::new();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        if pair.split('=').next() == Some(key) {
+            if !replaced {
+                pairs.push(format!("{}={}", key, value));
+                replaced = true;
+            }
+        } else {
+            pairs.push(pair.to_string());
+        }
+    }
+    if !replaced {
+        pairs.push(format!("{}={}", key, value));
+    }
+    format!("{}?{}", path, pairs.join("&"))
+}
+
+impl<T> Default for QueryOutput<T> {
+    fn default() -> Self {
+        QueryOutput {
+            items: Vec::new(),
+            limit: 0,
+            total: 0,
+            offset: None,
+            page: None,
+            total_pages: None,
+            links: None,
+            meta: None,
+            total_set: false,
+        }
+    }
+}
+
+impl<T> FromIterator<T> for QueryOutput<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        QueryOutput::from(iter.into_iter().collect::<Vec<T>>())
+    }
+}
+
+impl<T> From<Vec<T>> for QueryOutput<T> {
+    fn from(items: Vec<T>) -> Self {
+        QueryOutput::empty().items(items)
+    }
+}
+
+/// (本页数据, 总数)，limit为0
+impl<T> From<(Vec<T>, usize)> for QueryOutput<T> {
+    fn from((items, total): (Vec<T>, usize)) -> Self {
+        QueryOutput::empty().items(items).total(total)
+    }
+}
+
+impl<T> Extend<T> for QueryOutput<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.items.extend(iter);
+        if !self.total_set {
+            self.total = self.items.len();
+            self.update_total_pages();
+        }
+    }
+}
+
+impl<T> IntoIterator for QueryOutput<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a QueryOutput<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut QueryOutput<T> {
+    type Item = &'a mut T;
+    type IntoIter = std::slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter_mut()
+    }
+}
+
+impl<T> Index<usize> for QueryOutput<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        &self.items[index]
+    }
+}
+
+impl<T> QueryOutput<T> {
+    /// 由本页数据、总数和limit创建查询结果
+    pub fn new(items: Vec<T>, total: usize, limit: usize) -> Self {
+        QueryOutput::empty().items(items).total(total).limit(limit)
+    }
+
+    /// 没有数据的查询结果：items为空，limit和total为0，其它字段不设置
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// 本页数据的迭代器，以下len、is_empty、first同样只针对本页数据，与total无关
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.items.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.items.first()
+    }
+
+    /// 丢弃分页信息，只取本页数据
+    pub fn into_items(self) -> Vec<T> {
+        self.items
+    }
+
+    /// 对内存中的完整列表分页，total为完整列表的长度
+    ///
+    /// offset超出范围时返回空页并在meta中设置`out_of_range: true`；limit为0表示不限制，返回offset之后的全部数据
+    pub fn paginate_vec(items: Vec<T>, offset: usize, limit: usize) -> Self {
+        Self::paginate(items, offset, limit)
+    }
+
+    /// 同paginate_vec，按policy处理超出范围的offset
+    pub fn paginate_vec_with(
+        items: Vec<T>,
+        offset: usize,
+        limit: usize,
+        policy: OutOfRange,
+    ) -> Result<Self> {
+        let offset = policy.resolve(offset, limit, items.len())?;
+        Ok(Self::paginate(items, offset, limit))
+    }
+
+    fn paginate(items: Vec<T>, offset: usize, limit: usize) -> Self {
+        let total = items.len();
+        let page = items
+            .into_iter()
+            .skip(offset)
+            .take(if limit == 0 { usize::MAX } else { limit })
+            .collect();
+        QueryOutput::empty()
+            .items(page)
+            .total(total)
+            .limit(limit)
+            .offset(offset)
+            .flag_out_of_range()
+    }
+
+    /// paginate_vec的借用版本，复制本页的数据
+    pub fn paginate_slice(items: &[T], offset: usize, limit: usize) -> Self
+    where
+        T: Clone,
+    {
+        let start = offset.min(items.len());
+        let end = match limit {
+            0 => items.len(),
+            limit => start.saturating_add(limit).min(items.len()),
+        };
+        QueryOutput::empty()
+            .items(items[start..end].to_vec())
+            .total(items.len())
+            .limit(limit)
+            .offset(offset)
+            .flag_out_of_range()
+    }
+
+    /// offset超出范围时在meta中设置`out_of_range: true`，见OutOfRange::EmptyWithFlag
+    pub(crate) fn flag_out_of_range(self) -> Self {
+        match self.offset {
+            Some(offset) if is_out_of_range(offset, self.total) => {
+                self.meta_insert("out_of_range", true)
+            }
+            _ => self,
+        }
+    }
+
+    /// 设置本页数据，没有通过total()设置总数时total为本页数量
+    ///
+    /// 与total()的调用顺序无关，不会覆盖已经设置的total
+    pub fn items(mut self, items: Vec<T>) -> Self {
+        if !self.total_set {
+            self.total = items.len();
+        }
+        self.items = items;
+        self.update_total_pages();
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self.update_total_pages();
+        self
+    }
+
+    pub fn total(mut self, count: usize) -> Self {
+        self.total = count;
+        self.total_set = true;
+        self.update_total_pages();
+        self
+    }
+
+    /// 设置数据库返回的总数（diesel的count()为i64），负数或超出usize范围时返回DataBaseError
+    pub fn total_i64(self, count: i64) -> Result<Self> {
+        self.try_total(count)
+    }
+
+    /// 同total_i64，负数按0处理，超出usize范围时取usize::MAX
+    pub fn total_saturating(self, count: i64) -> Self {
+        let total = usize::try_from(count.max(0)).unwrap_or(usize::MAX);
+        self.total(total)
+    }
+
+    /// 设置任意整数类型的总数，转换为usize失败时返回DataBaseError，desc中包含原始值
+    pub fn try_total<N>(self, count: N) -> Result<Self>
+    where
+        N: TryInto<usize> + Display + Copy,
+    {
+        match count.try_into() {
+            Ok(total) => Ok(self.total(total)),
+            Err(_) => Err(DataBaseError.from_desc(format!(
+                "{}: {}",
+                current_locale().pick("总数超出范围", "total out of range"),
+                count
+            ))),
+        }
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    pub fn page(mut self, page: usize) -> Self {
+        self.page = Some(page);
+        self
+    }
+
+    /// 转换每一项的类型，保留limit、total等其它字段，不会根据转换后的数量重新计算total
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let output: QueryOutput<DeviceDto> = repo.list(&pagination)?.map(DeviceDto::from);
+    /// ```
+    pub fn map<U, F: FnMut(T) -> U>(self, f: F) -> QueryOutput<U> {
+        match self.replace_items(|items| Ok::<_, Infallible>(items.into_iter().map(f).collect())) {
+            Ok(output) => output,
+            Err(never) => match never {},
+        }
+    }
+
+    /// map的可失败版本，遇到第一个错误时返回该错误
+    pub fn try_map<U, F>(self, f: F) -> Result<QueryOutput<U>>
+    where
+        F: FnMut(T) -> Result<U>,
+    {
+        self.replace_items(|items| items.into_iter().map(f).collect())
+    }
+
+    /// 替换items并保留其它字段，新增字段时只需要修改这里
+    fn replace_items<U, E, F>(self, f: F) -> core::result::Result<QueryOutput<U>, E>
+    where
+        F: FnOnce(Vec<T>) -> core::result::Result<Vec<U>, E>,
+    {
+        Ok(QueryOutput {
+            items: f(self.items)?,
+            limit: self.limit,
+            total: self.total,
+            offset: self.offset,
+            page: self.page,
+            total_pages: self.total_pages,
+            links: self.links,
+            meta: self.meta,
+            total_set: self.total_set,
+        })
+    }
+
+    /// 按指定的命名和省略规则序列化
+    pub fn serialize_with(&self, profile: OutputProfile) -> ProfiledOutput<'_, T> {
+        ProfiledOutput {
+            output: self,
+            profile,
+        }
+    }
+
+    /// 在meta中添加一项，value无法序列化时记为null
+    pub fn meta_insert<K: Into<String>, V: Serialize>(mut self, key: K, value: V) -> Self {
+        let value = serde_json::to_value(value).unwrap_or(Value::Null);
+        self.meta
+            .get_or_insert_with(Map::new)
+            .insert(key.into(), value);
+        self
+    }
+
+    /// 整体替换meta
+    pub fn with_meta(mut self, meta: Map<String, Value>) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+
+    /// 根据当前请求生成分页导航链接，需要先设置limit和total
+    ///
+    /// 请求中使用page参数（且没有offset）时按页码生成，否则按offset生成，其它查询参数原样保留
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut output = QueryOutput::from(devices).total(total);
+    /// pagination.apply_to(&mut output);
+    /// HttpResponse::Ok().json(output.with_links(&req))
+    /// ```
+    pub fn with_links(mut self, req: &HttpRequest) -> Self {
+        self.links = Some(self.page_links(req));
+        self
+    }
+
+    /// 以响应头的形式返回分页信息：X-Total-Count、X-Limit、X-Offset和RFC 5988的Link
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut builder = HttpResponse::Ok();
+    /// output.apply_headers(&mut builder, &req);
+    /// builder.json(output)
+    /// ```
+    pub fn apply_headers(&self, builder: &mut HttpResponseBuilder, req: &HttpRequest) {
+        let links = self.page_links(req);
+        let offset = self
+            .offset
+            .or_else(|| self.page.map(|page| page.saturating_sub(1) * self.limit))
+            .unwrap_or(0);
+        let link = [
+            ("next", links.next.as_ref()),
+            ("prev", links.prev.as_ref()),
+            ("first", Some(&links.first)),
+            ("last", Some(&links.last)),
+        ]
+        .into_iter()
+        .filter_map(|(rel, url)| url.map(|url| format!("<{}>; rel=\"{}\"", ascii_url(url), rel)))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+        for (name, value) in [
+            ("x-total-count", self.total),
+            ("x-limit", self.limit),
+            ("x-offset", offset),
+        ] {
+            insert_header(
+                builder,
+                HeaderName::from_static(name),
+                HeaderValue::from(value),
+            );
+        }
+        if let Ok(value) = HeaderValue::from_str(&link) {
+            insert_header(builder, header::LINK, value);
+        }
+    }
+
+    fn page_links(&self, req: &HttpRequest) -> PageLinks {
+        let path = req.path();
+        let query = req.query_string();
+        let self_ = if query.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}?{}", path, query)
+        };
+        let has_param = |name: &str| {
+            query
+                .split('&')
+                .any(|pair| pair.split('=').next() == Some(name))
+        };
+        let limit = self.limit.max(1);
+        let total_pages = self.total.div_ceil(limit).max(1);
+
+        if has_param("page") && !has_param("offset") {
+            let page = self
+                .page
+                .or_else(|| self.offset.map(|offset| offset / limit + 1))
+                .unwrap_or(1)
+                .max(1);
+            let url = |page| replace_param(path, query, "page", page);
+            PageLinks {
+                self_,
+                next: (page < total_pages).then(|| url(page + 1)),
+                prev: (page > 1).then(|| url(page.min(total_pages + 1) - 1)),
+                first: url(1),
+                last: url(total_pages),
+            }
+        } else {
+            let offset = self
+                .offset
+                .or_else(|| self.page.map(|page| page.saturating_sub(1) * limit))
+                .unwrap_or(0);
+            let url = |offset| replace_param(path, query, "offset", offset);
+            PageLinks {
+                self_,
+                next: (offset + limit < self.total).then(|| url(offset + limit)),
+                prev: (offset > 0).then(|| url(offset.saturating_sub(limit))),
+                first: url(0),
+                last: url((total_pages - 1) * limit),
+            }
+        }
+    }
+
+    /// 根据total和limit重新计算total_pages，limit为0时无法计算，置为None
+    pub(crate) fn update_total_pages(&mut self) {
+        self.total_pages = match self.limit {
+            0 => None,
+            limit => Some(self.total.div_ceil(limit)),
+        };
+    }
+}
+
+/// 带汇总信息的分页查询结果，序列化时在QueryOutput的字段后增加summary
+///
+/// summary应反映过滤后的全部数据，而不是当前页，一般通过单独的聚合查询（GROUP BY、SUM）得到
+///
+/// # Example
+///
+/// ```ignore
+/// let summary = repo::count_by_status(&conn, &filters)?;
+/// HttpResponse::Ok().json(output.with_summary(summary))
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct QueryOutputWithSummary<T, S> {
+    #[serde(flatten)]
+    pub output: QueryOutput<T>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<S>,
+}
+
+impl<T, S> From<QueryOutput<T>> for QueryOutputWithSummary<T, S> {
+    fn from(output: QueryOutput<T>) -> Self {
+        QueryOutputWithSummary {
+            output,
+            summary: None,
+        }
+    }
+}
+
+impl<T, S> QueryOutputWithSummary<T, S> {
+    pub fn summary(mut self, summary: S) -> Self {
+        self.summary = Some(summary);
+        self
+    }
+}
+
+impl<T> QueryOutput<T> {
+    pub fn with_summary<S>(self, summary: S) -> QueryOutputWithSummary<T, S> {
+        QueryOutputWithSummary::from(self).summary(summary)
+    }
+}
+
+/// 按key_fn分组计数，常用于按状态统计数量
+///
+/// 数据量大时应在数据库中GROUP BY，这里只适合内存中的完整列表
+pub fn summarize_by_key<T, K, F>(items: &[T], key_fn: F) -> BTreeMap<String, usize>
+where
+    K: ToString,
+    F: Fn(&T) -> K,
+{
+    let mut counts = BTreeMap::new();
+    for item in items {
+        *counts.entry(key_fn(item).to_string()).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// 排序方向
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// offset超出范围（大于0且不小于总数）时的处理方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutOfRange {
+    /// 返回空页，并在meta中设置`out_of_range: true`
+    #[default]
+    EmptyWithFlag,
+    /// 改为返回最后一页
+    ClampToLastPage,
+    /// 返回InvalidInput，desc和extra.max_offset中给出最大的有效offset
+    Reject,
+}
+
+fn is_out_of_range(offset: usize, total: usize) -> bool {
+    offset > 0 && offset >= total
+}
+
+/// 最后一页的offset，limit为0（不限制）时为0
+fn last_page_offset(total: usize, limit: usize) -> usize {
+    if total == 0 || limit == 0 {
+        0
+    } else {
+        (total - 1) / limit * limit
+    }
+}
+
+impl OutOfRange {
+    /// 返回实际使用的offset
+    pub fn resolve(self, offset: usize, limit: usize, total: usize) -> Result<usize> {
+        if !is_out_of_range(offset, total) {
+            return Ok(offset);
+        }
+        let max_offset = last_page_offset(total, limit);
+        match self {
+            OutOfRange::EmptyWithFlag => Ok(offset),
+            OutOfRange::ClampToLastPage => Ok(max_offset),
+            OutOfRange::Reject => {
+                let desc = format!(
+                    "{}{}",
+                    current_locale().pick(
+                        "offset超出范围，最大有效值为",
+                        "offset out of range, max valid offset is "
+                    ),
+                    max_offset
+                );
+                Err(InvalidInput
+                    .from_desc(desc)
+                    .with_field("offset")
+                    .with_extra("max_offset", max_offset))
+            }
+        }
+    }
+}
+
+/// 关键字的最大字符数，超出部分被截掉
+pub const MAX_KEYWORD_CHARS: usize = 64;
+
+fn default_query_limit() -> usize {
+    20
+}
+
+/// 列表接口的标准查询参数，limit默认20，offset默认0
+///
+/// # Example
+///
+/// ```ignore
+/// async fn list(query: web::Query<QueryInput>) -> HttpResult<Json<QueryOutput<Device>>> {
+///     let mut input = query.into_inner();
+///     input.validate(100)?;
+///     input.sanitize_keyword();
+///     let output = input.to_output_shell().items(find(&input)?);
+///     ...
+/// }
+/// ```
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct QueryInput {
+    #[serde(default = "default_query_limit")]
+    pub limit: usize,
+
+    #[serde(default)]
+    pub offset: usize,
+
+    #[serde(default)]
+    pub keyword: Option<String>,
+
+    #[serde(default)]
+    pub sort: Option<String>,
+
+    #[serde(default)]
+    pub order: Option<SortOrder>,
+}
+
+impl Default for QueryInput {
+    fn default() -> Self {
+        QueryInput {
+            limit: default_query_limit(),
+            offset: 0,
+            keyword: None,
+            sort: None,
+            order: None,
+        }
+    }
+}
+
+impl QueryInput {
+    /// 检查limit在1到max_limit之间
+    pub fn validate(&self, max_limit: usize) -> Result<()> {
+        if self.limit == 0 || self.limit > max_limit {
+            let desc = format!(
+                "{}: 1-{}",
+                current_locale().pick("limit超出范围", "limit out of range"),
+                max_limit
+            );
+            return Err(InvalidInput.from_desc(desc).with_field("limit"));
+        }
+        Ok(())
+    }
+
+    /// 去掉关键字首尾的空白并截断到MAX_KEYWORD_CHARS个字符，结果为空时置为None
+    pub fn sanitize_keyword(&mut self) {
+        self.keyword = self
+            .keyword
+            .take()
+            .map(|keyword| {
+                keyword
+                    .trim()
+                    .chars()
+                    .take(MAX_KEYWORD_CHARS)
+                    .collect::<String>()
+            })
+            .map(|keyword| keyword.trim_end().to_string())
+            .filter(|keyword| !keyword.is_empty());
+    }
+
+    /// 创建已经填好limit和offset的查询结果
+    pub fn to_output_shell<T>(&self) -> QueryOutput<T> {
+        QueryOutput::empty().limit(self.limit).offset(self.offset)
+    }
+}
+
+/// 排序字段的最大数量
+pub const MAX_SORT_KEYS: usize = 5;
+
+/// 一个排序字段及其方向
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SortField {
+    pub field: String,
+    pub direction: SortOrder,
+}
+
+impl SortField {
+    /// 按方向调整升序比较的结果
+    pub fn apply(&self, ordering: Ordering) -> Ordering {
+        match self.direction {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    }
+}
+
+/// 解析`sort=name,-created_at`形式的排序参数
+///
+/// 字段前的`-`表示降序，`+`或不加前缀表示升序。字段必须在允许的列表中，重复的字段只保留第一次出现的
+///
+/// # Example
+///
+/// ```ignore
+/// let fields = SortSpec::parse(input.sort.as_deref().unwrap_or(""), &["name", "created_at"])?;
+/// let query = devices::table.order(sql::<Text>(&SortSpec::to_sql_order_by(&fields)));
+/// ```
+pub struct SortSpec;
+
+impl SortSpec {
+    pub fn parse(input: &str, allowed: &[&str]) -> Result<Vec<SortField>> {
+        let locale = current_locale();
+        let mut fields: Vec<SortField> = Vec::new();
+        for token in input
+            .split(',')
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+        {
+            let (field, direction) = match token.strip_prefix('-') {
+                Some(field) => (field, SortOrder::Desc),
+                None => (token.trim_start_matches('+'), SortOrder::Asc),
+            };
+            let field = field.trim();
+            if !allowed.contains(&field) {
+                let desc = format!(
+                    "{}`{}`{}: {}",
+                    locale.pick("不支持按", "unsupported sort field "),
+                    field,
+                    locale.pick("排序，可选字段", ", allowed fields"),
+                    allowed.join(", ")
+                );
+                return Err(InvalidInput
+                    .from_desc(desc)
+                    .with_field("sort")
+                    .with_extra("allowed", allowed));
+            }
+            if fields.iter().any(|sort| sort.field == field) {
+                continue;
+            }
+            if fields.len() == MAX_SORT_KEYS {
+                let desc = format!(
+                    "{}{}",
+                    locale.pick("排序字段过多，最多", "too many sort fields, at most "),
+                    MAX_SORT_KEYS
+                );
+                return Err(InvalidInput.from_desc(desc).with_field("sort"));
+            }
+            fields.push(SortField {
+                field: field.to_string(),
+                direction,
+            });
+        }
+        Ok(fields)
+    }
+
+    /// 生成ORDER BY后面的SQL片段，如`name ASC, created_at DESC`
+    ///
+    /// 字段名已经过parse的白名单校验，可以用于diesel的`sql::<Text>`
+    pub fn to_sql_order_by(fields: &[SortField]) -> String {
+        fields
+            .iter()
+            .map(|sort| match sort.direction {
+                SortOrder::Asc => format!("{} ASC", sort.field),
+                SortOrder::Desc => format!("{} DESC", sort.field),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// 依次按每个字段比较，cmp给出两项在某个字段上的升序比较结果
+    ///
+    /// ```ignore
+    /// devices.sort_by(|a, b| SortSpec::compare(&fields, a, b, |a, b, field| match field {
+    ///     "name" => a.name.cmp(&b.name),
+    ///     _ => a.created_at.cmp(&b.created_at),
+    /// }));
+    /// ```
+    pub fn compare<T, F>(fields: &[SortField], a: &T, b: &T, cmp: F) -> Ordering
+    where
+        F: Fn(&T, &T, &str) -> Ordering,
+    {
+        fields
+            .iter()
+            .map(|sort| sort.apply(cmp(a, b, &sort.field)))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// 游标分页中由数据项生成游标，通常是排序字段的值
+pub trait CursorFor {
+    fn cursor(&self) -> String;
+}
+
+/// 游标分页查询结果，next_cursor为None时不序列化
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct CursorOutput<T> {
+    pub items: Vec<T>,
+
+    pub limit: usize,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+
+    pub has_more: bool,
+}
+
+impl<T: CursorFor> CursorOutput<T> {
+    /// 数量达到limit时认为还有下一页，以最后一项的游标作为next_cursor
+    ///
+    /// 查询时可以多取一条（limit + 1）来准确判断是否还有数据，多出的一项会被去掉
+    pub fn from_items(items: Vec<T>, limit: usize) -> Self {
+        CursorOutput::build(items, limit, CursorFor::cursor)
+    }
+}
+
+impl<T> CursorOutput<T> {
+    /// 同from_items，next_cursor为codec对最后一项的key_fn返回值签名后的游标
+    #[cfg(feature = "cursor")]
+    pub fn from_items_signed<K, F>(
+        items: Vec<T>,
+        limit: usize,
+        codec: &CursorCodec,
+        key_fn: F,
+    ) -> Self
+    where
+        K: Serialize,
+        F: Fn(&T) -> K,
+    {
+        CursorOutput::build(items, limit, |item| codec.encode(&key_fn(item)))
+    }
+
+    fn build<F: FnOnce(&T) -> String>(mut items: Vec<T>, limit: usize, cursor: F) -> Self {
+        let has_more = limit > 0 && items.len() >= limit;
+        items.truncate(limit);
+        let next_cursor = if has_more {
+            items.last().map(cursor)
+        } else {
+            None
+        };
+        CursorOutput {
+            items,
+            limit,
+            next_cursor,
+            has_more,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::define::InvalidInput;
+    use serde_json::json;
+
+    fn pages(total: usize, limit: usize) -> Option<usize> {
+        QueryOutput::<u32>::default()
+            .total(total)
+            .limit(limit)
+            .total_pages
+    }
+
+    #[test]
+    fn test_total_pages() {
+        assert_eq!(pages(0, 10), Some(0));
+        assert_eq!(pages(1, 10), Some(1));
+        assert_eq!(pages(10, 10), Some(1));
+        assert_eq!(pages(11, 10), Some(2));
+        assert_eq!(pages(100, 20), Some(5));
+        assert_eq!(pages(101, 20), Some(6));
+        assert_eq!(pages(5, 1), Some(5));
+        assert_eq!(pages(5, 0), None);
+        assert_eq!(pages(0, 0), None);
+    }
+
+    #[test]
+    fn test_items_and_total_order() {
+        let a = QueryOutput::default()
+            .total(57)
+            .items(vec![1, 2, 3])
+            .limit(3);
+        let b = QueryOutput::default()
+            .limit(3)
+            .items(vec![1, 2, 3])
+            .total(57);
+        assert_eq!(a, b);
+        assert_eq!(a.total, 57);
+        assert_eq!(a.total_pages, Some(19));
+
+        let c = QueryOutput::default().items(vec![1, 2, 3]);
+        assert_eq!(c.total, 3);
+
+        // 显式设置的total小于本页数量时也不会被items()覆盖
+        let d = QueryOutput::default().total(0).items(vec![1, 2, 3]);
+        assert_eq!(d.total, 0);
+        let e = QueryOutput::default().items(vec![1, 2, 3]).total(0);
+        assert_eq!(d, e);
+
+        // 替换items时未显式设置的total跟随新的数据数量
+        let f = QueryOutput::from(vec![1, 2, 3]).items(vec![4]);
+        assert_eq!(f.total, 1);
+    }
+
+    #[test]
+    fn test_new() {
+        let output = QueryOutput::new(vec![1, 2, 3], 57, 3);
+        assert_eq!(
+            output,
+            QueryOutput::default()
+                .items(vec![1, 2, 3])
+                .total(57)
+                .limit(3)
+        );
+        assert_eq!(output.total_pages, Some(19));
+        assert_eq!(output.clone().items(vec![4]).total, 57);
+
+        let output = QueryOutput::from((vec!["a", "b"], 10));
+        assert_eq!(output.total, 10);
+        assert_eq!(output.limit, 0);
+        assert_eq!(output.total_pages, None);
+
+        // 反序列化得到的total视为已设置
+        let output: QueryOutput<u32> =
+            serde_json::from_value(json!({"items": [1], "limit": 1, "total": 9})).unwrap();
+        assert_eq!(output.items(vec![1, 2]).total, 9);
+    }
+
+    #[test]
+    fn test_total_i64() {
+        let output = QueryOutput::<u32>::default()
+            .limit(10)
+            .total_i64(25)
+            .unwrap();
+        assert_eq!(output.total, 25);
+        assert_eq!(output.total_pages, Some(3));
+
+        let err = QueryOutput::<u32>::default().total_i64(-1).unwrap_err();
+        assert_eq!(err.err, DataBaseError);
+        assert!(err.desc.contains("-1"));
+
+        // 超出usize范围，与32位平台上i64转换失败走同一条路径
+        let huge = i128::from(u64::MAX) + 1;
+        let err = QueryOutput::<u32>::default().try_total(huge).unwrap_err();
+        assert_eq!(err.err, DataBaseError);
+        assert!(err.desc.contains(&huge.to_string()));
+
+        assert_eq!(QueryOutput::<u32>::default().total_saturating(-5).total, 0);
+        assert_eq!(QueryOutput::<u32>::default().total_saturating(42).total, 42);
+    }
+
+    #[test]
+    fn test_map() {
+        let output = QueryOutput::default()
+            .items(vec![1, 2])
+            .total(42)
+            .limit(2)
+            .offset(10)
+            .page(6)
+            .map(|id| format!("device-{}", id));
+        assert_eq!(output.items, vec!["device-1", "device-2"]);
+        assert_eq!(output.total, 42);
+        assert_eq!(output.limit, 2);
+        assert_eq!(output.offset, Some(10));
+        assert_eq!(output.page, Some(6));
+        assert_eq!(output.total_pages, Some(21));
+    }
+
+    #[test]
+    fn test_try_map() {
+        let output = QueryOutput::default().items(vec![1, 2]).total(42);
+        let mapped = output.clone().try_map(|id| Ok(id * 10)).unwrap();
+        assert_eq!(mapped.items, vec![10, 20]);
+        assert_eq!(mapped.total, 42);
+
+        let err = output
+            .try_map(|id| match id {
+                2 => Err(InvalidInput.from_desc("设备2数据错误")),
+                id => Ok(id),
+            })
+            .unwrap_err();
+        assert_eq!(err.desc, "设备2数据错误");
+    }
+
+    #[test]
+    fn test_collect_and_from_vec() {
+        let output: QueryOutput<u32> = (1..=3).filter(|id| id % 2 == 1).collect();
+        assert_eq!(output.items, vec![1, 3]);
+        assert_eq!(output.total, 2);
+        assert_eq!(output, QueryOutput::from(vec![1, 3]));
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut output = QueryOutput::from(vec![1]);
+        output.extend(vec![2, 3]);
+        assert_eq!(output.total, 3);
+
+        let mut output = QueryOutput::from(vec![1]).total(50).limit(10);
+        output.extend(vec![2, 3]);
+        assert_eq!(output.items, vec![1, 2, 3]);
+        assert_eq!(output.total, 50);
+        assert_eq!(output.total_pages, Some(5));
+    }
+
+    #[test]
+    fn test_iteration() {
+        let mut output = QueryOutput::new(vec![1, 2, 3], 30, 3);
+        for item in &mut output {
+            *item *= 10;
+        }
+        let borrowed: Vec<&i32> = (&output).into_iter().collect();
+        assert_eq!(borrowed, vec![&10, &20, &30]);
+        assert_eq!(output.iter().sum::<i32>(), 60);
+        assert_eq!(output.len(), 3);
+        assert!(!output.is_empty());
+        assert_eq!(output.first(), Some(&10));
+        assert_eq!(output[2], 30);
+        assert_eq!(output.total, 30);
+
+        let owned: Vec<i32> = output.clone().into_iter().collect();
+        assert_eq!(owned, vec![10, 20, 30]);
+        assert_eq!(output.into_items(), vec![10, 20, 30]);
+
+        let empty = QueryOutput::<i32>::empty();
+        assert!(empty.is_empty());
+        assert_eq!(empty.first(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "index out of bounds")]
+    fn test_index_out_of_bounds() {
+        let output = QueryOutput::new(vec![1, 2, 3], 30, 3);
+        let _ = output[3];
+    }
+
+    #[test]
+    fn test_empty() {
+        struct NotDefault;
+        let output = QueryOutput::<NotDefault>::empty();
+        assert!(output.items.is_empty());
+        assert_eq!(
+            serde_json::to_value(QueryOutput::<u32>::empty()).unwrap(),
+            json!({"items": [], "limit": 0, "total": 0})
+        );
+    }
+
+    #[derive(Serialize)]
+    #[serde(transparent)]
+    struct Event {
+        id: u64,
+    }
+
+    impl CursorFor for Event {
+        fn cursor(&self) -> String {
+            self.id.to_string()
+        }
+    }
+
+    fn events(ids: std::ops::RangeInclusive<u64>) -> Vec<Event> {
+        ids.map(|id| Event { id }).collect()
+    }
+
+    #[test]
+    fn test_cursor_output() {
+        let output = CursorOutput::from_items(events(1..=3), 3);
+        assert!(output.has_more);
+        assert_eq!(
+            serde_json::to_value(&output).unwrap(),
+            json!({"items": [1, 2, 3], "limit": 3, "next_cursor": "3", "has_more": true})
+        );
+
+        let output = CursorOutput::from_items(events(1..=4), 3);
+        assert_eq!(output.items.len(), 3);
+        assert_eq!(output.next_cursor.as_deref(), Some("3"));
+
+        let output = CursorOutput::from_items(events(1..=2), 3);
+        assert!(!output.has_more);
+        assert_eq!(
+            serde_json::to_value(&output).unwrap(),
+            json!({"items": [1, 2], "limit": 3, "has_more": false})
+        );
+
+        let output = CursorOutput::from_items(events(1..=2), 0);
+        assert!(output.items.is_empty());
+        assert!(!output.has_more);
+    }
+
+    #[cfg(feature = "cursor")]
+    #[test]
+    fn test_cursor_output_signed() {
+        let codec = CursorCodec::new("secret");
+        let output = CursorOutput::from_items_signed(events(1..=4), 3, &codec, |event| event.id);
+        let next = output.next_cursor.unwrap();
+        assert_ne!(next, "3");
+        assert_eq!(codec.decode::<u32>(&next).unwrap(), 3);
+    }
+
+    fn page_links(uri: &str, output: QueryOutput<u32>) -> PageLinks {
+        let req = actix_web::test::TestRequest::get()
+            .uri(uri)
+            .to_http_request();
+        output.with_links(&req).links.unwrap()
+    }
+
+    #[test]
+    fn test_links_offset() {
+        let output = QueryOutput::default().total(95).limit(20).offset(40);
+        let links = page_links("/devices?status=up&offset=40&limit=20", output);
+        assert_eq!(links.self_, "/devices?status=up&offset=40&limit=20");
+        assert_eq!(links.next.unwrap(), "/devices?status=up&offset=60&limit=20");
+        assert_eq!(links.prev.unwrap(), "/devices?status=up&offset=20&limit=20");
+        assert_eq!(links.first, "/devices?status=up&offset=0&limit=20");
+        assert_eq!(links.last, "/devices?status=up&offset=80&limit=20");
+
+        let output = QueryOutput::default().total(95).limit(20).offset(0);
+        let links = page_links("/devices?limit=20", output);
+        assert_eq!(links.prev, None);
+        assert_eq!(links.next.unwrap(), "/devices?limit=20&offset=20");
+
+        let output = QueryOutput::default().total(95).limit(20).offset(80);
+        let links = page_links("/devices?limit=20&offset=80", output);
+        assert_eq!(links.next, None);
+        assert_eq!(links.prev.unwrap(), "/devices?limit=20&offset=60");
+    }
+
+    #[test]
+    fn test_links_page() {
+        let output = QueryOutput::default().total(45).limit(10).page(1);
+        let links = page_links("/devices?page=1&limit=10", output);
+        assert_eq!(links.prev, None);
+        assert_eq!(links.next.unwrap(), "/devices?page=2&limit=10");
+        assert_eq!(links.last, "/devices?page=5&limit=10");
+
+        let output = QueryOutput::default().total(45).limit(10).page(5);
+        let links = page_links("/devices?page=5&limit=10", output);
+        assert_eq!(links.next, None);
+        assert_eq!(links.prev.unwrap(), "/devices?page=4&limit=10");
+
+        let output = QueryOutput::default().total(0).limit(10).page(1);
+        let links = page_links("/devices?page=1", output);
+        assert_eq!((links.next, links.prev), (None, None));
+        assert_eq!(links.last, "/devices?page=1");
+    }
+
+    #[test]
+    fn test_links_keep_encoding() {
+        let uri = "/devices?keyword=%E8%B7%AF%E7%94%B1%E5%99%A8&tags[]=a%26b&page=2";
+        let output = QueryOutput::default().total(30).limit(10).page(2);
+        let links = page_links(uri, output);
+        assert_eq!(links.self_, uri);
+        let next = links.next.unwrap();
+        assert_eq!(
+            next,
+            "/devices?keyword=%E8%B7%AF%E7%94%B1%E5%99%A8&tags[]=a%26b&page=3"
+        );
+        let req = actix_web::test::TestRequest::get()
+            .uri(&next)
+            .to_http_request();
+        let query = actix_web::web::Query::<std::collections::HashMap<String, String>>::from_query(
+            req.query_string(),
+        )
+        .unwrap();
+        assert_eq!(query["keyword"], "路由器");
+        assert_eq!(query["tags[]"], "a&b");
+    }
+
+    #[test]
+    fn test_meta() {
+        let output = QueryOutput::from(vec![1]);
+        assert_eq!(
+            serde_json::to_string(&output).unwrap(),
+            r#"{"items":[1],"limit":0,"total":1}"#
+        );
+
+        let output = output
+            .meta_insert("status", "online")
+            .meta_insert("max_limit", 100)
+            .meta_insert("sum", json!({"bytes": 2048, "ratio": 0.5}));
+        assert_eq!(
+            serde_json::to_value(&output).unwrap(),
+            json!({
+                "items": [1],
+                "limit": 0,
+                "total": 1,
+                "meta": {"status": "online", "max_limit": 100, "sum": {"bytes": 2048, "ratio": 0.5}}
+            })
+        );
+
+        let mut meta = Map::new();
+        meta.insert("filter".into(), json!(null));
+        let output = output.with_meta(meta);
+        assert_eq!(
+            serde_json::to_value(&output).unwrap()["meta"],
+            json!({"filter": null})
+        );
+    }
+
+    #[test]
+    fn test_output_profile() {
+        let output = QueryOutput::from(vec![1, 2]).total(5).limit(2).offset(0);
+        let default = serde_json::to_string(&output).unwrap();
+        assert_eq!(
+            serde_json::to_string(&output.serialize_with(OutputProfile::new())).unwrap(),
+            default
+        );
+        assert_eq!(
+            default,
+            r#"{"items":[1,2],"limit":2,"total":5,"offset":0,"total_pages":3}"#
+        );
+
+        let camel = OutputProfile::new().camel_case(true).total_count(true);
+        let json = serde_json::to_string(&output.serialize_with(camel)).unwrap();
+        assert_eq!(
+            json,
+            r#"{"items":[1,2],"limit":2,"totalCount":5,"offset":0,"totalPages":3}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<QueryOutput<u32>>(&json).unwrap(),
+            output
+        );
+
+        let snake = OutputProfile::new().total_count(true);
+        let json = serde_json::to_string(&output.serialize_with(snake)).unwrap();
+        assert_eq!(
+            json,
+            r#"{"items":[1,2],"limit":2,"total_count":5,"offset":0,"total_pages":3}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<QueryOutput<u32>>(&json).unwrap(),
+            output
+        );
+
+        let output = QueryOutput::<u32>::empty().offset(0).with_meta(Map::new());
+        let skip_zero = OutputProfile::new().skip_zero(true);
+        let json = serde_json::to_string(&output.serialize_with(skip_zero)).unwrap();
+        assert_eq!(json, r#"{"items":[]}"#);
+        assert_eq!(
+            serde_json::from_str::<QueryOutput<u32>>(&json).unwrap(),
+            QueryOutput::empty()
+        );
+    }
+
+    fn query_input(query: &str) -> QueryInput {
+        actix_web::web::Query::<QueryInput>::from_query(query)
+            .unwrap()
+            .into_inner()
+    }
+
+    #[test]
+    fn test_query_input_defaults() {
+        let input = query_input("");
+        assert_eq!(input, QueryInput::default());
+        assert_eq!((input.limit, input.offset), (20, 0));
+        assert!(input.validate(100).is_ok());
+
+        let input = query_input("limit=5&offset=10&keyword=cam&sort=name&order=desc");
+        assert_eq!(input.order, Some(SortOrder::Desc));
+        let output = input.to_output_shell::<u32>();
+        assert_eq!((output.limit, output.offset), (5, Some(10)));
+    }
+
+    #[test]
+    fn test_query_input_validate() {
+        let err = query_input("limit=101").validate(100).unwrap_err();
+        assert_eq!(err.err.code(), 1012);
+        assert_eq!(err.field.as_deref(), Some("limit"));
+        assert!(query_input("limit=0").validate(100).is_err());
+        assert!(query_input("limit=100").validate(100).is_ok());
+    }
+
+    #[test]
+    fn test_sanitize_keyword() {
+        let mut input = query_input("keyword=%20%20%E8%B7%AF%E7%94%B1%E5%99%A8%20");
+        input.sanitize_keyword();
+        assert_eq!(input.keyword.as_deref(), Some("路由器"));
+
+        let mut input = query_input("keyword=%20%20");
+        input.sanitize_keyword();
+        assert_eq!(input.keyword, None);
+
+        let mut input = QueryInput {
+            keyword: Some("字".repeat(100)),
+            ..QueryInput::default()
+        };
+        input.sanitize_keyword();
+        assert_eq!(input.keyword.unwrap().chars().count(), MAX_KEYWORD_CHARS);
+    }
+
+    const SORTABLE: &[&str] = &["name", "created_at", "id"];
+
+    fn sort_field(field: &str, direction: SortOrder) -> SortField {
+        SortField {
+            field: field.to_string(),
+            direction,
+        }
+    }
+
+    #[test]
+    fn test_sort_spec_parse() {
+        let fields = SortSpec::parse("name, -created_at,+id", SORTABLE).unwrap();
+        assert_eq!(
+            fields,
+            vec![
+                sort_field("name", SortOrder::Asc),
+                sort_field("created_at", SortOrder::Desc),
+                sort_field("id", SortOrder::Asc),
+            ]
+        );
+        assert_eq!(
+            SortSpec::to_sql_order_by(&fields),
+            "name ASC, created_at DESC, id ASC"
+        );
+
+        assert!(SortSpec::parse("", SORTABLE).unwrap().is_empty());
+        assert!(SortSpec::parse(" , ", SORTABLE).unwrap().is_empty());
+        assert_eq!(SortSpec::to_sql_order_by(&[]), "");
+    }
+
+    #[test]
+    fn test_sort_spec_unknown_field() {
+        let err = SortSpec::parse("name,-password;drop table", SORTABLE).unwrap_err();
+        assert_eq!(err.err.code(), 1012);
+        assert_eq!(err.field.as_deref(), Some("sort"));
+        assert!(err.desc.contains("name, created_at, id"), "{}", err.desc);
+    }
+
+    #[test]
+    fn test_sort_spec_duplicates_and_cap() {
+        let fields = SortSpec::parse("-name,name,id,-id", SORTABLE).unwrap();
+        assert_eq!(
+            fields,
+            vec![
+                sort_field("name", SortOrder::Desc),
+                sort_field("id", SortOrder::Asc)
+            ]
+        );
+
+        let allowed = ["a", "b", "c", "d", "e", "f"];
+        assert!(SortSpec::parse("a,b,c,d,e", &allowed).is_ok());
+        assert!(SortSpec::parse("a,b,c,d,e,f", &allowed).is_err());
+    }
+
+    #[test]
+    fn test_sort_spec_compare() {
+        let fields = SortSpec::parse("-name,id", &["name", "id"]).unwrap();
+        let mut items = vec![("a", 2), ("b", 1), ("a", 1)];
+        items.sort_by(|a, b| {
+            SortSpec::compare(&fields, a, b, |a, b, field| match field {
+                "name" => a.0.cmp(b.0),
+                _ => a.1.cmp(&b.1),
+            })
+        });
+        assert_eq!(items, vec![("b", 1), ("a", 1), ("a", 2)]);
+    }
+
+    fn paging_headers(uri: &str, output: QueryOutput<u32>) -> actix_web::HttpResponse {
+        let req = actix_web::test::TestRequest::get()
+            .uri(uri)
+            .to_http_request();
+        let mut builder = actix_web::HttpResponse::Ok();
+        output.apply_headers(&mut builder, &req);
+        builder.finish()
+    }
+
+    fn header_value<'a>(res: &'a actix_web::HttpResponse, name: &str) -> &'a str {
+        res.headers().get(name).unwrap().to_str().unwrap()
+    }
+
+    #[test]
+    fn test_apply_headers() {
+        let output = QueryOutput::default().total(95).limit(20).offset(40);
+        let res = paging_headers("/devices?keyword=%E8%B7%AF&offset=40&limit=20", output);
+        assert_eq!(header_value(&res, "x-total-count"), "95");
+        assert_eq!(header_value(&res, "x-limit"), "20");
+        assert_eq!(header_value(&res, "x-offset"), "40");
+        assert_eq!(
+            header_value(&res, "link"),
+            "</devices?keyword=%E8%B7%AF&offset=60&limit=20>; rel=\"next\", \
+             </devices?keyword=%E8%B7%AF&offset=20&limit=20>; rel=\"prev\", \
+             </devices?keyword=%E8%B7%AF&offset=0&limit=20>; rel=\"first\", \
+             </devices?keyword=%E8%B7%AF&offset=80&limit=20>; rel=\"last\""
+        );
+
+        let output = QueryOutput::default().total(95).limit(20).offset(80);
+        let res = paging_headers("/devices?offset=80&limit=20", output);
+        let link = header_value(&res, "link");
+        assert!(!link.contains("rel=\"next\""), "{}", link);
+        assert!(link.contains("</devices?offset=60&limit=20>; rel=\"prev\""));
+    }
+
+    #[test]
+    fn test_ascii_url() {
+        assert_eq!(
+            ascii_url("/devices?keyword=路"),
+            "/devices?keyword=%E8%B7%AF"
+        );
+    }
+
+    #[test]
+    fn test_paginate_vec() {
+        let items: Vec<u32> = (1..=10).collect();
+        let cases = [
+            (3, 4, vec![4, 5, 6, 7]),
+            (10, 4, vec![]),
+            (25, 4, vec![]),
+            (8, 5, vec![9, 10]),
+            (7, 0, vec![8, 9, 10]),
+        ];
+        for (offset, limit, expected) in cases {
+            let output = QueryOutput::paginate_vec(items.clone(), offset, limit);
+            assert_eq!(output.items, expected);
+            assert_eq!(output.total, 10);
+            assert_eq!(output.limit, limit);
+            assert_eq!(output.offset, Some(offset));
+            assert_eq!(QueryOutput::paginate_slice(&items, offset, limit), output);
+        }
+        let output = QueryOutput::paginate_vec(items, 8, 5);
+        assert_eq!(output.total_pages, Some(2));
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let items: Vec<u32> = (1..=10).collect();
+        let page =
+            |offset, policy| QueryOutput::paginate_vec_with(items.clone(), offset, 4, policy);
+
+        // offset 9是最后一项，不算超出范围
+        for policy in [
+            OutOfRange::EmptyWithFlag,
+            OutOfRange::ClampToLastPage,
+            OutOfRange::Reject,
+        ] {
+            let output = page(9, policy).unwrap();
+            assert_eq!(output.items, vec![10]);
+            assert_eq!(output.meta, None);
+        }
+
+        let output = page(10, OutOfRange::EmptyWithFlag).unwrap();
+        assert!(output.items.is_empty());
+        assert_eq!(output.offset, Some(10));
+        assert_eq!(output.meta.unwrap()["out_of_range"], true);
+
+        let output = page(10, OutOfRange::ClampToLastPage).unwrap();
+        assert_eq!(output.items, vec![9, 10]);
+        assert_eq!(output.offset, Some(8));
+        assert_eq!(output.meta, None);
+
+        let err = page(10, OutOfRange::Reject).unwrap_err();
+        assert_eq!(err.err, InvalidInput);
+        assert_eq!(err.field.as_deref(), Some("offset"));
+        assert_eq!(err.extra.unwrap()["max_offset"], 8);
+
+        // 没有数据时offset 0不算超出范围
+        let output =
+            QueryOutput::<u32>::paginate_vec_with(vec![], 0, 4, OutOfRange::Reject).unwrap();
+        assert_eq!(output.meta, None);
+        assert_eq!(
+            QueryOutput::<u32>::paginate_vec_with(vec![], 4, 4, OutOfRange::ClampToLastPage)
+                .unwrap()
+                .offset,
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let output = QueryOutput::from(vec![1, 2]).total(41).limit(2);
+        let plain: QueryOutputWithSummary<u32, BTreeMap<String, usize>> = output.clone().into();
+        assert_eq!(
+            serde_json::to_value(&plain).unwrap(),
+            serde_json::to_value(&output).unwrap()
+        );
+
+        let statuses = ["online", "offline", "online", "unknown", "online"];
+        let summary = summarize_by_key(&statuses, |status| *status);
+        assert_eq!(
+            summary,
+            BTreeMap::from([
+                ("offline".to_string(), 1),
+                ("online".to_string(), 3),
+                ("unknown".to_string(), 1),
+            ])
+        );
+        let with_summary = output.with_summary(summary);
+        let json = serde_json::to_value(&with_summary).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "items": [1, 2],
+                "limit": 2,
+                "total": 41,
+                "total_pages": 21,
+                "summary": {"offline": 1, "online": 3, "unknown": 1}
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<QueryOutputWithSummary<u32, BTreeMap<String, usize>>>(json)
+                .unwrap(),
+            with_summary
+        );
+        assert!(summarize_by_key(&[] as &[u32], |id| *id).is_empty());
+    }
+
+    #[test]
+    fn test_serialize_optional_fields() {
+        let output = QueryOutput::default().items(vec![1]);
+        assert_eq!(
+            serde_json::to_value(&output).unwrap(),
+            json!({"items": [1], "limit": 0, "total": 1})
+        );
+
+        let output = QueryOutput::default()
+            .items(vec![1, 2])
+            .total(5)
+            .limit(2)
+            .offset(2)
+            .page(2);
+        assert_eq!(
+            serde_json::to_value(&output).unwrap(),
+            json!({"items": [1, 2], "limit": 2, "total": 5, "offset": 2, "page": 2, "total_pages": 3})
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use super::{QueryOutput, SortField};
+use crate::define::{InvalidInput, Result};
+use crate::middleware::current_locale;
+use std::cmp::Ordering;
+
+/// 内存排序中某个字段的值
+///
+/// 比较规则：数字（Int和Float之间按数值比较）< String < DateTime < Null。
+/// Float使用total_cmp，NaN排在所有数字之后。sort_items中Null不受排序方向影响，总是排在最后
+#[derive(Debug, Clone)]
+pub enum SortKey {
+    String(String),
+    Int(i64),
+    Float(f64),
+    #[cfg(feature = "chrono")]
+    DateTime(chrono::DateTime<chrono::Utc>),
+    Null,
+}
+
+impl SortKey {
+    fn rank(&self) -> u8 {
+        match self {
+            SortKey::Int(_) | SortKey::Float(_) => 0,
+            SortKey::String(_) => 1,
+            #[cfg(feature = "chrono")]
+            SortKey::DateTime(_) => 2,
+            SortKey::Null => 3,
+        }
+    }
+}
+
+impl<T: Into<SortKey>> From<Option<T>> for SortKey {
+    fn from(value: Option<T>) -> Self {
+        value.map(Into::into).unwrap_or(SortKey::Null)
+    }
+}
+
+impl From<String> for SortKey {
+    fn from(value: String) -> Self {
+        SortKey::String(value)
+    }
+}
+
+impl From<&str> for SortKey {
+    fn from(value: &str) -> Self {
+        SortKey::String(value.to_string())
+    }
+}
+
+impl From<i64> for SortKey {
+    fn from(value: i64) -> Self {
+        SortKey::Int(value)
+    }
+}
+
+impl From<i32> for SortKey {
+    fn from(value: i32) -> Self {
+        SortKey::Int(i64::from(value))
+    }
+}
+
+impl From<f64> for SortKey {
+    fn from(value: f64) -> Self {
+        SortKey::Float(value)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for SortKey {
+    fn from(value: chrono::DateTime<chrono::Utc>) -> Self {
+        SortKey::DateTime(value)
+    }
+}
+
+impl Ord for SortKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (SortKey::Int(a), SortKey::Int(b)) => a.cmp(b),
+            (SortKey::Float(a), SortKey::Float(b)) => a.total_cmp(b),
+            // 数值相等时Int排在Float之前，保证Ord与Eq一致
+            (SortKey::Int(a), SortKey::Float(b)) => (*a as f64).total_cmp(b).then(Ordering::Less),
+            (SortKey::Float(a), SortKey::Int(b)) => {
+                a.total_cmp(&(*b as f64)).then(Ordering::Greater)
+            }
+            (SortKey::String(a), SortKey::String(b)) => a.cmp(b),
+            #[cfg(feature = "chrono")]
+            (SortKey::DateTime(a), SortKey::DateTime(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for SortKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SortKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortKey {}
+
+/// 按SortSpec::parse的结果对内存中的数据排序，排序是稳定的
+///
+/// accessor返回某一项在字段上的值，返回None表示不支持该字段，此时返回InvalidInput且items保持不变
+///
+/// # Example
+///
+/// ```ignore
+/// let fields = SortSpec::parse(input.sort.as_deref().unwrap_or(""), &["name", "created_at"])?;
+/// sort_items(&mut devices, &fields, |device, field| match field {
+///     "name" => Some(device.name.as_str().into()),
+///     "created_at" => Some(device.created_at.into()),
+///     _ => None,
+/// })?;
+/// ```
+pub fn sort_items<T, F>(items: &mut Vec<T>, spec: &[SortField], accessor: F) -> Result<()>
+where
+    F: Fn(&T, &str) -> Option<SortKey>,
+{
+    if spec.is_empty() || items.is_empty() {
+        return Ok(());
+    }
+    let keys = items
+        .iter()
+        .map(|item| {
+            spec.iter()
+                .map(|sort| accessor(item, &sort.field).ok_or_else(|| unknown_field(&sort.field)))
+                .collect::<Result<Vec<SortKey>>>()
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    order.sort_by(|&a, &b| {
+        spec.iter()
+            .zip(keys[a].iter().zip(keys[b].iter()))
+            .map(|(sort, (a, b))| match (a, b) {
+                (SortKey::Null, SortKey::Null) => Ordering::Equal,
+                (SortKey::Null, _) => Ordering::Greater,
+                (_, SortKey::Null) => Ordering::Less,
+                (a, b) => sort.apply(a.cmp(b)),
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    let mut slots: Vec<Option<T>> = items.drain(..).map(Some).collect();
+    items.extend(order.into_iter().filter_map(|index| slots[index].take()));
+    Ok(())
+}
+
+fn unknown_field(field: &str) -> crate::define::ExtraDescError {
+    let desc = format!(
+        "{}`{}`{}",
+        current_locale().pick("不支持按", "unsupported sort field "),
+        field,
+        current_locale().pick("排序", "")
+    );
+    InvalidInput.from_desc(desc).with_field("sort")
+}
+
+impl<T> QueryOutput<T> {
+    /// 按排序字段对本页数据排序，见sort_items
+    pub fn sort_by_spec<F>(mut self, spec: &[SortField], accessor: F) -> Result<Self>
+    where
+        F: Fn(&T, &str) -> Option<SortKey>,
+    {
+        sort_items(&mut self.items, spec, accessor)?;
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::SortSpec;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Device {
+        id: i64,
+        name: &'static str,
+        score: Option<f64>,
+    }
+
+    fn device(id: i64, name: &'static str, score: Option<f64>) -> Device {
+        Device { id, name, score }
+    }
+
+    fn accessor(device: &Device, field: &str) -> Option<SortKey> {
+        match field {
+            "id" => Some(device.id.into()),
+            "name" => Some(device.name.into()),
+            "score" => Some(device.score.into()),
+            _ => None,
+        }
+    }
+
+    fn ids(items: &[Device]) -> Vec<i64> {
+        items.iter().map(|device| device.id).collect()
+    }
+
+    #[test]
+    fn test_mixed_directions() {
+        let mut items = vec![
+            device(1, "b", None),
+            device(2, "a", None),
+            device(3, "b", None),
+            device(4, "a", None),
+        ];
+        let spec = SortSpec::parse("name,-id", &["name", "id"]).unwrap();
+        sort_items(&mut items, &spec, accessor).unwrap();
+        assert_eq!(ids(&items), vec![4, 2, 3, 1]);
+    }
+
+    #[test]
+    fn test_null_last() {
+        let items = vec![
+            device(1, "a", None),
+            device(2, "a", Some(1.5)),
+            device(3, "a", Some(-2.0)),
+            device(4, "a", None),
+        ];
+        let asc = SortSpec::parse("score", &["score"]).unwrap();
+        let mut sorted = items.clone();
+        sort_items(&mut sorted, &asc, accessor).unwrap();
+        assert_eq!(ids(&sorted), vec![3, 2, 1, 4]);
+
+        let desc = SortSpec::parse("-score", &["score"]).unwrap();
+        let mut sorted = items;
+        sort_items(&mut sorted, &desc, accessor).unwrap();
+        assert_eq!(ids(&sorted), vec![2, 3, 1, 4]);
+
+        assert!(SortKey::Int(2) < SortKey::Float(2.5));
+        assert!(SortKey::Float(1e18) < SortKey::String(String::new()));
+        assert!(SortKey::String("z".into()) < SortKey::Null);
+    }
+
+    #[test]
+    fn test_stable() {
+        let mut items: Vec<Device> = (1..=6).map(|id| device(id, "same", Some(1.0))).collect();
+        let spec = SortSpec::parse("-name,score", &["name", "score"]).unwrap();
+        sort_items(&mut items, &spec, accessor).unwrap();
+        assert_eq!(ids(&items), vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        let mut items = vec![device(2, "b", None), device(1, "a", None)];
+        let spec = SortSpec::parse("name,color", &["name", "color"]).unwrap();
+        let err = sort_items(&mut items, &spec, accessor).unwrap_err();
+        assert_eq!(err.err, InvalidInput);
+        assert!(err.desc.contains("color"));
+        assert_eq!(ids(&items), vec![2, 1]);
+
+        let output = QueryOutput::from(items)
+            .sort_by_spec(&spec[..1], accessor)
+            .unwrap();
+        assert_eq!(ids(&output.items), vec![1, 2]);
+    }
+}
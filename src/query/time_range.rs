@@ -0,0 +1,183 @@
+use crate::define::{InvalidInput, Result};
+use crate::middleware::current_locale;
+use chrono::{DateTime, Duration, Utc};
+use std::convert::TryFrom;
+
+#[derive(Deserialize)]
+struct RawTimeRange {
+    #[serde(default)]
+    start: Option<String>,
+    #[serde(default)]
+    end: Option<String>,
+}
+
+/// 解析RFC 3339时间或秒级时间戳，统一转换为UTC
+fn parse_time(value: &str) -> std::result::Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<i64>() {
+        return DateTime::from_timestamp(secs, 0)
+            .ok_or_else(|| format!("timestamp out of range: {}", value));
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|e| format!("invalid time `{}`: {}", value, e))
+}
+
+/// 查询参数中的start、end时间范围，支持RFC 3339和秒级时间戳，统一转换为UTC
+///
+/// 两端都没有传时默认为最近24小时，只传一端时另一端不限
+///
+/// # Example
+///
+/// ```ignore
+/// async fn events(range: web::Query<TimeRange>) -> HttpResult<Json<QueryOutput<Event>>> {
+///     range.validate(Duration::days(7))?;
+///     let (start, end) = (range.start(), range.end());
+///     ...
+/// }
+/// ```
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "RawTimeRange")]
+pub struct TimeRange {
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<RawTimeRange> for TimeRange {
+    type Error = String;
+
+    fn try_from(raw: RawTimeRange) -> std::result::Result<Self, Self::Error> {
+        let parse = |value: Option<String>| {
+            value
+                .filter(|value| !value.trim().is_empty())
+                .map(|value| parse_time(&value))
+                .transpose()
+        };
+        match (parse(raw.start)?, parse(raw.end)?) {
+            (None, None) => Ok(TimeRange::default()),
+            (start, end) => Ok(TimeRange { start, end }),
+        }
+    }
+}
+
+impl Default for TimeRange {
+    /// 最近24小时
+    fn default() -> Self {
+        TimeRange::last(Duration::hours(24))
+    }
+}
+
+impl TimeRange {
+    pub fn new(start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) -> Self {
+        TimeRange { start, end }
+    }
+
+    /// 截止到当前时间的一段时间
+    pub fn last(span: Duration) -> Self {
+        let end = Utc::now();
+        TimeRange {
+            start: Some(end - span),
+            end: Some(end),
+        }
+    }
+
+    pub fn start(&self) -> Option<DateTime<Utc>> {
+        self.start
+    }
+
+    pub fn end(&self) -> Option<DateTime<Utc>> {
+        self.end
+    }
+
+    /// 检查start不晚于end，且时间跨度不超过max_span
+    ///
+    /// 没有end时按当前时间计算跨度；没有start时跨度不限，同样视为超出
+    pub fn validate(&self, max_span: Duration) -> Result<()> {
+        let locale = current_locale();
+        if let (Some(start), Some(end)) = (self.start, self.end) {
+            if start > end {
+                let desc = locale.pick("开始时间晚于结束时间", "start is after end");
+                return Err(InvalidInput.from_desc(desc).with_field("start"));
+            }
+        }
+        let span = self
+            .start
+            .map(|start| self.end.unwrap_or_else(Utc::now) - start);
+        if span.is_none_or(|span| span > max_span) {
+            let desc = format!(
+                "{}{}{}",
+                locale.pick("时间范围不能超过", "time range must not exceed "),
+                max_span.num_seconds(),
+                locale.pick("秒", " seconds")
+            );
+            return Err(InvalidInput.from_desc(desc).with_field("start"));
+        }
+        Ok(())
+    }
+
+    /// 判断时间是否在范围内，包含start，不包含end
+    pub fn contains(&self, ts: DateTime<Utc>) -> bool {
+        self.start.is_none_or(|start| ts >= start) && self.end.is_none_or(|end| ts < end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str) -> std::result::Result<TimeRange, String> {
+        actix_web::web::Query::<TimeRange>::from_query(query)
+            .map(|range| range.into_inner())
+            .map_err(|e| e.to_string())
+    }
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_input_formats() {
+        let range = parse("start=2024-05-01T08:00:00%2B08:00&end=1714564800").unwrap();
+        assert_eq!(range.start(), Some(utc("2024-05-01T00:00:00Z")));
+        assert_eq!(range.end(), Some(utc("2024-05-01T12:00:00Z")));
+        assert!(range.validate(Duration::days(1)).is_ok());
+        assert!(range.contains(utc("2024-05-01T00:00:00Z")));
+        assert!(!range.contains(utc("2024-05-01T12:00:00Z")));
+
+        let range = parse("start=1714521600").unwrap();
+        assert_eq!(range.end(), None);
+        assert!(range.contains(utc("2030-01-01T00:00:00Z")));
+
+        assert!(parse("start=yesterday").is_err());
+    }
+
+    #[test]
+    fn test_reversed_range() {
+        let range = parse("start=2024-05-02T00:00:00Z&end=2024-05-01T00:00:00Z").unwrap();
+        let err = range.validate(Duration::days(7)).unwrap_err();
+        assert_eq!(err.err.code(), 1012);
+        assert_eq!(err.desc, "开始时间晚于结束时间");
+    }
+
+    #[test]
+    fn test_over_cap() {
+        let range = parse("start=2024-05-01T00:00:00Z&end=2024-05-09T00:00:00Z").unwrap();
+        let err = range.validate(Duration::days(7)).unwrap_err();
+        assert_eq!(err.desc, "时间范围不能超过604800秒");
+        assert!(range.validate(Duration::days(8)).is_ok());
+
+        let range = parse("end=2024-05-09T00:00:00Z").unwrap();
+        assert!(range.validate(Duration::days(7)).is_err());
+    }
+
+    #[test]
+    fn test_default_window() {
+        let range = parse("").unwrap();
+        let (start, end) = (range.start().unwrap(), range.end().unwrap());
+        assert_eq!(end - start, Duration::hours(24));
+        assert!(Utc::now() - end < Duration::minutes(1));
+        assert!(range.validate(Duration::days(1)).is_ok());
+    }
+}
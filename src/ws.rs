@@ -0,0 +1,103 @@
+use super::define::*;
+use actix_http::ws::{CloseCode, CloseReason};
+
+/// WebSocket关闭帧description的最大字节数
+const MAX_REASON_LEN: usize = 123;
+
+/// 关闭帧description中的内容
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CloseDescription {
+    pub code: u16,
+    pub reason: String,
+}
+
+#[allow(non_upper_case_globals)]
+fn close_code(err: &Error) -> CloseCode {
+    match *err {
+        PermissionDenied | Unauthorized | TokenMissing | TokenMalformed | TokenExpired
+        | TokenInvalid => CloseCode::Policy,
+        InvalidData | InvalidMessageData | InvalidInput => CloseCode::Invalid,
+        PayloadTooLarge => CloseCode::Size,
+        _ => CloseCode::Error,
+    }
+}
+
+/// 把错误转换为WebSocket关闭原因
+///
+/// description为`{"code":..,"reason":..}`格式的json，超过123字节时按字符截断reason
+///
+/// # Example
+///
+/// ```ignore
+/// session.close(Some(close_with_error(&DeviceNotFound.from_desc("设备不存在")))).await?;
+/// ```
+pub fn close_with_error(err: &ExtraDescError) -> CloseReason {
+    let mut reason = err.desc.clone();
+    if reason.len() > MAX_REASON_LEN {
+        let mut end = MAX_REASON_LEN;
+        while !reason.is_char_boundary(end) {
+            end -= 1;
+        }
+        reason.truncate(end);
+    }
+    let description = loop {
+        let description = CloseDescription {
+            code: err.err.code(),
+            reason,
+        };
+        let json = serde_json::to_string(&description).unwrap_or_default();
+        if json.len() <= MAX_REASON_LEN {
+            break json;
+        }
+        reason = description.reason;
+        reason.pop();
+    };
+    CloseReason {
+        code: close_code(&err.err),
+        description: Some(description),
+    }
+}
+
+/// 解析close_with_error生成的关闭原因，供客户端和测试使用
+pub fn parse_close_reason(reason: &CloseReason) -> Option<CloseDescription> {
+    serde_json::from_str(reason.description.as_deref()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_mapping() {
+        let reason = close_with_error(&PermissionDenied.from_desc("无权限"));
+        assert_eq!(reason.code, CloseCode::Policy);
+        let reason = close_with_error(&InvalidData.from_desc("数据格式错误"));
+        assert_eq!(reason.code, CloseCode::Invalid);
+        let reason = close_with_error(&DeviceNotFound.from_desc("设备不存在"));
+        assert_eq!(reason.code, CloseCode::Error);
+        assert_eq!(
+            parse_close_reason(&reason),
+            Some(CloseDescription {
+                code: 4004,
+                reason: "设备不存在".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_truncate_on_char_boundary() {
+        let desc = "设备连接异常".repeat(20);
+        let reason = close_with_error(&ReceiveUnexpectedEof.from_desc(desc.clone()));
+        let description = reason.description.as_deref().unwrap();
+        assert!(description.len() <= MAX_REASON_LEN);
+        let parsed = parse_close_reason(&reason).unwrap();
+        assert_eq!(parsed.code, 4011);
+        assert!(!parsed.reason.is_empty());
+        assert!(desc.starts_with(&parsed.reason));
+
+        let desc = "\"".repeat(200);
+        let reason = close_with_error(&DeviceNotFound.from_desc(desc));
+        assert!(reason.description.as_deref().unwrap().len() <= MAX_REASON_LEN);
+        assert!(parse_close_reason(&reason).is_some());
+    }
+}